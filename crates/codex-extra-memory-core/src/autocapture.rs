@@ -1,27 +1,171 @@
-use crate::types::{AutoCaptureCandidate, AutoCaptureConfig, MemoryCategory};
-use crate::utils::{is_probably_secret, normalize_for_hash, sha256};
+use crate::llm::LlmCaptureProposal;
+use crate::locale::{Language, languages_for_message, patterns};
+use crate::secrets::SecretScanner;
+use crate::types::{
+    AutoCaptureCandidate, AutoCaptureConfig, AutoCaptureRule, CaptureAction, MemoryCategory,
+    ScopeTarget, SecretPolicy,
+};
+use crate::utils::{normalize_for_hash, sha256};
 use regex::Regex;
 use serde_json::Value;
 use std::collections::HashSet;
 use std::sync::OnceLock;
 
-fn explicit_remember_regex() -> &'static Regex {
+fn no_longer_regex() -> &'static Regex {
     static REGEX: OnceLock<Regex> = OnceLock::new();
     REGEX.get_or_init(|| {
-        Regex::new(r"(?i)(?:please\s+)?remember(?:\s+that)?\s+(.+)").expect("valid regex")
+        Regex::new(
+            r"(?i)^(?:actually,?\s+)?(?:we|i)\s+(?:no\s+longer\s+(.+?)(?:\s+any\s?more)?|(?:don'?t|do\s+not)\s+(.+?)\s+any\s?more)[.!]?$",
+        )
+        .expect("valid regex")
     })
 }
 
-fn explicit_preference_regex() -> &'static Regex {
+fn switched_regex() -> &'static Regex {
     static REGEX: OnceLock<Regex> = OnceLock::new();
     REGEX.get_or_init(|| {
-        Regex::new(r"(?i)(?:my\s+preference\s+is|i\s+prefer)\s+(.+)").expect("valid regex")
+        Regex::new(
+            r"(?i)^(?:actually,?\s+)?(?:we|i)(?:'ve|\s+have)?\s+(?:switched|moved|migrated)\s+from\s+(.+?)\s+to\s+(.+)$",
+        )
+        .expect("valid regex")
     })
 }
 
-fn assistant_marker_regex() -> &'static Regex {
+fn instead_of_regex() -> &'static Regex {
     static REGEX: OnceLock<Regex> = OnceLock::new();
-    REGEX.get_or_init(|| Regex::new(r"(?i)^(?:memory|remember)\s*:\s*(.+)$").expect("valid regex"))
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r"(?i)^actually,?\s+(?:we\s+|i\s+)?(?:use|prefer)\s+(.+?),?\s+(?:not|instead\s+of)\s+(.+)$",
+        )
+        .expect("valid regex")
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct CompiledCaptureRule {
+    pub regex: Regex,
+    pub role: Option<String>,
+    pub category: Option<MemoryCategory>,
+    pub scope: Option<ScopeTarget>,
+    pub tag: Option<String>,
+}
+
+pub fn compile_capture_rule(rule: &AutoCaptureRule) -> Result<CompiledCaptureRule, String> {
+    if rule.pattern.trim().is_empty() {
+        return Err("pattern must not be empty".to_string());
+    }
+    let regex = Regex::new(&rule.pattern).map_err(|error| format!("invalid pattern: {error}"))?;
+    let applies_to = match rule.role.trim().to_lowercase().as_str() {
+        "" | "any" => None,
+        "user" => Some("user".to_string()),
+        "assistant" => Some("assistant".to_string()),
+        other => {
+            return Err(format!(
+                "invalid role '{other}'. Allowed: user, assistant, any"
+            ));
+        }
+    };
+    let category = rule
+        .category
+        .as_deref()
+        .map(str::parse::<MemoryCategory>)
+        .transpose()?;
+    let scope = rule
+        .scope
+        .as_deref()
+        .map(str::parse::<ScopeTarget>)
+        .transpose()?;
+    let tag = rule
+        .tag
+        .as_deref()
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string);
+
+    Ok(CompiledCaptureRule {
+        regex,
+        role: applies_to,
+        category,
+        scope,
+        tag,
+    })
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CaptureRules {
+    pub compiled: Vec<CompiledCaptureRule>,
+    pub errors: Vec<(usize, String)>,
+}
+
+impl CaptureRules {
+    #[must_use]
+    pub fn error_for(&self, index: usize) -> Option<&str> {
+        self.errors
+            .iter()
+            .find(|(failed, _)| *failed == index)
+            .map(|(_, error)| error.as_str())
+    }
+
+    #[must_use]
+    pub fn error_messages(&self) -> Vec<String> {
+        self.errors
+            .iter()
+            .map(|(index, error)| format!("autoCapture.rules[{index}]: {error}"))
+            .collect()
+    }
+}
+
+#[must_use]
+pub fn compile_capture_rules(rules: &[AutoCaptureRule]) -> CaptureRules {
+    let mut compiled = CaptureRules::default();
+    for (index, rule) in rules.iter().enumerate() {
+        match compile_capture_rule(rule) {
+            Ok(rule) => compiled.compiled.push(rule),
+            Err(error) => compiled.errors.push((index, error)),
+        }
+    }
+    compiled
+}
+
+type ExtractedCapture = (
+    String,
+    MemoryCategory,
+    String,
+    Option<ScopeTarget>,
+    Option<String>,
+);
+
+fn extract_rule_matches(
+    role: &str,
+    text: &str,
+    languages: &[Language],
+    rules: &[CompiledCaptureRule],
+) -> Vec<ExtractedCapture> {
+    let mut results = Vec::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        for rule in rules {
+            if rule.role.as_deref().is_some_and(|r| r != role) {
+                continue;
+            }
+            let Some(caps) = rule.regex.captures(line) else {
+                continue;
+            };
+            let Some(found) = caps.get(1).or_else(|| caps.get(0)) else {
+                continue;
+            };
+            let body = found.as_str().to_string();
+            results.push((
+                body.clone(),
+                rule.category
+                    .unwrap_or_else(|| infer_category(&body, languages)),
+                format!("custom rule /{}/", rule.regex.as_str()),
+                rule.scope,
+                rule.tag.clone(),
+            ));
+            break;
+        }
+    }
+    results
 }
 
 fn extract_text_from_message_content(content: &Value) -> String {
@@ -60,96 +204,152 @@ fn cleanup_text(value: &str) -> String {
         .to_string()
 }
 
-fn infer_category(text: &str) -> MemoryCategory {
+fn infer_category(text: &str, languages: &[Language]) -> MemoryCategory {
     let lower = text.to_lowercase();
-    if ["prefer", "preference", "like", "dislike"]
-        .iter()
-        .any(|needle| lower.contains(needle))
-    {
+    let has_keyword = |select: fn(Language) -> &'static [&'static str]| {
+        languages
+            .iter()
+            .chain(std::iter::once(&Language::En))
+            .any(|language| {
+                select(*language)
+                    .iter()
+                    .any(|needle| lower.contains(needle))
+            })
+    };
+
+    if has_keyword(|language| patterns(language).preference_keywords) {
         return MemoryCategory::Preference;
     }
 
-    if [
-        "always", "usually", "workflow", "run", "command", "format", "style",
-    ]
-    .iter()
-    .any(|needle| lower.contains(needle))
-    {
+    if has_keyword(|language| patterns(language).workflow_keywords) {
         return MemoryCategory::Workflow;
     }
 
-    if [
-        "never", "must", "mustn't", "do not", "don't", "avoid", "required", "forbid",
-    ]
-    .iter()
-    .any(|needle| lower.contains(needle))
-    {
+    if has_keyword(|language| patterns(language).constraint_keywords) {
         return MemoryCategory::Constraint;
     }
 
     MemoryCategory::Other
 }
 
-fn extract_user_explicit(text: &str) -> Vec<(String, MemoryCategory, String)> {
+fn extract_user_explicit(
+    text: &str,
+    languages: &[Language],
+) -> Vec<(String, MemoryCategory, String)> {
     let mut results = Vec::new();
     for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
-        if let Some(found) = explicit_remember_regex()
-            .captures(line)
-            .and_then(|caps| caps.get(1))
-        {
-            let body = found.as_str().to_string();
-            results.push((
-                body.clone(),
-                infer_category(&body),
-                "explicit remember statement".to_string(),
-            ));
-            continue;
-        }
+        for language in languages {
+            let set = patterns(*language);
+            if let Some(found) = set.remember.captures(line).and_then(|caps| caps.get(1)) {
+                let body = found.as_str().to_string();
+                results.push((
+                    body.clone(),
+                    infer_category(&body, languages),
+                    format!("explicit remember statement ({})", language.as_str()),
+                ));
+                break;
+            }
 
-        if let Some(found) = explicit_preference_regex()
-            .captures(line)
-            .and_then(|caps| caps.get(1))
-        {
-            results.push((
-                found.as_str().to_string(),
-                MemoryCategory::Preference,
-                "explicit preference statement".to_string(),
-            ));
+            if let Some(found) = set.preference.captures(line).and_then(|caps| caps.get(1)) {
+                results.push((
+                    found.as_str().to_string(),
+                    MemoryCategory::Preference,
+                    format!("explicit preference statement ({})", language.as_str()),
+                ));
+                break;
+            }
         }
     }
     results
 }
 
-fn extract_assistant_marked(text: &str) -> Vec<(String, MemoryCategory, String)> {
+fn extract_assistant_marked(
+    text: &str,
+    languages: &[Language],
+) -> Vec<(String, MemoryCategory, String)> {
     let mut results = Vec::new();
     for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
-        if let Some(found) = assistant_marker_regex()
-            .captures(line)
-            .and_then(|caps| caps.get(1))
-        {
-            let body = found.as_str().to_string();
-            results.push((
-                body.clone(),
-                infer_category(&body),
-                "assistant memory marker".to_string(),
-            ));
+        for language in languages {
+            if let Some(found) = patterns(*language)
+                .assistant_marker
+                .captures(line)
+                .and_then(|caps| caps.get(1))
+            {
+                let body = found.as_str().to_string();
+                results.push((
+                    body.clone(),
+                    infer_category(&body, languages),
+                    format!("assistant memory marker ({})", language.as_str()),
+                ));
+                break;
+            }
         }
     }
     results
 }
 
-pub fn extract_auto_capture_candidates(
-    messages: &Value,
-    config: &AutoCaptureConfig,
-    processed_hashes: &HashSet<String>,
-) -> Vec<AutoCaptureCandidate> {
+struct Correction {
+    action: CaptureAction,
+    text: String,
+    target: String,
+    reason: &'static str,
+}
+
+fn parse_correction(line: &str, languages: &[Language]) -> Option<Correction> {
+    if let Some(caps) = switched_regex().captures(line) {
+        let old = cleanup_text(&caps[1]);
+        let new = cleanup_text(&caps[2]);
+        return Some(Correction {
+            action: CaptureAction::Replace,
+            text: format!("Use {new} instead of {old}"),
+            target: old,
+            reason: "correction statement",
+        });
+    }
+
+    if let Some(caps) = instead_of_regex().captures(line) {
+        let new = cleanup_text(&caps[1]);
+        let old = cleanup_text(&caps[2]);
+        return Some(Correction {
+            action: CaptureAction::Replace,
+            text: format!("Use {new} instead of {old}"),
+            target: old,
+            reason: "correction statement",
+        });
+    }
+
+    let retracted = languages
+        .iter()
+        .find_map(|language| patterns(*language).forget.captures(line))
+        .or_else(|| no_longer_regex().captures(line))?;
+    let target = cleanup_text(retracted.get(1).or_else(|| retracted.get(2))?.as_str());
+    Some(Correction {
+        action: CaptureAction::Retract,
+        text: target.clone(),
+        target,
+        reason: "retraction statement",
+    })
+}
+
+fn split_user_corrections(text: &str, languages: &[Language]) -> (Vec<Correction>, String) {
+    let mut corrections = Vec::new();
+    let mut remaining = Vec::new();
+    for line in text.lines() {
+        match parse_correction(line.trim(), languages) {
+            Some(correction) => corrections.push(correction),
+            None => remaining.push(line),
+        }
+    }
+    (corrections, remaining.join("\n"))
+}
+
+#[must_use]
+pub fn collect_agent_messages(messages: &Value) -> Vec<(String, String)> {
     let Some(messages) = messages.as_array() else {
         return Vec::new();
     };
 
-    let mut candidates = Vec::new();
-    let mut seen_turn = HashSet::new();
-
+    let mut collected = Vec::new();
     for message in messages {
         let role = message.get("role").and_then(Value::as_str);
         let Some(role) = role else {
@@ -166,38 +366,138 @@ pub fn extract_auto_capture_candidates(
         if text.is_empty() {
             continue;
         }
+        collected.push((role.to_string(), text));
+    }
+    collected
+}
 
-        let extracted = if role == "user" {
-            extract_user_explicit(&text)
-        } else {
-            extract_assistant_marked(&text)
-        };
+fn accept_candidate(
+    role: &str,
+    raw_text: &str,
+    category: MemoryCategory,
+    reason: String,
+    routing: (Option<ScopeTarget>, Option<String>),
+    action: CaptureAction,
+    config: &AutoCaptureConfig,
+    secrets: &SecretScanner,
+    processed_hashes: &HashSet<String>,
+    seen_turn: &mut HashSet<String>,
+) -> Option<AutoCaptureCandidate> {
+    let mut cleaned = cleanup_text(raw_text);
+    if cleaned.is_empty() {
+        return None;
+    }
+    let mut reason = reason;
+    if secrets.is_secret(&cleaned) {
+        if secrets.policy == SecretPolicy::Reject {
+            return None;
+        }
+        let (redacted, detectors) = secrets.redact(&cleaned);
+        cleaned = redacted;
+        reason = format!("{reason} (redacted: {})", detectors.join(", "));
+    }
+    let char_count = cleaned.chars().count();
+    let min_chars = if action == CaptureAction::Retract {
+        1
+    } else {
+        config.min_chars
+    };
+    if char_count < min_chars || char_count > config.max_chars {
+        return None;
+    }
+    let hash = match action {
+        CaptureAction::Add => sha256(&format!("{role}:{}", normalize_for_hash(&cleaned))),
+        _ => sha256(&format!(
+            "{role}:{}:{}",
+            action.as_str(),
+            normalize_for_hash(&cleaned)
+        )),
+    };
+    if processed_hashes.contains(&hash) || seen_turn.contains(&hash) {
+        return None;
+    }
+    seen_turn.insert(hash.clone());
 
-        for (raw_text, category, reason) in extracted {
-            let cleaned = cleanup_text(&raw_text);
-            if cleaned.is_empty() {
-                continue;
-            }
-            let char_count = cleaned.chars().count();
-            if char_count < config.min_chars || char_count > config.max_chars {
-                continue;
-            }
-            if is_probably_secret(&cleaned) {
-                continue;
-            }
+    Some(AutoCaptureCandidate {
+        hash,
+        text: cleaned,
+        category,
+        reason,
+        scope: routing.0,
+        tag: routing.1,
+        action,
+        target: None,
+    })
+}
 
-            let hash = sha256(&format!("{role}:{}", normalize_for_hash(&cleaned)));
-            if processed_hashes.contains(&hash) || seen_turn.contains(&hash) {
-                continue;
+#[must_use]
+pub fn extract_auto_capture_candidates(
+    messages: &Value,
+    config: &AutoCaptureConfig,
+    rules: &[CompiledCaptureRule],
+    secrets: &SecretScanner,
+    processed_hashes: &HashSet<String>,
+) -> Vec<AutoCaptureCandidate> {
+    let mut candidates = Vec::new();
+    let mut seen_turn = HashSet::new();
+
+    for (role, mut text) in collect_agent_messages(messages) {
+        let languages = languages_for_message(&text, &config.languages);
+        if role == "user" {
+            let (corrections, remaining) = split_user_corrections(&text, &languages);
+            text = remaining;
+            for correction in corrections {
+                let Some(mut candidate) = accept_candidate(
+                    &role,
+                    &correction.text,
+                    infer_category(&correction.text, &languages),
+                    format!("regex: {}", correction.reason),
+                    (None, None),
+                    correction.action,
+                    config,
+                    secrets,
+                    processed_hashes,
+                    &mut seen_turn,
+                ) else {
+                    continue;
+                };
+                candidate.target = Some(correction.target);
+                candidates.push(candidate);
+
+                if candidates.len() >= config.max_per_turn {
+                    return candidates;
+                }
             }
+        }
 
-            candidates.push(AutoCaptureCandidate {
-                hash: hash.clone(),
-                text: cleaned,
+        let mut extracted = extract_rule_matches(&role, &text, &languages, rules);
+        let builtin = if role == "user" {
+            extract_user_explicit(&text, &languages)
+        } else {
+            extract_assistant_marked(&text, &languages)
+        };
+        extracted.extend(
+            builtin
+                .into_iter()
+                .map(|(body, category, reason)| (body, category, reason, None, None)),
+        );
+
+        for (raw_text, category, reason, scope, tag) in extracted {
+            let Some(candidate) = accept_candidate(
+                &role,
+                &raw_text,
                 category,
-                reason,
-            });
-            seen_turn.insert(hash);
+                format!("regex: {reason}"),
+                (scope, tag),
+                CaptureAction::Add,
+                config,
+                secrets,
+                processed_hashes,
+                &mut seen_turn,
+            ) else {
+                continue;
+            };
+            candidates.push(candidate);
 
             if candidates.len() >= config.max_per_turn {
                 return candidates;
@@ -208,6 +508,107 @@ pub fn extract_auto_capture_candidates(
     candidates
 }
 
+fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Keeps the newest messages that fit the token budget. A message that does not
+/// fit is skipped rather than ending the walk, so a long final reply does not
+/// crowd out the shorter user messages before it.
+#[must_use]
+pub fn budget_llm_capture_messages(
+    messages: &[(String, String)],
+    max_input_tokens: usize,
+) -> Vec<(String, String)> {
+    let mut used = 0_usize;
+    let mut selected = Vec::new();
+    for (role, text) in messages.iter().rev() {
+        let needed = estimate_tokens(text);
+        if used + needed > max_input_tokens {
+            continue;
+        }
+        used += needed;
+        selected.push((role.clone(), text.clone()));
+    }
+    selected.reverse();
+    selected
+}
+
+#[must_use]
+pub fn candidates_from_llm_proposals(
+    proposals: &[LlmCaptureProposal],
+    config: &AutoCaptureConfig,
+    secrets: &SecretScanner,
+    processed_hashes: &HashSet<String>,
+) -> Vec<AutoCaptureCandidate> {
+    let mut candidates = Vec::new();
+    let mut seen_turn = HashSet::new();
+
+    for proposal in proposals {
+        if proposal.confidence < config.llm.min_confidence {
+            continue;
+        }
+        let role = match proposal.role.as_deref() {
+            Some("assistant") => "assistant",
+            _ => "user",
+        };
+        let category = proposal
+            .category
+            .as_deref()
+            .and_then(|raw| raw.parse::<MemoryCategory>().ok())
+            .unwrap_or_else(|| {
+                infer_category(
+                    &proposal.text,
+                    &languages_for_message(&proposal.text, &config.languages),
+                )
+            });
+        let Some(candidate) = accept_candidate(
+            role,
+            &proposal.text,
+            category,
+            format!(
+                "llm: proposed durable fact (confidence {:.2})",
+                proposal.confidence
+            ),
+            (None, None),
+            CaptureAction::Add,
+            config,
+            secrets,
+            processed_hashes,
+            &mut seen_turn,
+        ) else {
+            continue;
+        };
+        candidates.push(candidate);
+
+        if candidates.len() >= config.max_per_turn {
+            break;
+        }
+    }
+
+    candidates
+}
+
+#[must_use]
+pub fn merge_auto_capture_candidates(
+    primary: Vec<AutoCaptureCandidate>,
+    fallback: Vec<AutoCaptureCandidate>,
+    max_per_turn: usize,
+) -> Vec<AutoCaptureCandidate> {
+    let mut seen = primary
+        .iter()
+        .map(|candidate| normalize_for_hash(&candidate.text))
+        .collect::<HashSet<_>>();
+    let mut merged = primary;
+    for candidate in fallback {
+        if seen.insert(normalize_for_hash(&candidate.text)) {
+            merged.push(candidate);
+        }
+    }
+    merged.truncate(max_per_turn);
+    merged
+}
+
 #[must_use]
 pub fn get_agent_end_messages(event: &Value) -> Value {
     event
@@ -218,22 +619,270 @@ pub fn get_agent_end_messages(event: &Value) -> Value {
 
 #[cfg(test)]
 mod tests {
-    use super::{extract_assistant_marked, extract_user_explicit};
+    use super::{
+        budget_llm_capture_messages, candidates_from_llm_proposals, compile_capture_rules,
+        extract_assistant_marked, extract_auto_capture_candidates, extract_user_explicit,
+        merge_auto_capture_candidates,
+    };
+    use crate::config::MemoryConfig;
+    use crate::llm::LlmCaptureProposal;
+    use crate::locale::Language;
+    use crate::secrets::default_scanner;
+    use crate::types::{AutoCaptureRule, CaptureAction, MemoryCategory, ScopeTarget};
+    use std::collections::HashSet;
 
     #[test]
     fn user_explicit_patterns_extract_expected_entries() {
         let entries = extract_user_explicit(
             "please remember that always run tests\nI prefer rust for tooling",
+            &[Language::En],
         );
         assert_eq!(entries.len(), 2);
         assert_eq!(entries[0].0, "always run tests");
         assert_eq!(entries[1].0, "rust for tooling");
     }
 
+    #[test]
+    fn norwegian_patterns_extract_expected_entries() {
+        let entries = extract_user_explicit(
+            "husk at vi alltid kjører tester før merge\njeg foretrekker korte commit-meldinger",
+            &[Language::Nb],
+        );
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].0, "vi alltid kjører tester før merge");
+        assert_eq!(entries[0].1, MemoryCategory::Workflow);
+        assert_eq!(entries[1].0, "korte commit-meldinger");
+        assert_eq!(entries[1].1, MemoryCategory::Preference);
+    }
+
+    #[test]
+    fn german_patterns_extract_expected_entries() {
+        let entries = extract_user_explicit(
+            "Bitte merk dir, dass wir niemals direkt auf main pushen\nIch bevorzuge kleine Pull Requests",
+            &[Language::De],
+        );
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].0, "wir niemals direkt auf main pushen");
+        assert_eq!(entries[0].1, MemoryCategory::Constraint);
+        assert_eq!(entries[1].0, "kleine Pull Requests");
+    }
+
+    #[test]
+    fn french_patterns_extract_expected_entries() {
+        let entries = extract_user_explicit(
+            "Souviens-toi que nous utilisons toujours pnpm\nJe préfère des réponses courtes",
+            &[Language::Fr],
+        );
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].0, "nous utilisons toujours pnpm");
+        assert_eq!(entries[0].1, MemoryCategory::Workflow);
+        assert_eq!(entries[1].0, "des réponses courtes");
+    }
+
+    #[test]
+    fn spanish_patterns_extract_expected_entries() {
+        let entries = extract_user_explicit(
+            "Recuerda que nunca usamos unwrap en producción\nPrefiero commits pequeños",
+            &[Language::Es],
+        );
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].0, "nunca usamos unwrap en producción");
+        assert_eq!(entries[0].1, MemoryCategory::Constraint);
+        assert_eq!(entries[1].0, "commits pequeños");
+    }
+
+    #[test]
+    fn auto_detected_language_drives_capture_and_retraction() {
+        let mut config = MemoryConfig::default().auto_capture;
+        config.max_per_turn = 5;
+        let candidates = extract_auto_capture_candidates(
+            &serde_json::json!([
+                {"role": "user", "content": "Husk at vi bruker pnpm i dette repoet"},
+                {"role": "user", "content": "Vergiss, dass wir yarn verwenden"},
+                {"role": "user", "content": "Vergiss nicht, dass wir immer Tests schreiben"},
+                {"role": "assistant", "content": "Mémoire: les commits sont signés"}
+            ]),
+            &config,
+            &[],
+            default_scanner(),
+            &HashSet::new(),
+        );
+
+        let summary = candidates
+            .iter()
+            .map(|candidate| (candidate.action, candidate.text.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (CaptureAction::Add, "vi bruker pnpm i dette repoet"),
+                (CaptureAction::Retract, "wir yarn verwenden"),
+                (CaptureAction::Add, "wir immer Tests schreiben"),
+                (CaptureAction::Add, "les commits sont signés"),
+            ]
+        );
+    }
+
     #[test]
     fn assistant_marker_pattern_extracts_expected_entry() {
-        let entries = extract_assistant_marked("Memory: keep answers concise");
+        let entries = extract_assistant_marked("Memory: keep answers concise", &[Language::En]);
         assert_eq!(entries.len(), 1);
         assert_eq!(entries[0].0, "keep answers concise");
     }
+
+    #[test]
+    fn llm_proposals_respect_confidence_and_label_source() {
+        let config = MemoryConfig::default().auto_capture;
+        let proposals = vec![
+            LlmCaptureProposal {
+                text: "Don't use unwrap in library code".to_string(),
+                category: Some("constraint".to_string()),
+                confidence: 0.9,
+                role: None,
+            },
+            LlmCaptureProposal {
+                text: "Maybe look at the logs tomorrow".to_string(),
+                category: Some("other".to_string()),
+                confidence: 0.2,
+                role: None,
+            },
+        ];
+
+        let candidates =
+            candidates_from_llm_proposals(&proposals, &config, default_scanner(), &HashSet::new());
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].category, MemoryCategory::Constraint);
+        assert!(candidates[0].reason.starts_with("llm: "));
+
+        let regex = extract_auto_capture_candidates(
+            &serde_json::json!([
+                {"role": "user", "content": "remember that don't use unwrap in library code"},
+                {"role": "user", "content": "remember that run migrations before deploying"}
+            ]),
+            &config,
+            &[],
+            default_scanner(),
+            &HashSet::new(),
+        );
+        assert!(regex.iter().all(|c| c.reason.starts_with("regex: ")));
+
+        let merged = merge_auto_capture_candidates(candidates, regex, 5);
+        assert_eq!(merged.len(), 2);
+        assert!(merged[1].text.contains("migrations"));
+    }
+
+    #[test]
+    fn llm_budget_skips_an_oversized_final_reply_and_keeps_earlier_messages() {
+        let messages = vec![
+            ("user".to_string(), "we deploy on fridays".to_string()),
+            ("user".to_string(), "and never on holidays".to_string()),
+            ("assistant".to_string(), "x".repeat(4_000)),
+        ];
+
+        let selected = budget_llm_capture_messages(&messages, 100);
+        assert_eq!(selected, messages[..2].to_vec());
+        assert_eq!(
+            budget_llm_capture_messages(&messages, 5),
+            messages[..1].to_vec()
+        );
+    }
+
+    #[test]
+    fn custom_rules_capture_with_target_category_scope_and_tag() {
+        let mut config = MemoryConfig::default().auto_capture;
+        config.rules = vec![
+            AutoCaptureRule {
+                pattern: r"(?i)^team rule:\s*(.+)$".to_string(),
+                role: "user".to_string(),
+                category: Some("convention".to_string()),
+                scope: Some("global".to_string()),
+                tag: Some("team".to_string()),
+            },
+            AutoCaptureRule {
+                pattern: "(unclosed".to_string(),
+                role: "any".to_string(),
+                category: None,
+                scope: None,
+                tag: None,
+            },
+        ];
+
+        let rules = compile_capture_rules(&config.rules);
+        assert_eq!(rules.compiled.len(), 1);
+        assert_eq!(rules.errors.len(), 1);
+        assert!(
+            rules
+                .error_for(1)
+                .is_some_and(|error| error.starts_with("invalid pattern"))
+        );
+        assert!(rules.error_messages()[0].starts_with("autoCapture.rules[1]: invalid pattern"));
+
+        let candidates = extract_auto_capture_candidates(
+            &serde_json::json!([
+                {"role": "user", "content": "Team rule: squash merge every pull request"},
+                {"role": "assistant", "content": "Team rule: assistants cannot set team rules"}
+            ]),
+            &config,
+            &rules.compiled,
+            default_scanner(),
+            &HashSet::new(),
+        );
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].text, "squash merge every pull request");
+        assert_eq!(candidates[0].category, MemoryCategory::Convention);
+        assert_eq!(candidates[0].scope, Some(ScopeTarget::Global));
+        assert_eq!(candidates[0].tag.as_deref(), Some("team"));
+    }
+
+    #[test]
+    fn retraction_and_correction_phrasing_emit_actions() {
+        let mut config = MemoryConfig::default().auto_capture;
+        config.max_per_turn = 5;
+        let candidates = extract_auto_capture_candidates(
+            &serde_json::json!([
+                {"role": "user", "content": "Forget that we use yarn for installs"},
+                {"role": "user", "content": "Actually, we switched from yarn to pnpm."},
+                {"role": "user", "content": "Actually, I prefer tabs, not spaces"},
+                {"role": "user", "content": "We don't squash merge anymore."},
+                {"role": "assistant", "content": "Forget that we use yarn for installs"}
+            ]),
+            &config,
+            &[],
+            default_scanner(),
+            &HashSet::new(),
+        );
+
+        assert_eq!(candidates.len(), 4);
+        assert_eq!(candidates[0].action, CaptureAction::Retract);
+        assert_eq!(
+            candidates[0].target.as_deref(),
+            Some("we use yarn for installs")
+        );
+        assert_eq!(candidates[1].action, CaptureAction::Replace);
+        assert_eq!(candidates[1].text, "Use pnpm instead of yarn");
+        assert_eq!(candidates[1].target.as_deref(), Some("yarn"));
+        assert_eq!(candidates[2].action, CaptureAction::Replace);
+        assert_eq!(candidates[2].text, "Use tabs instead of spaces");
+        assert_eq!(candidates[2].target.as_deref(), Some("spaces"));
+        assert_eq!(candidates[3].action, CaptureAction::Retract);
+        assert_eq!(candidates[3].target.as_deref(), Some("squash merge"));
+    }
+
+    #[test]
+    fn plain_negative_preferences_are_captured_not_retracted() {
+        let candidates = extract_auto_capture_candidates(
+            &serde_json::json!([
+                {"role": "user", "content": "Remember that we don't deploy on Fridays"},
+                {"role": "user", "content": "I don't use unwrap in library code"}
+            ]),
+            &MemoryConfig::default().auto_capture,
+            &[],
+            default_scanner(),
+            &HashSet::new(),
+        );
+
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].action, CaptureAction::Add);
+        assert_eq!(candidates[0].text, "we don't deploy on Fridays");
+    }
 }
@@ -0,0 +1,180 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::{Value, json};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const INJECTED_USER_PREFIXES: &[&str] = &[
+    "<environment_context>",
+    "<user_instructions>",
+    "<user_shell_command>",
+    "# AGENTS.md instructions",
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct SessionTranscript {
+    pub session_id: Option<String>,
+    pub cwd: Option<String>,
+    pub turns: Vec<Vec<Value>>,
+}
+
+fn modified_on_or_after(path: &Path, since: NaiveDate) -> bool {
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .map_or(true, |modified| {
+            DateTime::<Utc>::from(modified).date_naive() >= since
+        })
+}
+
+pub fn find_session_files(sessions_dir: &Path, since: Option<NaiveDate>) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![sessions_dir.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let entries = fs::read_dir(&dir)
+            .with_context(|| format!("reading sessions directory {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().and_then(|ext| ext.to_str()) == Some("jsonl")
+                && since.is_none_or(|since| modified_on_or_after(&path, since))
+            {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+fn message_text(item: &Value) -> String {
+    if let Some(text) = item.get("content").and_then(Value::as_str) {
+        return text.to_string();
+    }
+
+    item.get("content")
+        .and_then(Value::as_array)
+        .map(|blocks| {
+            blocks
+                .iter()
+                .filter(|block| {
+                    matches!(
+                        block.get("type").and_then(Value::as_str),
+                        Some("input_text" | "output_text" | "text")
+                    )
+                })
+                .filter_map(|block| block.get("text").and_then(Value::as_str))
+                .filter(|text| !text.trim().is_empty())
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default()
+}
+
+fn is_injected_context(text: &str) -> bool {
+    let trimmed = text.trim_start();
+    INJECTED_USER_PREFIXES
+        .iter()
+        .any(|prefix| trimmed.starts_with(prefix))
+}
+
+#[must_use]
+pub fn parse_session_lines(raw: &str) -> SessionTranscript {
+    let mut transcript = SessionTranscript::default();
+    let mut current_turn: Vec<Value> = Vec::new();
+
+    for line in raw.lines().filter(|line| !line.trim().is_empty()) {
+        let Ok(entry) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+
+        let item = match entry.get("type").and_then(Value::as_str) {
+            Some("session_meta") => {
+                let payload = entry.get("payload").unwrap_or(&Value::Null);
+                transcript.session_id = payload
+                    .get("id")
+                    .and_then(Value::as_str)
+                    .map(str::to_string);
+                if let Some(cwd) = payload.get("cwd").and_then(Value::as_str) {
+                    transcript.cwd = Some(cwd.to_string());
+                }
+                continue;
+            }
+            Some("turn_context") => {
+                if transcript.cwd.is_none() {
+                    transcript.cwd = entry
+                        .pointer("/payload/cwd")
+                        .and_then(Value::as_str)
+                        .map(str::to_string);
+                }
+                continue;
+            }
+            Some("response_item") => entry.get("payload").cloned().unwrap_or(Value::Null),
+            Some("message") => entry,
+            _ => continue,
+        };
+
+        if item.get("type").and_then(Value::as_str) != Some("message") {
+            continue;
+        }
+        let Some(role) = item.get("role").and_then(Value::as_str) else {
+            continue;
+        };
+        if role != "user" && role != "assistant" {
+            continue;
+        }
+
+        let text = message_text(&item);
+        if text.trim().is_empty() || (role == "user" && is_injected_context(&text)) {
+            continue;
+        }
+
+        if role == "user" && !current_turn.is_empty() {
+            transcript.turns.push(std::mem::take(&mut current_turn));
+        }
+        current_turn.push(json!({"role": role, "content": text}));
+    }
+
+    if !current_turn.is_empty() {
+        transcript.turns.push(current_turn);
+    }
+    transcript
+}
+
+pub fn parse_session_file(path: &Path) -> Result<SessionTranscript> {
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("reading session file {}", path.display()))?;
+    Ok(parse_session_lines(&raw))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_session_lines;
+
+    #[test]
+    fn rollout_lines_become_turns_without_injected_context() {
+        let raw = [
+            r#"{"timestamp":"2025-06-01T10:00:00Z","type":"session_meta","payload":{"id":"abc","cwd":"/work/app"}}"#,
+            r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"<environment_context>cwd</environment_context>"}]}}"#,
+            r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"remember that CI runs on every push"}]}}"#,
+            r#"{"type":"response_item","payload":{"type":"reasoning","summary":[]}}"#,
+            r#"{"type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":"Noted."}]}}"#,
+            r#"{"type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"I prefer short commit subjects"}]}}"#,
+            "not json",
+        ]
+        .join("\n");
+
+        let transcript = parse_session_lines(&raw);
+        assert_eq!(transcript.session_id.as_deref(), Some("abc"));
+        assert_eq!(transcript.cwd.as_deref(), Some("/work/app"));
+        assert_eq!(transcript.turns.len(), 2);
+        assert_eq!(transcript.turns[0].len(), 2);
+        assert_eq!(
+            transcript.turns[0][0]["content"],
+            "remember that CI runs on every push"
+        );
+        assert_eq!(transcript.turns[1][0]["role"], "user");
+    }
+}
@@ -1,5 +1,6 @@
 use crate::types::{MemoryCategory, ScopeTarget};
 use crate::utils::split_first_token;
+use chrono::NaiveDate;
 
 #[derive(Debug, Clone)]
 pub struct AddArgs {
@@ -15,6 +16,74 @@ pub struct ExportArgs {
     pub output_path_raw: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackfillArgs {
+    pub since: Option<NaiveDate>,
+    pub sessions_dir: Option<String>,
+    pub apply: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditRemediation {
+    None,
+    Redact,
+    Delete,
+}
+
+impl AuditRemediation {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Redact => "redact",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditArgs {
+    pub include_inactive: bool,
+    pub remediation: AuditRemediation,
+    pub report_path: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScopeSpec {
+    Target(ScopeTarget),
+    Raw(String),
+}
+
+impl ScopeSpec {
+    #[must_use]
+    pub fn parse(raw: &str) -> Self {
+        raw.parse::<ScopeTarget>()
+            .map_or_else(|_| Self::Raw(raw.trim().to_string()), Self::Target)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferArgs {
+    pub id_or_prefix: String,
+    pub target: ScopeSpec,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScopeAction {
+    Show,
+    Migrate { from: String, to: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigAction {
+    Show { origin: bool },
+    Get { key: String },
+    Set { key: String, value: String },
+    Unset { key: String },
+    Schema,
+    Migrate { dry_run: bool },
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExportFormat {
     Json,
@@ -44,16 +113,19 @@ pub enum MemoryCommand {
     Help,
     Refresh,
     Sync,
+    SyncStatus,
     Add(AddArgs),
     Show,
     List {
         limit: Option<usize>,
         cursor: Option<String>,
+        include_pending: bool,
     },
     Search {
         query: String,
         limit: Option<usize>,
         cursor: Option<String>,
+        include_pending: bool,
     },
     Delete {
         id_or_prefix: String,
@@ -65,30 +137,90 @@ pub enum MemoryCommand {
     Auto {
         mode: AutoMode,
     },
+    Review(ReviewAction),
     Stats,
     Export(ExportArgs),
+    Backfill(BackfillArgs),
+    Audit(AuditArgs),
+    Encrypt,
+    Decrypt,
+    SecretsTest {
+        text: String,
+    },
+    Scope(ScopeAction),
+    Scopes,
+    BranchPromote {
+        branch: Option<String>,
+    },
+    Move(TransferArgs),
+    Copy(TransferArgs),
+    Config(ConfigAction),
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum AutoMode {
     On,
     Off,
+    Review,
     Status,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReviewTargets {
+    All,
+    Ids(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+pub enum ReviewAction {
+    List {
+        limit: Option<usize>,
+        cursor: Option<String>,
+    },
+    Approve(ReviewTargets),
+    Reject(ReviewTargets),
+    Edit {
+        id_or_prefix: String,
+        text: String,
+    },
+    Recategorize {
+        category: MemoryCategory,
+        targets: ReviewTargets,
+    },
+}
+
 pub const COMMAND_HELP: &str = r"Persistent memory commands:
 
-/memory add [--global|--project] [--category <category>] <text>
+/memory add [--global|--org|--project|--package|--branch] [--category <category>] <text>
 /memory show
-/memory list [--limit <n>] [--cursor <token>]
-/memory search <query> [--limit <n>] [--cursor <token>]
+/memory list [--pending] [--limit <n>] [--cursor <token>]
+/memory search <query> [--pending] [--limit <n>] [--cursor <token>]
 /memory delete <id-or-prefix>
 /memory pin <id-or-prefix> on|off
-/memory auto [on|off|status]
+/memory auto [on|off|review|status]
+/memory review [list] [--limit <n>] [--cursor <token>]
+/memory review approve|reject <id-or-prefix>...|--all
+/memory review edit <id-or-prefix> <text>
+/memory review category <category> <id-or-prefix>...|--all
 /memory stats
 /memory export [--all] [json|md] [path]
+/memory backfill [--since <YYYY-MM-DD>] [--sessions-dir <path>] [--apply]
+/memory audit [--include-deleted] [--redact|--delete] [--report <path>]
+/memory secrets test <text>
+/memory encrypt
+/memory decrypt
+/memory scope [migrate <old-scope> <new-scope|current>]
+/memory scopes
+/memory branch promote [<branch>]
+/memory move <id-or-prefix> --global|--org|--project|--package|--branch|--scope <scope>
+/memory copy <id-or-prefix> --to <scope>
+/memory config show [--origin]
+/memory config get|unset <key>
+/memory config set <key> <value>
+/memory config schema
+/memory config migrate [--dry-run]
 /memory refresh
-/memory sync
+/memory sync [status]
 /memory help
 ";
 
@@ -112,6 +244,18 @@ pub fn parse_add_args(raw: &str) -> Result<AddArgs, String> {
                 scope_target = ScopeTarget::Project;
                 remaining = rest;
             }
+            "--package" => {
+                scope_target = ScopeTarget::Package;
+                remaining = rest;
+            }
+            "--branch" => {
+                scope_target = ScopeTarget::Branch;
+                remaining = rest;
+            }
+            "--org" => {
+                scope_target = ScopeTarget::Org;
+                remaining = rest;
+            }
             "--category" => {
                 let (category_token, category_rest) = split_first_token(rest);
                 if category_token.is_empty() {
@@ -129,7 +273,8 @@ pub fn parse_add_args(raw: &str) -> Result<AddArgs, String> {
     let text = remaining.trim().to_string();
     if text.is_empty() {
         return Err(
-            "Usage: /memory add [--global|--project] [--category <category>] <text>".to_string(),
+            "Usage: /memory add [--global|--org|--project|--package|--branch] [--category <category>] <text>"
+                .to_string(),
         );
     }
 
@@ -191,6 +336,153 @@ pub fn parse_export_args(raw: &str) -> ExportArgs {
     }
 }
 
+const BACKFILL_USAGE: &str =
+    "Usage: /memory backfill [--since <YYYY-MM-DD>] [--sessions-dir <path>] [--apply]";
+
+pub fn parse_backfill_args(raw: &str) -> Result<BackfillArgs, String> {
+    let tokens = raw.split_whitespace().collect::<Vec<_>>();
+    let mut args = BackfillArgs {
+        since: None,
+        sessions_dir: None,
+        apply: false,
+    };
+
+    let mut index = 0;
+    while index < tokens.len() {
+        match tokens[index] {
+            "--since" => {
+                let value = tokens
+                    .get(index + 1)
+                    .ok_or_else(|| "Missing value for --since".to_string())?;
+                let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+                    format!("--since must be a date like 2025-01-31, got '{value}'")
+                })?;
+                args.since = Some(date);
+                index += 2;
+            }
+            "--sessions-dir" => {
+                let value = tokens
+                    .get(index + 1)
+                    .ok_or_else(|| "Missing value for --sessions-dir".to_string())?;
+                args.sessions_dir = Some((*value).to_string());
+                index += 2;
+            }
+            "--apply" => {
+                args.apply = true;
+                index += 1;
+            }
+            _ => return Err(BACKFILL_USAGE.to_string()),
+        }
+    }
+
+    Ok(args)
+}
+
+const AUDIT_USAGE: &str =
+    "Usage: /memory audit [--include-deleted] [--redact|--delete] [--report <path>]";
+
+pub fn parse_audit_args(raw: &str) -> Result<AuditArgs, String> {
+    let tokens = raw.split_whitespace().collect::<Vec<_>>();
+    let mut args = AuditArgs {
+        include_inactive: false,
+        remediation: AuditRemediation::None,
+        report_path: None,
+    };
+
+    let mut index = 0;
+    while index < tokens.len() {
+        match tokens[index] {
+            "--include-deleted" => {
+                args.include_inactive = true;
+                index += 1;
+            }
+            flag @ ("--redact" | "--delete") => {
+                let remediation = if flag == "--redact" {
+                    AuditRemediation::Redact
+                } else {
+                    AuditRemediation::Delete
+                };
+                if args.remediation != AuditRemediation::None && args.remediation != remediation {
+                    return Err("Use either --redact or --delete, not both".to_string());
+                }
+                args.remediation = remediation;
+                index += 1;
+            }
+            "--report" => {
+                let value = tokens
+                    .get(index + 1)
+                    .ok_or_else(|| "Missing value for --report".to_string())?;
+                args.report_path = Some((*value).to_string());
+                index += 2;
+            }
+            _ => return Err(AUDIT_USAGE.to_string()),
+        }
+    }
+
+    Ok(args)
+}
+
+const REVIEW_USAGE: &str = "Usage: /memory review [list] | approve|reject <id>...|--all | edit <id> <text> | category <category> <id>...|--all";
+
+fn parse_review_targets(raw: &str) -> Result<ReviewTargets, String> {
+    let tokens = raw.split_whitespace().collect::<Vec<_>>();
+    if tokens.is_empty() {
+        return Err(REVIEW_USAGE.to_string());
+    }
+    if tokens.contains(&"--all") {
+        if tokens.len() > 1 {
+            return Err("--all cannot be combined with ids.".to_string());
+        }
+        return Ok(ReviewTargets::All);
+    }
+    Ok(ReviewTargets::Ids(
+        tokens.into_iter().map(str::to_string).collect(),
+    ))
+}
+
+pub fn parse_review_args(raw: &str) -> Result<ReviewAction, String> {
+    let (action, rest) = split_first_token(raw);
+    match action.to_lowercase().as_str() {
+        "" | "list" | "--limit" | "--cursor" => {
+            let options = if action.starts_with("--") { raw } else { rest };
+            let tokens = options.split_whitespace().collect::<Vec<_>>();
+            let (limit, cursor) = parse_limit_cursor(&tokens)?;
+            Ok(ReviewAction::List { limit, cursor })
+        }
+        "approve" => Ok(ReviewAction::Approve(parse_review_targets(rest)?)),
+        "reject" => Ok(ReviewAction::Reject(parse_review_targets(rest)?)),
+        "edit" => {
+            let (id_or_prefix, text) = split_first_token(rest);
+            if id_or_prefix.is_empty() || text.is_empty() {
+                return Err("Usage: /memory review edit <id-or-prefix> <text>".to_string());
+            }
+            Ok(ReviewAction::Edit {
+                id_or_prefix: id_or_prefix.to_string(),
+                text: text.to_string(),
+            })
+        }
+        "category" | "recategorize" => {
+            let (category, targets) = split_first_token(rest);
+            if category.is_empty() {
+                return Err(REVIEW_USAGE.to_string());
+            }
+            Ok(ReviewAction::Recategorize {
+                category: category.parse::<MemoryCategory>()?,
+                targets: parse_review_targets(targets)?,
+            })
+        }
+        _ => Err(REVIEW_USAGE.to_string()),
+    }
+}
+
+fn take_pending_flag(raw: &str) -> (Vec<&str>, bool) {
+    let mut tokens = raw.split_whitespace().collect::<Vec<_>>();
+    let before = tokens.len();
+    tokens.retain(|token| *token != "--pending");
+    let include_pending = tokens.len() != before;
+    (tokens, include_pending)
+}
+
 fn parse_limit_cursor(tokens: &[&str]) -> Result<(Option<usize>, Option<String>), String> {
     let mut limit = None;
     let mut cursor = None;
@@ -246,19 +538,28 @@ pub fn parse_memory_command(raw_input: &str) -> Result<MemoryCommand, String> {
     match subcommand.as_str() {
         "help" => Ok(MemoryCommand::Help),
         "refresh" => Ok(MemoryCommand::Refresh),
-        "sync" => Ok(MemoryCommand::Sync),
+        "sync" => match rest.trim().to_lowercase().as_str() {
+            "" => Ok(MemoryCommand::Sync),
+            "status" => Ok(MemoryCommand::SyncStatus),
+            _ => Err("Usage: /memory sync [status]".to_string()),
+        },
         "add" => Ok(MemoryCommand::Add(parse_add_args(rest)?)),
         "show" => Ok(MemoryCommand::Show),
         "list" => {
-            let tokens = rest.split_whitespace().collect::<Vec<_>>();
+            let (tokens, include_pending) = take_pending_flag(rest);
             let (limit, cursor) = parse_limit_cursor(&tokens)?;
-            Ok(MemoryCommand::List { limit, cursor })
+            Ok(MemoryCommand::List {
+                limit,
+                cursor,
+                include_pending,
+            })
         }
         "search" => {
-            let tokens = rest.split_whitespace().collect::<Vec<_>>();
+            let (tokens, include_pending) = take_pending_flag(rest);
             if tokens.is_empty() {
                 return Err(
-                    "Usage: /memory search <query> [--limit <n>] [--cursor <token>]".to_string(),
+                    "Usage: /memory search <query> [--pending] [--limit <n>] [--cursor <token>]"
+                        .to_string(),
                 );
             }
             let mut query_tokens = Vec::new();
@@ -272,7 +573,8 @@ pub fn parse_memory_command(raw_input: &str) -> Result<MemoryCommand, String> {
             }
             if query_tokens.is_empty() {
                 return Err(
-                    "Usage: /memory search <query> [--limit <n>] [--cursor <token>]".to_string(),
+                    "Usage: /memory search <query> [--pending] [--limit <n>] [--cursor <token>]"
+                        .to_string(),
                 );
             }
             let query = query_tokens.join(" ");
@@ -281,6 +583,7 @@ pub fn parse_memory_command(raw_input: &str) -> Result<MemoryCommand, String> {
                 query,
                 limit,
                 cursor,
+                include_pending,
             })
         }
         "delete" => {
@@ -310,22 +613,121 @@ pub fn parse_memory_command(raw_input: &str) -> Result<MemoryCommand, String> {
                 "" | "status" => AutoMode::Status,
                 "on" => AutoMode::On,
                 "off" => AutoMode::Off,
-                _ => return Err("Usage: /memory auto [on|off|status]".to_string()),
+                "review" => AutoMode::Review,
+                _ => return Err("Usage: /memory auto [on|off|review|status]".to_string()),
             };
             Ok(MemoryCommand::Auto { mode })
         }
+        "review" => Ok(MemoryCommand::Review(parse_review_args(rest)?)),
         "stats" => Ok(MemoryCommand::Stats),
         "export" => Ok(MemoryCommand::Export(parse_export_args(rest))),
+        "backfill" => Ok(MemoryCommand::Backfill(parse_backfill_args(rest)?)),
+        "audit" => Ok(MemoryCommand::Audit(parse_audit_args(rest)?)),
+        "encrypt" => Ok(MemoryCommand::Encrypt),
+        "decrypt" => Ok(MemoryCommand::Decrypt),
+        "secrets" => {
+            let (action, text) = split_first_token(rest.trim());
+            if action != "test" || text.trim().is_empty() {
+                return Err("Usage: /memory secrets test <text>".to_string());
+            }
+            Ok(MemoryCommand::SecretsTest {
+                text: text.trim().to_string(),
+            })
+        }
+        "scope" => Ok(MemoryCommand::Scope(parse_scope_args(rest)?)),
+        "scopes" => Ok(MemoryCommand::Scopes),
+        "config" => Ok(MemoryCommand::Config(parse_config_args(rest)?)),
+        "move" => Ok(MemoryCommand::Move(parse_transfer_args(rest, false)?)),
+        "copy" => Ok(MemoryCommand::Copy(parse_transfer_args(rest, true)?)),
+        "branch" => {
+            let tokens = rest.split_whitespace().collect::<Vec<_>>();
+            match tokens.as_slice() {
+                ["promote"] => Ok(MemoryCommand::BranchPromote { branch: None }),
+                ["promote", branch] => Ok(MemoryCommand::BranchPromote {
+                    branch: Some((*branch).to_string()),
+                }),
+                _ => Err("Usage: /memory branch promote [<branch>]".to_string()),
+            }
+        }
         _ => Err(format!("Unknown subcommand: {subcommand}")),
     }
 }
 
+const MOVE_USAGE: &str = "Usage: /memory move <id-or-prefix> --global|--org|--project|--package|--branch|--scope <scope>";
+const COPY_USAGE: &str = "Usage: /memory copy <id-or-prefix> --to <scope>";
+
+pub fn parse_transfer_args(raw: &str, copy: bool) -> Result<TransferArgs, String> {
+    let usage = if copy { COPY_USAGE } else { MOVE_USAGE };
+    let tokens = raw.split_whitespace().collect::<Vec<_>>();
+    let target = match tokens.as_slice() {
+        [_, "--to", scope] => ScopeSpec::parse(scope),
+        [_, "--scope", scope] if !copy => ScopeSpec::parse(scope),
+        [_, flag] if !copy => {
+            let name = flag.strip_prefix("--").ok_or_else(|| usage.to_string())?;
+            ScopeSpec::Target(name.parse::<ScopeTarget>().map_err(|_| usage.to_string())?)
+        }
+        _ => return Err(usage.to_string()),
+    };
+    Ok(TransferArgs {
+        id_or_prefix: tokens[0].to_string(),
+        target,
+    })
+}
+
+const SCOPE_USAGE: &str = "Usage: /memory scope [migrate <old-scope> <new-scope|current>]";
+
+pub fn parse_scope_args(raw: &str) -> Result<ScopeAction, String> {
+    let tokens = raw.split_whitespace().collect::<Vec<_>>();
+    match tokens.as_slice() {
+        [] | ["show"] => Ok(ScopeAction::Show),
+        ["migrate", from, to] => Ok(ScopeAction::Migrate {
+            from: (*from).to_string(),
+            to: (*to).to_string(),
+        }),
+        _ => Err(SCOPE_USAGE.to_string()),
+    }
+}
+
+pub const CONFIG_USAGE: &str = "Usage: /memory config show [--origin] | get <key> | set <key> <value> | unset <key> | schema | migrate [--dry-run]";
+
+pub fn parse_config_args(raw: &str) -> Result<ConfigAction, String> {
+    let (verb, rest) = raw
+        .trim()
+        .split_once(char::is_whitespace)
+        .unwrap_or_else(|| (raw.trim(), ""));
+    let rest = rest.trim();
+    let (key, value) = rest
+        .split_once(char::is_whitespace)
+        .map_or((rest, ""), |(key, value)| (key, value.trim()));
+    match (verb, key, value) {
+        ("" | "show", "", "") => Ok(ConfigAction::Show { origin: false }),
+        ("show", "--origin", "") => Ok(ConfigAction::Show { origin: true }),
+        ("get", key, "") if !key.is_empty() => Ok(ConfigAction::Get {
+            key: key.to_string(),
+        }),
+        ("set", key, value) if !key.is_empty() && !value.is_empty() => Ok(ConfigAction::Set {
+            key: key.to_string(),
+            value: value.to_string(),
+        }),
+        ("unset", key, "") if !key.is_empty() => Ok(ConfigAction::Unset {
+            key: key.to_string(),
+        }),
+        ("schema", "", "") => Ok(ConfigAction::Schema),
+        ("migrate", "", "") => Ok(ConfigAction::Migrate { dry_run: false }),
+        ("migrate", "--dry-run", "") => Ok(ConfigAction::Migrate { dry_run: true }),
+        _ => Err(CONFIG_USAGE.to_string()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
-        AutoMode, ExportFormat, MemoryCommand, parse_add_args, parse_export_args,
-        parse_memory_command,
+        AuditRemediation, AutoMode, ConfigAction, ExportFormat, MemoryCommand, ReviewAction,
+        ReviewTargets, ScopeAction, ScopeSpec, parse_add_args, parse_audit_args,
+        parse_backfill_args, parse_config_args, parse_export_args, parse_memory_command,
+        parse_review_args, parse_scope_args, parse_transfer_args,
     };
+    use crate::types::ScopeTarget;
 
     #[test]
     fn parse_add_category() {
@@ -353,4 +755,162 @@ mod tests {
             }
         ));
     }
+
+    #[test]
+    fn parse_pending_flag_on_list_and_search() {
+        assert!(matches!(
+            parse_memory_command("/memory list --pending --limit 5").expect("list"),
+            MemoryCommand::List {
+                limit: Some(5),
+                include_pending: true,
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse_memory_command("/memory search release branch --pending").expect("search"),
+            MemoryCommand::Search { query, include_pending: true, .. } if query == "release branch"
+        ));
+        assert!(matches!(
+            parse_memory_command("/memory list").expect("list"),
+            MemoryCommand::List {
+                include_pending: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_review_actions() {
+        assert!(matches!(
+            parse_review_args("").expect("list"),
+            ReviewAction::List { limit: None, .. }
+        ));
+        assert!(matches!(
+            parse_review_args("approve abc def").expect("approve"),
+            ReviewAction::Approve(ReviewTargets::Ids(ids)) if ids == ["abc", "def"]
+        ));
+        assert!(matches!(
+            parse_review_args("category workflow --all").expect("category"),
+            ReviewAction::Recategorize {
+                targets: ReviewTargets::All,
+                ..
+            }
+        ));
+        assert!(parse_review_args("reject --all abc").is_err());
+    }
+
+    #[test]
+    fn parse_backfill_options() {
+        let args = parse_backfill_args("--since 2025-03-01 --sessions-dir /tmp/sessions --apply")
+            .expect("backfill args");
+        assert_eq!(
+            args.since.map(|date| date.to_string()).as_deref(),
+            Some("2025-03-01")
+        );
+        assert_eq!(args.sessions_dir.as_deref(), Some("/tmp/sessions"));
+        assert!(args.apply);
+
+        assert!(parse_backfill_args("--since yesterday").is_err());
+        assert!(parse_backfill_args("--bogus").is_err());
+        assert!(matches!(
+            parse_memory_command("/memory backfill"),
+            Ok(MemoryCommand::Backfill(args)) if !args.apply && args.since.is_none()
+        ));
+    }
+
+    #[test]
+    fn parse_audit_options() {
+        let args =
+            parse_audit_args("--include-deleted --redact --report audit.json").expect("audit args");
+        assert!(args.include_inactive);
+        assert_eq!(args.remediation, AuditRemediation::Redact);
+        assert_eq!(args.report_path.as_deref(), Some("audit.json"));
+
+        assert!(parse_audit_args("--redact --delete").is_err());
+        assert!(parse_audit_args("--report").is_err());
+        assert!(matches!(
+            parse_memory_command("/memory audit"),
+            Ok(MemoryCommand::Audit(args))
+                if args.remediation == AuditRemediation::None && !args.include_inactive
+        ));
+    }
+
+    #[test]
+    fn parse_secrets_test_keeps_raw_text() {
+        assert!(matches!(
+            parse_memory_command("/memory secrets test  token = abc123def456ghi "),
+            Ok(MemoryCommand::SecretsTest { text }) if text == "token = abc123def456ghi"
+        ));
+        assert!(parse_memory_command("/memory secrets test").is_err());
+        assert!(parse_memory_command("/memory secrets list").is_err());
+    }
+
+    #[test]
+    fn parse_scope_show_and_migrate() {
+        assert_eq!(parse_scope_args(""), Ok(ScopeAction::Show));
+        assert_eq!(
+            parse_scope_args("migrate project:old current"),
+            Ok(ScopeAction::Migrate {
+                from: "project:old".to_string(),
+                to: "current".to_string(),
+            })
+        );
+        assert!(parse_scope_args("migrate project:old").is_err());
+    }
+
+    #[test]
+    fn parse_config_show_with_origin() {
+        assert_eq!(
+            parse_config_args(""),
+            Ok(ConfigAction::Show { origin: false })
+        );
+        assert_eq!(
+            parse_config_args("show --origin"),
+            Ok(ConfigAction::Show { origin: true })
+        );
+        assert!(parse_config_args("show --verbose").is_err());
+        assert_eq!(
+            parse_config_args("set autoCapture.llm.model gpt 5 mini"),
+            Ok(ConfigAction::Set {
+                key: "autoCapture.llm.model".to_string(),
+                value: "gpt 5 mini".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_config_args("unset injection.maxItems"),
+            Ok(ConfigAction::Unset {
+                key: "injection.maxItems".to_string(),
+            })
+        );
+        assert_eq!(parse_config_args("schema"), Ok(ConfigAction::Schema));
+        assert_eq!(
+            parse_config_args("migrate --dry-run"),
+            Ok(ConfigAction::Migrate { dry_run: true })
+        );
+        assert!(parse_config_args("migrate --force").is_err());
+        assert!(parse_config_args("set injection.maxItems").is_err());
+        assert!(parse_config_args("get").is_err());
+    }
+
+    #[test]
+    fn parse_move_and_copy_targets() {
+        let moved = parse_transfer_args("abc --global", false).expect("move flag");
+        assert_eq!(moved.id_or_prefix, "abc");
+        assert_eq!(moved.target, ScopeSpec::Target(ScopeTarget::Global));
+        assert_eq!(
+            parse_transfer_args("abc --scope project:123", false)
+                .expect("move scope")
+                .target,
+            ScopeSpec::Raw("project:123".to_string())
+        );
+        assert_eq!(
+            parse_transfer_args("abc --to org", true)
+                .expect("copy")
+                .target,
+            ScopeSpec::Target(ScopeTarget::Org)
+        );
+        assert!(parse_transfer_args("abc --global", true).is_err());
+        assert!(parse_transfer_args("abc --nowhere", false).is_err());
+        assert!(parse_transfer_args("abc", false).is_err());
+    }
 }
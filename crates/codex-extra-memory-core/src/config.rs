@@ -1,48 +1,108 @@
-use crate::paths::{get_config_path, get_memory_dir};
-use crate::types::{AutoCaptureConfig, ScopeTarget};
-use crate::utils::{parse_boolean, parse_positive_int};
+use crate::llm::{
+    DEFAULT_PROMPT_VERSION, DEFAULT_SYSTEM_PROMPT, DEFAULT_USER_PROMPT_TEMPLATE,
+    MEMORIES_PLACEHOLDER,
+};
+use crate::locale::Language;
+use crate::paths::{get_memory_dir, global_config_path};
+use crate::scope::{DEFAULT_SCOPE_DETECTORS, SCOPE_MARKER_FILE};
+use crate::secrets::{SecretPatternConfig, SecretScanner, SecretsConfig};
+use crate::types::{
+    AutoCaptureConfig, AutoCaptureLlmConfig, AutoCaptureRule, ScopeTarget, SecretPolicy,
+};
+use crate::utils::{parse_boolean, parse_positive_int, sha256};
 use anyhow::Context;
 use chrono::Utc;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use toml_edit::{ArrayOfTables, DocumentMut, InlineTable, Item, Table};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct InjectionConfig {
     pub max_items: usize,
     pub max_chars: usize,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactionPromptConfig {
+    pub version: u32,
+    pub system: String,
+    pub user_template: String,
+}
+
+impl Default for CompactionPromptConfig {
+    fn default() -> Self {
+        Self {
+            version: DEFAULT_PROMPT_VERSION,
+            system: DEFAULT_SYSTEM_PROMPT.to_string(),
+            user_template: DEFAULT_USER_PROMPT_TEMPLATE.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct LlmCompactionConfig {
     pub enabled: bool,
     pub model: String,
     pub timeout_ms: u64,
     pub max_output_chars: usize,
+    pub prompt: CompactionPromptConfig,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct RetentionConfig {
     pub event_days: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionConfig {
+    pub key_file: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScopeConfig {
+    pub detectors: Vec<String>,
+}
+
+impl Default for ScopeConfig {
+    fn default() -> Self {
+        Self {
+            detectors: DEFAULT_SCOPE_DETECTORS
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MemoryConfig {
+    pub config_version: u64,
     pub injection: InjectionConfig,
     pub list_limit: usize,
     pub search_limit: usize,
     pub auto_capture: AutoCaptureConfig,
     pub llm_compaction: LlmCompactionConfig,
     pub retention: RetentionConfig,
+    pub secrets: SecretsConfig,
+    pub encryption: EncryptionConfig,
+    pub scope: ScopeConfig,
 }
 
 impl Default for MemoryConfig {
     fn default() -> Self {
         Self {
+            config_version: CONFIG_VERSION,
             injection: InjectionConfig {
                 max_items: 10,
                 max_chars: 3_000,
@@ -51,18 +111,26 @@ impl Default for MemoryConfig {
             search_limit: 20,
             auto_capture: AutoCaptureConfig {
                 enabled: true,
+                review: false,
                 scope: ScopeTarget::Project,
                 max_per_turn: 2,
                 min_chars: 12,
                 max_chars: 240,
+                llm: AutoCaptureLlmConfig::default(),
+                rules: Vec::new(),
+                languages: vec!["auto".to_string()],
             },
             llm_compaction: LlmCompactionConfig {
                 enabled: true,
                 model: "gpt-5-mini".to_string(),
                 timeout_ms: 8_000,
                 max_output_chars: 1_500,
+                prompt: CompactionPromptConfig::default(),
             },
             retention: RetentionConfig { event_days: 180 },
+            secrets: SecretsConfig::default(),
+            encryption: EncryptionConfig::default(),
+            scope: ScopeConfig::default(),
         }
     }
 }
@@ -74,14 +142,46 @@ struct PartialInjectionConfig {
     max_chars: Option<usize>,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PartialAutoCaptureLlmConfig {
+    enabled: Option<serde_json::Value>,
+    model: Option<String>,
+    timeout_ms: Option<u64>,
+    max_input_tokens: Option<usize>,
+    min_confidence: Option<f64>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct PartialAutoCaptureConfig {
     enabled: Option<serde_json::Value>,
+    review: Option<serde_json::Value>,
     scope: Option<String>,
     max_per_turn: Option<usize>,
     min_chars: Option<usize>,
     max_chars: Option<usize>,
+    llm: Option<PartialAutoCaptureLlmConfig>,
+    rules: Option<Vec<PartialAutoCaptureRule>>,
+    languages: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PartialAutoCaptureRule {
+    pattern: Option<String>,
+    role: Option<String>,
+    category: Option<String>,
+    scope: Option<String>,
+    tag: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PartialCompactionPromptConfig {
+    version: Option<u32>,
+    system: Option<String>,
+    user_template: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -91,6 +191,7 @@ struct PartialLlmCompactionConfig {
     model: Option<String>,
     timeout_ms: Option<u64>,
     max_output_chars: Option<usize>,
+    prompt: Option<PartialCompactionPromptConfig>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -108,6 +209,223 @@ struct PartialMemoryConfig {
     auto_capture: Option<PartialAutoCaptureConfig>,
     llm_compaction: Option<PartialLlmCompactionConfig>,
     retention: Option<PartialRetentionConfig>,
+    secrets: Option<PartialSecretsConfig>,
+    encryption: Option<PartialEncryptionConfig>,
+    scope: Option<PartialScopeConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PartialScopeConfig {
+    detectors: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PartialEncryptionConfig {
+    key_file: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum PartialSecretsConfig {
+    Policy(String),
+    Full(PartialSecretsOptions),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PartialSecretsOptions {
+    policy: Option<String>,
+    pii: Option<serde_json::Value>,
+    patterns: Option<Vec<PartialSecretPattern>>,
+    allowlist: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PartialSecretPattern {
+    name: Option<String>,
+    pattern: Option<String>,
+}
+
+fn normalize_prompt_config(
+    partial: Option<&PartialCompactionPromptConfig>,
+    defaults: CompactionPromptConfig,
+) -> CompactionPromptConfig {
+    let Some(partial) = partial else {
+        return defaults;
+    };
+
+    let system = partial
+        .system
+        .clone()
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or(defaults.system);
+    let user_template = match partial.user_template.clone() {
+        Some(template) if template.contains(MEMORIES_PLACEHOLDER) => template,
+        Some(_) => {
+            eprintln!(
+                "codex-extra-memory: llmCompaction.prompt.userTemplate must contain {MEMORIES_PLACEHOLDER}; using the built-in template."
+            );
+            defaults.user_template
+        }
+        None => defaults.user_template,
+    };
+
+    CompactionPromptConfig {
+        version: partial
+            .version
+            .filter(|v| *v > 0)
+            .unwrap_or(defaults.version),
+        system,
+        user_template,
+    }
+}
+
+fn normalize_auto_capture_llm_config(
+    partial: Option<&PartialAutoCaptureLlmConfig>,
+    defaults: AutoCaptureLlmConfig,
+) -> AutoCaptureLlmConfig {
+    let Some(partial) = partial else {
+        return defaults;
+    };
+
+    AutoCaptureLlmConfig {
+        enabled: parse_boolean(partial.enabled.clone(), defaults.enabled),
+        model: partial
+            .model
+            .clone()
+            .filter(|m| !m.trim().is_empty())
+            .unwrap_or(defaults.model),
+        timeout_ms: partial.timeout_ms.unwrap_or(defaults.timeout_ms),
+        max_input_tokens: parse_positive_int(
+            partial.max_input_tokens.map(|x| x as i64),
+            defaults.max_input_tokens,
+        ),
+        min_confidence: partial
+            .min_confidence
+            .filter(|c| (0.0..=1.0).contains(c))
+            .unwrap_or(defaults.min_confidence),
+    }
+}
+
+fn normalize_auto_capture_rules(
+    partial: Option<&Vec<PartialAutoCaptureRule>>,
+) -> Vec<AutoCaptureRule> {
+    partial
+        .map(|rules| {
+            rules
+                .iter()
+                .map(|rule| AutoCaptureRule {
+                    pattern: rule.pattern.clone().unwrap_or_default(),
+                    role: rule.role.clone().unwrap_or_else(|| "user".to_string()),
+                    category: rule.category.clone(),
+                    scope: rule.scope.clone(),
+                    tag: rule.tag.clone(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn normalize_scope_detectors(partial: Option<&Vec<String>>, defaults: Vec<String>) -> Vec<String> {
+    let Some(partial) = partial else {
+        return defaults;
+    };
+
+    let mut detectors = Vec::new();
+    for raw in partial {
+        let name = raw.trim().to_lowercase();
+        if !DEFAULT_SCOPE_DETECTORS.contains(&name.as_str()) {
+            eprintln!(
+                "codex-extra-memory: scope.detectors: unknown detector '{raw}' (expected {}).",
+                DEFAULT_SCOPE_DETECTORS.join(", ")
+            );
+        } else if !detectors.contains(&name) {
+            detectors.push(name);
+        }
+    }
+    detectors
+}
+
+fn normalize_auto_capture_languages(
+    partial: Option<&Vec<String>>,
+    defaults: Vec<String>,
+) -> Vec<String> {
+    let Some(partial) = partial else {
+        return defaults;
+    };
+
+    let mut languages = Vec::new();
+    for raw in partial {
+        let code = raw.trim().to_lowercase();
+        let normalized = if code == "auto" {
+            Ok(code)
+        } else {
+            code.parse::<Language>()
+                .map(|language| language.as_str().to_string())
+        };
+        match normalized {
+            Ok(code) if !languages.contains(&code) => languages.push(code),
+            Ok(_) => {}
+            Err(error) => eprintln!("codex-extra-memory: autoCapture.languages: {error}"),
+        }
+    }
+
+    if languages.is_empty() {
+        defaults
+    } else {
+        languages
+    }
+}
+
+fn normalize_secret_policy(partial: Option<&str>, defaults: SecretPolicy) -> SecretPolicy {
+    match partial.map(str::parse::<SecretPolicy>) {
+        Some(Ok(policy)) => policy,
+        Some(Err(error)) => {
+            eprintln!("codex-extra-memory: secrets: {error}; using reject.");
+            defaults
+        }
+        None => defaults,
+    }
+}
+
+fn normalize_secrets_config(
+    partial: Option<&PartialSecretsConfig>,
+    defaults: SecretsConfig,
+) -> SecretsConfig {
+    let secrets = match partial {
+        None => return defaults,
+        Some(PartialSecretsConfig::Policy(policy)) => SecretsConfig {
+            policy: normalize_secret_policy(Some(policy), defaults.policy),
+            ..defaults
+        },
+        Some(PartialSecretsConfig::Full(options)) => SecretsConfig {
+            policy: normalize_secret_policy(options.policy.as_deref(), defaults.policy),
+            pii: parse_boolean(options.pii.clone(), defaults.pii),
+            patterns: options
+                .patterns
+                .as_ref()
+                .map(|patterns| {
+                    patterns
+                        .iter()
+                        .map(|pattern| SecretPatternConfig {
+                            name: pattern.name.clone().unwrap_or_default(),
+                            pattern: pattern.pattern.clone().unwrap_or_default(),
+                        })
+                        .collect()
+                })
+                .unwrap_or(defaults.patterns),
+            allowlist: options.allowlist.clone().unwrap_or(defaults.allowlist),
+        },
+    };
+
+    let (_, errors) = SecretScanner::from_config(&secrets);
+    for error in errors {
+        eprintln!("codex-extra-memory: {error}; entry is ignored until fixed.");
+    }
+    secrets
 }
 
 fn normalize_config(partial: PartialMemoryConfig) -> MemoryConfig {
@@ -141,6 +459,7 @@ fn normalize_config(partial: PartialMemoryConfig) -> MemoryConfig {
         .unwrap_or(defaults.auto_capture.scope);
 
     MemoryConfig {
+        config_version: CONFIG_VERSION,
         injection: InjectionConfig {
             max_items: parse_positive_int(
                 partial
@@ -172,6 +491,10 @@ fn normalize_config(partial: PartialMemoryConfig) -> MemoryConfig {
                     .and_then(|c| c.enabled.clone()),
                 defaults.auto_capture.enabled,
             ),
+            review: parse_boolean(
+                partial.auto_capture.as_ref().and_then(|c| c.review.clone()),
+                defaults.auto_capture.review,
+            ),
             scope,
             max_per_turn: parse_positive_int(
                 partial
@@ -183,6 +506,20 @@ fn normalize_config(partial: PartialMemoryConfig) -> MemoryConfig {
             ),
             min_chars: auto_min,
             max_chars: auto_max,
+            llm: normalize_auto_capture_llm_config(
+                partial.auto_capture.as_ref().and_then(|c| c.llm.as_ref()),
+                defaults.auto_capture.llm,
+            ),
+            rules: normalize_auto_capture_rules(
+                partial.auto_capture.as_ref().and_then(|c| c.rules.as_ref()),
+            ),
+            languages: normalize_auto_capture_languages(
+                partial
+                    .auto_capture
+                    .as_ref()
+                    .and_then(|c| c.languages.as_ref()),
+                defaults.auto_capture.languages,
+            ),
         },
         llm_compaction: LlmCompactionConfig {
             enabled: parse_boolean(
@@ -211,6 +548,13 @@ fn normalize_config(partial: PartialMemoryConfig) -> MemoryConfig {
                     .map(|x| x as i64),
                 defaults.llm_compaction.max_output_chars,
             ),
+            prompt: normalize_prompt_config(
+                partial
+                    .llm_compaction
+                    .as_ref()
+                    .and_then(|c| c.prompt.as_ref()),
+                defaults.llm_compaction.prompt,
+            ),
         },
         retention: RetentionConfig {
             event_days: partial
@@ -219,20 +563,748 @@ fn normalize_config(partial: PartialMemoryConfig) -> MemoryConfig {
                 .and_then(|r| r.event_days)
                 .unwrap_or(defaults.retention.event_days),
         },
+        secrets: normalize_secrets_config(partial.secrets.as_ref(), defaults.secrets),
+        encryption: EncryptionConfig {
+            key_file: partial
+                .encryption
+                .as_ref()
+                .and_then(|e| e.key_file.clone())
+                .map(|path| path.trim().to_string())
+                .filter(|path| !path.is_empty()),
+        },
+        scope: ScopeConfig {
+            detectors: normalize_scope_detectors(
+                partial.scope.as_ref().and_then(|s| s.detectors.as_ref()),
+                defaults.scope.detectors,
+            ),
+        },
     }
 }
 
-fn next_invalid_backup_path(config_path: &Path) -> anyhow::Result<PathBuf> {
+pub const CONFIG_ENV_PREFIX: &str = "CODEX_MEMORY_";
+pub const CONFIG_VERSION: u64 = 2;
+pub const CONFIG_VERSION_KEY: &str = "configVersion";
+const MARKER_ONLY_KEYS: &[&str] = &["scope_id", "aliases", "package"];
+// A committed `.codex-memory.toml` comes from whoever wrote the repo, so it cannot weaken
+// secret detection, change what is sent to the LLM, or switch LLM features on.
+const GLOBAL_ONLY_KEYS: &[&str] = &["encryption", "scope", "secrets", "llmCompaction.prompt"];
+const REPO_DISABLE_ONLY_KEYS: &[&str] = &["autoCapture.llm.enabled", "llmCompaction.enabled"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigMigration {
+    pub path: String,
+    pub from_version: u64,
+    pub to_version: u64,
+    pub changes: Vec<String>,
+    pub unknown_keys: Vec<String>,
+    pub dry_run: bool,
+    pub backup: Option<String>,
+}
+
+type ConfigMigrationStep = fn(&mut Map<String, Value>) -> Vec<String>;
+
+// Entry `i` upgrades a version `i + 1` document to version `i + 2`.
+const CONFIG_MIGRATIONS: &[ConfigMigrationStep] = &[migrate_v1_secrets_table];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigLayer {
+    pub origin: String,
+    pub source: String,
+    pub values: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigFileStamp {
+    pub path: PathBuf,
+    pub modified: Option<SystemTime>,
+    pub len: Option<u64>,
+}
+
+impl ConfigFileStamp {
+    #[must_use]
+    pub fn read(path: &Path) -> Self {
+        let meta = fs::metadata(path).ok();
+        Self {
+            path: path.to_path_buf(),
+            modified: meta.as_ref().and_then(|meta| meta.modified().ok()),
+            len: meta.map(|meta| meta.len()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigOrigin {
+    pub key: String,
+    pub value: Value,
+    pub origin: String,
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LayeredConfig {
+    layers: Vec<ConfigLayer>,
+}
+
+impl LayeredConfig {
+    #[must_use]
+    pub fn new(layers: Vec<ConfigLayer>) -> Self {
+        Self { layers }
+    }
+
+    #[must_use]
+    pub fn layers(&self) -> &[ConfigLayer] {
+        &self.layers
+    }
+
+    #[must_use]
+    pub fn with_repo_layer(&self, repo: Option<ConfigLayer>) -> Self {
+        let mut layers = self.layers.clone();
+        if let Some(repo) = repo {
+            let at = layers
+                .iter()
+                .position(|layer| layer.origin == "env")
+                .unwrap_or(layers.len());
+            layers.insert(at, repo);
+        }
+        Self { layers }
+    }
+
+    #[must_use]
+    pub fn resolve(&self) -> MemoryConfig {
+        let mut merged = Value::Object(Map::new());
+        for layer in &self.layers {
+            merge_config_values(&mut merged, &layer.values);
+        }
+        match serde_json::from_value::<PartialMemoryConfig>(merged) {
+            Ok(partial) => normalize_config(partial),
+            Err(error) => {
+                eprintln!(
+                    "codex-extra-memory: merged config is invalid ({error}); using defaults."
+                );
+                MemoryConfig::default()
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn origins(&self, config: &MemoryConfig) -> Vec<ConfigOrigin> {
+        let layer_keys = self
+            .layers
+            .iter()
+            .map(|layer| {
+                let mut keys = Vec::new();
+                flatten_config_value(&layer.values, "", &mut keys);
+                keys.into_iter().map(|(key, _)| key).collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let mut effective = Vec::new();
+        flatten_config_value(
+            &serde_json::to_value(config).unwrap_or(Value::Null),
+            "",
+            &mut effective,
+        );
+
+        effective
+            .into_iter()
+            .map(|(key, value)| {
+                let layer = self
+                    .layers
+                    .iter()
+                    .zip(&layer_keys)
+                    .rev()
+                    .find(|(_, keys)| keys.iter().any(|set| key_covers(set, &key)))
+                    .map(|(layer, _)| layer);
+                ConfigOrigin {
+                    origin: layer.map_or_else(|| "default".to_string(), |l| l.origin.clone()),
+                    source: layer.map(|l| l.source.clone()),
+                    key,
+                    value,
+                }
+            })
+            .collect()
+    }
+}
+
+fn key_covers(set: &str, key: &str) -> bool {
+    config_key_matches(set, key) || config_key_matches(key, set)
+}
+
+fn merge_config_values(base: &mut Value, over: &Value) {
+    match (base, over) {
+        (Value::Object(base), Value::Object(over)) => {
+            for (key, value) in over {
+                match base.get_mut(key) {
+                    Some(existing) => merge_config_values(existing, value),
+                    None => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, over) => *base = over.clone(),
+    }
+}
+
+fn flatten_config_value(value: &Value, prefix: &str, out: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten_config_value(value, &path, out);
+            }
+        }
+        _ if !prefix.is_empty() => out.push((prefix.to_string(), value.clone())),
+        _ => {}
+    }
+}
+
+pub fn set_config_value(root: &mut Value, key: &str, value: Value) {
+    let mut node = root;
+    let mut segments = key.split('.').peekable();
+    while let Some(segment) = segments.next() {
+        if !node.is_object() {
+            *node = Value::Object(Map::new());
+        }
+        let Value::Object(map) = node else {
+            return;
+        };
+        if segments.peek().is_none() {
+            map.insert(segment.to_string(), value);
+            return;
+        }
+        node = map
+            .entry(segment.to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+}
+
+fn remove_config_value(root: &mut Value, key: &str) -> Option<Value> {
+    let (parent, last) = match key.rsplit_once('.') {
+        Some((parent, last)) => (root.pointer_mut(&config_pointer(parent))?, last),
+        None => (root, key),
+    };
+    parent.as_object_mut()?.remove(last)
+}
+
+fn config_pointer(key: &str) -> String {
+    format!("/{}", key.replace('.', "/"))
+}
+
+fn default_config_value(key: &str) -> Option<Value> {
+    serde_json::to_value(MemoryConfig::default())
+        .ok()?
+        .pointer(&config_pointer(key))
+        .cloned()
+}
+
+#[must_use]
+pub fn is_config_key(key: &str) -> bool {
+    !key.trim().is_empty() && default_config_value(key).is_some()
+}
+
+#[must_use]
+pub fn config_key_matches(key: &str, candidate: &str) -> bool {
+    candidate == key
+        || candidate
+            .strip_prefix(key)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+fn drop_invalid_keys(values: &mut Value, source: &str) {
+    let mut leaves = Vec::new();
+    flatten_config_value(values, "", &mut leaves);
+    for (key, value) in leaves {
+        let mut single = Value::Object(Map::new());
+        set_config_value(&mut single, &key, value);
+        if let Err(error) = validate_config_values(&single) {
+            eprintln!("codex-extra-memory: {source}: {key}: {error}; using the default.");
+            remove_config_value(values, &key);
+        }
+    }
+}
+
+pub fn parse_config_setting(values: &Value, key: &str, raw: &str) -> Result<Value, String> {
+    if key == CONFIG_VERSION_KEY {
+        return Err(format!(
+            "{CONFIG_VERSION_KEY} is managed by `/memory config migrate`."
+        ));
+    }
+    let Some(default) = default_config_value(key) else {
+        return Err(format!("Unknown config key '{key}'."));
+    };
+    let value = if default.is_object() {
+        serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.trim().to_string()))
+    } else {
+        parse_setting_value(raw, &default).ok_or_else(|| format!("{key}: cannot parse '{raw}'."))?
+    };
+
+    let mut candidate = values.clone();
+    set_config_value(&mut candidate, key, value.clone());
+    validate_config_values(&candidate).map_err(|error| format!("{key}: {error}"))?;
+    let resolved = serde_json::to_value(
+        LayeredConfig::new(vec![ConfigLayer {
+            origin: "global".to_string(),
+            source: String::new(),
+            values: candidate,
+            etag: None,
+        }])
+        .resolve(),
+    )
+    .map_err(|error| error.to_string())?;
+
+    let mut requested = Vec::new();
+    flatten_config_value(&value, key, &mut requested);
+    for (path, wanted) in requested {
+        if wanted
+            .as_array()
+            .is_some_and(|items| items.iter().any(Value::is_object))
+        {
+            continue;
+        }
+        if let Some(actual) = resolved.pointer(&config_pointer(&path))
+            && !actual.is_object()
+            && actual != &wanted
+        {
+            return Err(format!("{path}: {wanted} is not a valid value."));
+        }
+    }
+    Ok(value)
+}
+
+#[must_use]
+pub fn config_schema() -> Value {
+    let mut schema =
+        serde_json::to_value(schemars::schema_for!(MemoryConfig)).unwrap_or(Value::Null);
+    strip_required(&mut schema);
+    if let Some(secrets) = schema.pointer_mut("/properties/secrets") {
+        let full = secrets.take();
+        *secrets = json!({"anyOf": [{"$ref": "#/$defs/SecretPolicy"}, full]});
+    }
+    schema
+}
+
+fn strip_required(schema: &mut Value) {
+    match schema {
+        Value::Object(map) => {
+            map.remove("required");
+            map.values_mut().for_each(strip_required);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_required),
+        _ => {}
+    }
+}
+
+fn validate_config_values(values: &Value) -> Result<(), serde_json::Error> {
+    serde_json::from_value::<PartialMemoryConfig>(values.clone()).map(|_| ())
+}
+
+fn migrate_v1_secrets_table(values: &mut Map<String, Value>) -> Vec<String> {
+    let Some(Value::String(policy)) = values.get("secrets") else {
+        return Vec::new();
+    };
+    let change = format!("secrets = {policy:?} -> secrets.policy = {policy:?}");
+    let policy = json!({"policy": policy});
+    values.insert("secrets".to_string(), policy);
+    vec![change]
+}
+
+#[must_use]
+pub fn config_document_version(values: &Value) -> u64 {
+    values
+        .get(CONFIG_VERSION_KEY)
+        .and_then(Value::as_u64)
+        .unwrap_or(1)
+}
+
+pub fn migrate_config_values(values: &mut Value) -> Vec<String> {
+    let from = config_document_version(values);
+    let Some(map) = values.as_object_mut() else {
+        return Vec::new();
+    };
+    let mut changes = Vec::new();
+    for (version, step) in (1..).zip(CONFIG_MIGRATIONS).filter(|(v, _)| *v >= from) {
+        for change in step(map) {
+            changes.push(format!("v{version} -> v{}: {change}", version + 1));
+        }
+    }
+    if from < CONFIG_VERSION {
+        map.insert(CONFIG_VERSION_KEY.to_string(), json!(CONFIG_VERSION));
+    }
+    changes
+}
+
+fn collect_unknown_keys(value: &Value, known: &Value, prefix: &str, out: &mut Vec<String>) {
+    let (Value::Object(map), Value::Object(known)) = (value, known) else {
+        return;
+    };
+    for (key, value) in map {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match known.get(key) {
+            Some(known) => collect_unknown_keys(value, known, &path, out),
+            None => out.push(path),
+        }
+    }
+}
+
+#[must_use]
+pub fn unknown_config_keys(values: &Value) -> Vec<String> {
+    let mut unknown = Vec::new();
+    collect_unknown_keys(
+        values,
+        &serde_json::to_value(MemoryConfig::default()).unwrap_or(Value::Null),
+        "",
+        &mut unknown,
+    );
+    unknown
+}
+
+fn warn_unknown_keys(values: &Value, source: &str) {
+    for key in unknown_config_keys(values) {
+        eprintln!("codex-extra-memory: {source}: unknown key {key}; ignoring it.");
+    }
+}
+
+fn toml_value_to_json(value: &toml_edit::Value) -> Value {
+    match value {
+        toml_edit::Value::String(text) => Value::String(text.value().clone()),
+        toml_edit::Value::Integer(number) => json!(*number.value()),
+        toml_edit::Value::Float(number) => json!(*number.value()),
+        toml_edit::Value::Boolean(flag) => Value::Bool(*flag.value()),
+        toml_edit::Value::Datetime(stamp) => Value::String(stamp.value().to_string()),
+        toml_edit::Value::Array(items) => {
+            Value::Array(items.iter().map(toml_value_to_json).collect())
+        }
+        toml_edit::Value::InlineTable(table) => Value::Object(
+            table
+                .iter()
+                .map(|(key, value)| (key.to_string(), toml_value_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn toml_table_to_json(table: &Table) -> Value {
+    Value::Object(
+        table
+            .iter()
+            .map(|(key, item)| {
+                let value = match item {
+                    Item::None => Value::Null,
+                    Item::Value(value) => toml_value_to_json(value),
+                    Item::Table(table) => toml_table_to_json(table),
+                    Item::ArrayOfTables(tables) => {
+                        Value::Array(tables.iter().map(toml_table_to_json).collect())
+                    }
+                };
+                (key.to_string(), value)
+            })
+            .collect(),
+    )
+}
+
+fn json_to_toml_value(value: &Value) -> Option<toml_edit::Value> {
+    match value {
+        Value::Null => None,
+        Value::Bool(flag) => Some((*flag).into()),
+        Value::Number(number) => number
+            .as_i64()
+            .map(Into::into)
+            .or_else(|| number.as_f64().map(Into::into)),
+        Value::String(text) => Some(text.as_str().into()),
+        Value::Array(items) => Some(toml_edit::Value::Array(
+            items.iter().filter_map(json_to_toml_value).collect(),
+        )),
+        Value::Object(map) => {
+            let mut table = InlineTable::new();
+            for (key, value) in map {
+                if let Some(value) = json_to_toml_value(value) {
+                    table.insert(key, value);
+                }
+            }
+            Some(table.into())
+        }
+    }
+}
+
+fn json_to_toml_item(value: &Value) -> Option<Item> {
+    match value {
+        Value::Object(inner) => Some(Item::Table(json_to_toml_table(inner))),
+        Value::Array(items) if !items.is_empty() && items.iter().all(Value::is_object) => {
+            let mut tables = ArrayOfTables::new();
+            for item in items.iter().filter_map(Value::as_object) {
+                tables.push(json_to_toml_table(item));
+            }
+            Some(Item::ArrayOfTables(tables))
+        }
+        _ => json_to_toml_value(value).map(Item::Value),
+    }
+}
+
+fn json_to_toml_table(map: &Map<String, Value>) -> Table {
+    let mut table = Table::new();
+    for (key, value) in map {
+        if let Some(item) = json_to_toml_item(value) {
+            table.insert(key, item);
+        }
+    }
+    table
+}
+
+fn set_toml_value(doc: &mut DocumentMut, key: &str, value: &Value) {
+    let segments = key.split('.').collect::<Vec<_>>();
+    let Some((last, parents)) = segments.split_last() else {
+        return;
+    };
+    let Some(mut new) = json_to_toml_item(value) else {
+        remove_toml_value(doc, key);
+        return;
+    };
+    let mut parent = doc.as_item_mut();
+    for segment in parents {
+        if !parent.is_table_like() {
+            *parent = Item::Table(Table::new());
+        }
+        parent = &mut parent[*segment];
+    }
+    if !parent.is_table_like() {
+        *parent = Item::Table(Table::new());
+    }
+    // A value that becomes a table stays inline so it keeps its place and comment.
+    let replaces_value = parent.get(*last).is_some_and(Item::is_value);
+    if (parent.is_inline_table() || replaces_value)
+        && let Ok(inline) = new.clone().into_value()
+    {
+        new = Item::Value(inline);
+    }
+    let slot = &mut parent[*last];
+    if let (Some(existing), Item::Value(replacement)) = (slot.as_value(), &mut new) {
+        *replacement.decor_mut() = existing.decor().clone();
+        *slot = new;
+    } else if let Some(table) = parent.as_table_like_mut() {
+        // A fresh key keeps `secrets = "x"` from rendering as `[secrets ]`.
+        table.remove(last);
+        table.insert(last, new);
+    }
+}
+
+fn sync_toml_top_level(doc: &mut DocumentMut, before: &Value, after: &Value) {
+    let (Some(before), Some(after)) = (before.as_object(), after.as_object()) else {
+        return;
+    };
+    for (key, value) in after {
+        if before.get(key) != Some(value) {
+            set_toml_value(doc, key, value);
+        }
+    }
+    for key in before.keys().filter(|key| !after.contains_key(*key)) {
+        doc.remove(key);
+    }
+}
+
+fn remove_toml_value(doc: &mut DocumentMut, key: &str) -> bool {
+    let (parents, last) = key
+        .rsplit_once('.')
+        .map_or((None, key), |(p, l)| (Some(p), l));
+    let mut parent = doc.as_item_mut();
+    for segment in parents.into_iter().flat_map(|p| p.split('.')) {
+        let Some(next) = parent.get_mut(segment) else {
+            return false;
+        };
+        parent = next;
+    }
+    parent
+        .as_table_like_mut()
+        .and_then(|table| table.remove(last))
+        .is_some()
+}
+
+fn is_toml_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "toml")
+}
+
+fn parse_config_text(config_path: &Path, raw: &str) -> anyhow::Result<Value> {
+    let values = if is_toml_path(config_path) {
+        toml_table_to_json(raw.parse::<DocumentMut>()?.as_table())
+    } else {
+        serde_json::from_str::<Value>(raw)?
+    };
+    if !values.is_object() {
+        anyhow::bail!("expected a table of settings");
+    }
+    Ok(values)
+}
+
+fn env_var_name(key: &str) -> String {
+    let mut name = CONFIG_ENV_PREFIX.to_string();
+    for (index, segment) in key.split('.').enumerate() {
+        if index > 0 {
+            name.push('_');
+        }
+        for ch in segment.chars() {
+            if ch.is_ascii_uppercase() {
+                name.push('_');
+            }
+            name.push(ch.to_ascii_uppercase());
+        }
+    }
+    name
+}
+
+fn parse_setting_value(raw: &str, default: &Value) -> Option<Value> {
+    let raw = raw.trim();
+    match default {
+        Value::Bool(_) => match raw.to_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => Some(Value::Bool(true)),
+            "false" | "0" | "no" | "off" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        Value::Number(_) => raw
+            .parse::<u64>()
+            .map(Value::from)
+            .ok()
+            .or_else(|| raw.parse::<f64>().ok().map(Value::from)),
+        Value::Array(_) if raw.starts_with('[') => serde_json::from_str(raw).ok(),
+        Value::Array(_) => Some(Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| Value::String(item.to_string()))
+                .collect(),
+        )),
+        _ => Some(Value::String(raw.to_string())),
+    }
+}
+
+#[must_use]
+pub fn env_config_layers<I>(vars: I) -> Vec<ConfigLayer>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let mut known = Vec::new();
+    flatten_config_value(
+        &serde_json::to_value(MemoryConfig::default()).unwrap_or(Value::Null),
+        "",
+        &mut known,
+    );
+    let mut vars = vars
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(CONFIG_ENV_PREFIX))
+        .collect::<Vec<_>>();
+    vars.sort();
+
+    let mut layers = Vec::new();
+    for (name, raw) in vars {
+        let Some((key, default)) = known
+            .iter()
+            .filter(|(key, _)| key != CONFIG_VERSION_KEY)
+            .find(|(key, _)| env_var_name(key) == name)
+        else {
+            continue;
+        };
+        let mut values = Value::Object(Map::new());
+        let Some(value) = parse_setting_value(&raw, default) else {
+            eprintln!("codex-extra-memory: {name}: cannot parse '{raw}'; ignoring it.");
+            continue;
+        };
+        set_config_value(&mut values, key, value);
+        if let Err(error) = validate_config_values(&values) {
+            eprintln!("codex-extra-memory: {name}: {error}; ignoring it.");
+            continue;
+        }
+        layers.push(ConfigLayer {
+            origin: "env".to_string(),
+            source: name,
+            values,
+            etag: None,
+        });
+    }
+    layers
+}
+
+#[must_use]
+pub fn repo_config_layer(root: &Path) -> Option<ConfigLayer> {
+    let path = root.join(SCOPE_MARKER_FILE);
+    let raw = fs::read_to_string(&path).ok()?;
+    let mut values = match raw
+        .parse::<DocumentMut>()
+        .map(|doc| toml_table_to_json(doc.as_table()))
+    {
+        Ok(values) => values,
+        Err(error) => {
+            eprintln!(
+                "codex-extra-memory: invalid {} ({error}); ignoring its settings.",
+                path.display()
+            );
+            return None;
+        }
+    };
+    let map = values.as_object_mut()?;
+    for key in MARKER_ONLY_KEYS {
+        map.remove(*key);
+    }
+    for key in GLOBAL_ONLY_KEYS {
+        if remove_config_value(&mut values, key).is_some() {
+            eprintln!(
+                "codex-extra-memory: {}: {key} is only read from the global config; ignoring it.",
+                path.display()
+            );
+        }
+    }
+    for key in REPO_DISABLE_ONLY_KEYS {
+        if values
+            .pointer(&config_pointer(key))
+            .is_some_and(|value| value != &Value::Bool(false))
+        {
+            remove_config_value(&mut values, key);
+            eprintln!(
+                "codex-extra-memory: {}: {key} can only be turned off from a repo file; ignoring it.",
+                path.display()
+            );
+        }
+    }
+    migrate_config_values(&mut values);
+    if let Some(map) = values.as_object_mut() {
+        map.remove(CONFIG_VERSION_KEY);
+    }
+    drop_invalid_keys(&mut values, &path.to_string_lossy());
+    warn_unknown_keys(&values, &path.to_string_lossy());
+    if values.as_object().is_none_or(Map::is_empty) {
+        return None;
+    }
+    Some(ConfigLayer {
+        origin: "repo".to_string(),
+        source: path.to_string_lossy().to_string(),
+        values,
+        etag: None,
+    })
+}
+
+fn next_backup_path(config_path: &Path, label: &str) -> anyhow::Result<PathBuf> {
     let Some(parent) = config_path.parent() else {
         anyhow::bail!("invalid config path")
     };
     let stamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+    let ext = if is_toml_path(config_path) {
+        "toml"
+    } else {
+        "json"
+    };
 
     for suffix in 0..10_000 {
         let file_name = if suffix == 0 {
-            format!("config.invalid-{stamp}.json.bak")
+            format!("config.{label}-{stamp}.{ext}.bak")
         } else {
-            format!("config.invalid-{stamp}-{suffix}.json.bak")
+            format!("config.{label}-{stamp}-{suffix}.{ext}.bak")
         };
         let candidate = parent.join(file_name);
         if !candidate.exists() {
@@ -240,11 +1312,11 @@ fn next_invalid_backup_path(config_path: &Path) -> anyhow::Result<PathBuf> {
         }
     }
 
-    anyhow::bail!("could not allocate invalid config backup path")
+    anyhow::bail!("could not allocate {label} config backup path")
 }
 
 fn backup_invalid_config(config_path: &Path) -> anyhow::Result<PathBuf> {
-    let backup_path = next_invalid_backup_path(config_path)?;
+    let backup_path = next_backup_path(config_path, "invalid")?;
     fs::rename(config_path, &backup_path).with_context(|| {
         format!(
             "backup invalid config {} to {}",
@@ -255,65 +1327,327 @@ fn backup_invalid_config(config_path: &Path) -> anyhow::Result<PathBuf> {
     Ok(backup_path)
 }
 
+pub fn backup_config_before_upgrade(config_path: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let Ok(raw) = fs::read_to_string(config_path) else {
+        return Ok(None);
+    };
+    let Ok(values) = parse_config_text(config_path, &raw) else {
+        return Ok(None);
+    };
+    let from = config_document_version(&values);
+    if from >= CONFIG_VERSION {
+        return Ok(None);
+    }
+    let backup_path = next_backup_path(config_path, &format!("v{from}"))?;
+    fs::copy(config_path, &backup_path).with_context(|| {
+        format!(
+            "backup config {} to {}",
+            config_path.display(),
+            backup_path.display()
+        )
+    })?;
+    Ok(Some(backup_path))
+}
+
+pub fn migrate_config_file_at(
+    config_path: &Path,
+    dry_run: bool,
+) -> anyhow::Result<ConfigMigration> {
+    let raw = fs::read_to_string(config_path)
+        .with_context(|| format!("read config {}", config_path.display()))?;
+    let original = parse_config_text(config_path, &raw)
+        .with_context(|| format!("parse config {}", config_path.display()))?;
+    let mut values = original.clone();
+    let from_version = config_document_version(&values);
+    if from_version > CONFIG_VERSION {
+        anyhow::bail!(
+            "config {} is version {from_version}, newer than the supported version {CONFIG_VERSION}",
+            config_path.display()
+        );
+    }
+    let changes = migrate_config_values(&mut values);
+    let mut migration = ConfigMigration {
+        path: config_path.to_string_lossy().to_string(),
+        from_version,
+        to_version: CONFIG_VERSION,
+        changes,
+        unknown_keys: unknown_config_keys(&values),
+        dry_run,
+        backup: None,
+    };
+    if dry_run || from_version == CONFIG_VERSION {
+        return Ok(migration);
+    }
+    migration.backup =
+        backup_config_before_upgrade(config_path)?.map(|path| path.to_string_lossy().to_string());
+    // As in `edit_config_file_at`, TOML keeps its comments and only changed keys are rewritten.
+    let text = if is_toml_path(config_path) {
+        let mut doc = raw.parse::<DocumentMut>()?;
+        sync_toml_top_level(&mut doc, &original, &values);
+        doc.to_string()
+    } else {
+        render_config_text(config_path, &values)?
+    };
+    write_config_text_at(config_path, &text, Some(&sha256(&raw)))?;
+    Ok(migration)
+}
+
+fn prepare_global_values(config_path: &Path, raw: &str) -> anyhow::Result<Value> {
+    let mut values = parse_config_text(config_path, raw)?;
+    let source = config_path.to_string_lossy();
+    let version = config_document_version(&values);
+    if version < CONFIG_VERSION {
+        eprintln!(
+            "codex-extra-memory: {source} is config version {version}; upgrading it in memory to {CONFIG_VERSION}. Run `/memory config migrate` to rewrite the file."
+        );
+    } else if version > CONFIG_VERSION {
+        eprintln!(
+            "codex-extra-memory: {source} is config version {version}, newer than the supported version {CONFIG_VERSION}; settings this version does not know are ignored."
+        );
+    }
+    migrate_config_values(&mut values);
+    drop_invalid_keys(&mut values, &source);
+    warn_unknown_keys(&values, &source);
+    Ok(values)
+}
+
+// Unlike `load_config_layer_at`, a missing or unparsable file is an error here and is
+// left untouched, so a half-saved edit never gets backed up and replaced by defaults.
+pub fn reload_config_layer_at(config_path: &Path) -> anyhow::Result<ConfigLayer> {
+    let raw = fs::read_to_string(config_path)
+        .with_context(|| format!("read config {}", config_path.display()))?;
+    let values = prepare_global_values(config_path, &raw)
+        .with_context(|| format!("parse config {}", config_path.display()))?;
+    Ok(ConfigLayer {
+        origin: "global".to_string(),
+        source: config_path.to_string_lossy().to_string(),
+        values,
+        etag: Some(sha256(&raw)),
+    })
+}
+
+pub fn load_config_layer_at(config_path: &Path) -> anyhow::Result<ConfigLayer> {
+    let Some(parent) = config_path.parent() else {
+        anyhow::bail!("invalid config path")
+    };
+    fs::create_dir_all(parent)
+        .with_context(|| format!("create config dir {}", parent.display()))?;
+
+    let loaded = if config_path.exists() {
+        let raw = fs::read_to_string(config_path)
+            .with_context(|| format!("read config {}", config_path.display()))?;
+        match prepare_global_values(config_path, &raw) {
+            Ok(values) => Some((values, sha256(&raw))),
+            Err(error) => {
+                let backup_path = backup_invalid_config(config_path)?;
+                eprintln!(
+                    "codex-extra-memory: invalid config at {} ({}). Backed up to {} and regenerated defaults.",
+                    config_path.display(),
+                    error,
+                    backup_path.display()
+                );
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let (values, etag) = if let Some(loaded) = loaded {
+        loaded
+    } else {
+        let values = serde_json::to_value(MemoryConfig::default())?;
+        let etag = save_config_values_at(config_path, &values, None)?;
+        (values, etag)
+    };
+    Ok(ConfigLayer {
+        origin: "global".to_string(),
+        source: config_path.to_string_lossy().to_string(),
+        values,
+        etag: Some(etag),
+    })
+}
+
 pub fn load_config_file_at(config_path: &Path) -> anyhow::Result<MemoryConfig> {
+    Ok(LayeredConfig::new(vec![load_config_layer_at(config_path)?]).resolve())
+}
+
+#[must_use]
+pub fn config_etag(config_path: &Path) -> Option<String> {
+    fs::read_to_string(config_path).ok().map(|raw| sha256(&raw))
+}
+
+fn render_config_text(config_path: &Path, values: &Value) -> anyhow::Result<String> {
+    if is_toml_path(config_path) {
+        let mut doc = DocumentMut::new();
+        if let Some(map) = values.as_object() {
+            *doc.as_table_mut() = json_to_toml_table(map);
+        }
+        Ok(doc.to_string())
+    } else {
+        Ok(format!("{}\n", serde_json::to_string_pretty(values)?))
+    }
+}
+
+pub fn save_config_values_at(
+    config_path: &Path,
+    values: &Value,
+    expected_etag: Option<&str>,
+) -> anyhow::Result<String> {
+    write_config_text_at(
+        config_path,
+        &render_config_text(config_path, values)?,
+        expected_etag,
+    )
+}
+
+fn write_config_text_at(
+    config_path: &Path,
+    text: &str,
+    expected_etag: Option<&str>,
+) -> anyhow::Result<String> {
     let Some(parent) = config_path.parent() else {
         anyhow::bail!("invalid config path")
     };
     fs::create_dir_all(parent)
         .with_context(|| format!("create config dir {}", parent.display()))?;
+    let file_name = config_path
+        .file_name()
+        .map_or_else(|| "config".into(), |name| name.to_string_lossy());
+    let temp_path = parent.join(format!(".{file_name}.{}.tmp", std::process::id()));
+    fs::write(&temp_path, text).with_context(|| format!("write config {}", temp_path.display()))?;
 
-    if !config_path.exists() {
-        let default = MemoryConfig::default();
-        save_config_file_at(config_path, &default)?;
-        return Ok(default);
+    if let Some(expected) = expected_etag
+        && config_etag(config_path).as_deref() != Some(expected)
+    {
+        let _ = fs::remove_file(&temp_path);
+        anyhow::bail!(
+            "config {} changed on disk since it was loaded; not overwriting it",
+            config_path.display()
+        );
     }
+    fs::rename(&temp_path, config_path).with_context(|| {
+        format!(
+            "replace config {} with {}",
+            config_path.display(),
+            temp_path.display()
+        )
+    })?;
+    Ok(sha256(text))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigEdit {
+    Set(String, Value),
+    Unset(String),
+}
 
+// Applies edits to the file as it is on disk, so keys that failed validation and TOML
+// comments survive. Older documents are backed up and migrated first; in TOML only the
+// top-level keys a migration changed are rewritten.
+pub fn edit_config_file_at(
+    config_path: &Path,
+    expected_etag: &str,
+    edits: &[ConfigEdit],
+) -> anyhow::Result<(ConfigLayer, bool)> {
     let raw = fs::read_to_string(config_path)
         .with_context(|| format!("read config {}", config_path.display()))?;
-
-    match serde_json::from_str::<PartialMemoryConfig>(&raw) {
-        Ok(parsed) => Ok(normalize_config(parsed)),
-        Err(error) => {
-            let backup_path = backup_invalid_config(config_path)?;
+    if sha256(&raw) != expected_etag {
+        anyhow::bail!(
+            "config {} changed on disk since it was loaded; not overwriting it",
+            config_path.display()
+        );
+    }
+    let original = parse_config_text(config_path, &raw)
+        .with_context(|| format!("parse config {}", config_path.display()))?;
+    let mut values = original.clone();
+    let outdated = config_document_version(&original) < CONFIG_VERSION;
+    if outdated {
+        if let Some(backup) = backup_config_before_upgrade(config_path)? {
             eprintln!(
-                "codex-extra-memory: invalid config at {} ({}). Backed up to {} and regenerated defaults.",
+                "codex-extra-memory: upgrading {} to config version {CONFIG_VERSION}; the previous file is kept at {}.",
                 config_path.display(),
-                error,
-                backup_path.display()
+                backup.display()
             );
-            let default = MemoryConfig::default();
-            save_config_file_at(config_path, &default)?;
-            Ok(default)
         }
+        migrate_config_values(&mut values);
     }
-}
 
-pub fn save_config_file_at(config_path: &Path, config: &MemoryConfig) -> anyhow::Result<()> {
-    let Some(parent) = config_path.parent() else {
-        anyhow::bail!("invalid config path")
+    let mut changed = false;
+    let text = if is_toml_path(config_path) {
+        let mut doc = raw.parse::<DocumentMut>()?;
+        if outdated {
+            sync_toml_top_level(&mut doc, &original, &values);
+        }
+        for edit in edits {
+            changed |= match edit {
+                ConfigEdit::Set(key, value) => {
+                    set_toml_value(&mut doc, key, value);
+                    true
+                }
+                ConfigEdit::Unset(key) => remove_toml_value(&mut doc, key),
+            };
+        }
+        doc.to_string()
+    } else {
+        for edit in edits {
+            changed |= match edit {
+                ConfigEdit::Set(key, value) => {
+                    set_config_value(&mut values, key, value.clone());
+                    true
+                }
+                ConfigEdit::Unset(key) => remove_config_value(&mut values, key).is_some(),
+            };
+        }
+        render_config_text(config_path, &values)?
     };
-    fs::create_dir_all(parent)
-        .with_context(|| format!("create config dir {}", parent.display()))?;
-    let text = serde_json::to_string_pretty(config)?;
-    fs::write(config_path, format!("{text}\n"))
-        .with_context(|| format!("write config {}", config_path.display()))?;
-    Ok(())
+
+    let etag = write_config_text_at(config_path, &text, Some(expected_etag))?;
+    let layer = ConfigLayer {
+        origin: "global".to_string(),
+        source: config_path.to_string_lossy().to_string(),
+        values: prepare_global_values(config_path, &text)?,
+        etag: Some(etag),
+    };
+    Ok((layer, changed))
+}
+
+pub fn save_config_file_at(
+    config_path: &Path,
+    config: &MemoryConfig,
+    expected_etag: Option<&str>,
+) -> anyhow::Result<String> {
+    save_config_values_at(config_path, &serde_json::to_value(config)?, expected_etag)
+}
+
+pub fn load_layered_config(memory_dir: &Path) -> anyhow::Result<LayeredConfig> {
+    let mut layers = vec![load_config_layer_at(&global_config_path(memory_dir))?];
+    layers.extend(env_config_layers(std::env::vars()));
+    Ok(LayeredConfig::new(layers))
+}
+
+pub fn reload_layered_config(memory_dir: &Path) -> anyhow::Result<LayeredConfig> {
+    let mut layers = vec![reload_config_layer_at(&global_config_path(memory_dir))?];
+    layers.extend(env_config_layers(std::env::vars()));
+    Ok(LayeredConfig::new(layers))
 }
 
 pub fn load_config_file() -> anyhow::Result<MemoryConfig> {
     let memory_dir = get_memory_dir();
     fs::create_dir_all(&memory_dir)
         .with_context(|| format!("create memory dir {}", memory_dir.display()))?;
-    load_config_file_at(&get_config_path())
-}
-
-pub fn save_config_file(config: &MemoryConfig) -> anyhow::Result<()> {
-    save_config_file_at(&get_config_path(), config)
+    Ok(load_layered_config(&memory_dir)?.resolve())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{MemoryConfig, load_config_file_at};
+    use super::{
+        CONFIG_MIGRATIONS, CONFIG_VERSION, ConfigEdit, LayeredConfig, MemoryConfig, config_etag,
+        config_schema, edit_config_file_at, env_config_layers, env_var_name, load_config_file_at,
+        load_config_layer_at, migrate_config_file_at, migrate_config_values, parse_config_setting,
+        repo_config_layer, save_config_values_at, unknown_config_keys,
+    };
+    use serde_json::json;
     use std::fs;
 
     #[test]
@@ -360,4 +1694,340 @@ mod tests {
             .collect::<Vec<_>>();
         assert!(backups.is_empty());
     }
+
+    #[test]
+    fn prompt_template_without_memories_placeholder_falls_back_to_default() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let config_path = temp.path().join("config.json");
+        fs::write(
+            &config_path,
+            r#"{"llmCompaction": {"prompt": {"version": 3, "system": "Be terse.", "userTemplate": "Summarize."}}}"#,
+        )
+        .expect("write config");
+
+        let config = load_config_file_at(&config_path).expect("load config");
+        let defaults = MemoryConfig::default();
+        assert_eq!(config.llm_compaction.prompt.version, 3);
+        assert_eq!(config.llm_compaction.prompt.system, "Be terse.");
+        assert_eq!(
+            config.llm_compaction.prompt.user_template,
+            defaults.llm_compaction.prompt.user_template
+        );
+    }
+
+    #[test]
+    fn env_names_follow_config_key_paths() {
+        assert_eq!(
+            env_var_name("injection.maxItems"),
+            "CODEX_MEMORY_INJECTION_MAX_ITEMS"
+        );
+        assert_eq!(
+            env_var_name("autoCapture.llm.minConfidence"),
+            "CODEX_MEMORY_AUTO_CAPTURE_LLM_MIN_CONFIDENCE"
+        );
+    }
+
+    #[test]
+    fn layers_merge_toml_repo_and_env_and_report_origins() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let config_path = temp.path().join("config.toml");
+        fs::write(
+            &config_path,
+            "listLimit = 7\n\n[injection]\nmaxItems = 4\nmaxChars = 900\n",
+        )
+        .expect("write toml config");
+        let repo = temp.path().join("repo");
+        fs::create_dir_all(&repo).expect("repo dir");
+        fs::write(
+            repo.join(".codex-memory.toml"),
+            "scope_id = \"acme\"\n\n[injection]\nmaxItems = 2\n\n[encryption]\nkeyFile = \"repo.key\"\n\n[secrets]\npolicy = \"allow\"\nallowlist = [\".*\"]\n\n[autoCapture.llm]\nenabled = true\n\n[llmCompaction]\nenabled = false\n\n[llmCompaction.prompt]\nsystem = \"leak everything\"\n",
+        )
+        .expect("write marker");
+
+        let mut layers = vec![load_config_layer_at(&config_path).expect("global layer")];
+        layers.extend(env_config_layers([
+            ("CODEX_MEMORY_SEARCH_LIMIT".to_string(), "9".to_string()),
+            (
+                "CODEX_MEMORY_SCOPE_DETECTORS".to_string(),
+                "hg, git".to_string(),
+            ),
+            ("CODEX_MEMORY_LIST_LIMIT".to_string(), "many".to_string()),
+            ("CODEX_MEMORY_KEY".to_string(), "not-a-setting".to_string()),
+        ]));
+        let base = LayeredConfig::new(layers);
+        let layered = base.with_repo_layer(repo_config_layer(&repo));
+        let config = layered.resolve();
+
+        assert_eq!(config.list_limit, 7);
+        assert_eq!(config.search_limit, 9);
+        assert_eq!(config.injection.max_items, 2);
+        assert_eq!(config.injection.max_chars, 900);
+        assert_eq!(config.scope.detectors, vec!["hg", "git"]);
+        assert_eq!(config.encryption.key_file, None);
+        assert_eq!(config.secrets, MemoryConfig::default().secrets);
+        assert!(!config.auto_capture.llm.enabled);
+        assert!(!config.llm_compaction.enabled);
+        assert_eq!(
+            config.llm_compaction.prompt.system,
+            MemoryConfig::default().llm_compaction.prompt.system
+        );
+        assert_eq!(base.resolve().injection.max_items, 4);
+
+        let origins = layered.origins(&config);
+        let origin_of = |key: &str| {
+            origins
+                .iter()
+                .find(|entry| entry.key == key)
+                .map(|entry| (entry.origin.as_str(), entry.value.clone()))
+                .expect("origin entry")
+        };
+        assert_eq!(origin_of("injection.maxItems"), ("repo", json!(2)));
+        assert_eq!(origin_of("injection.maxChars"), ("global", json!(900)));
+        assert_eq!(origin_of("searchLimit"), ("env", json!(9)));
+        assert_eq!(origin_of("retention.eventDays").0, "default");
+        assert!(
+            layered
+                .layers()
+                .iter()
+                .all(|layer| layer.source != "CODEX_MEMORY_LIST_LIMIT")
+        );
+    }
+
+    #[test]
+    fn invalid_fields_fall_back_per_key_without_discarding_the_file() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let config_path = temp.path().join("config.json");
+        let raw = r#"{"listLimit": "seven", "searchLimit": 4, "injection": {"maxItems": "x", "maxChars": 500}}"#;
+        fs::write(&config_path, raw).expect("write config");
+
+        let config = load_config_file_at(&config_path).expect("load config");
+        let defaults = MemoryConfig::default();
+        assert_eq!(config.list_limit, defaults.list_limit);
+        assert_eq!(config.search_limit, 4);
+        assert_eq!(config.injection.max_items, defaults.injection.max_items);
+        assert_eq!(config.injection.max_chars, 500);
+        assert_eq!(fs::read_to_string(&config_path).expect("read config"), raw);
+        assert_eq!(fs::read_dir(temp.path()).expect("read dir").count(), 1);
+    }
+
+    #[test]
+    fn settings_are_validated_against_their_type_and_range() {
+        let values = json!({});
+        assert_eq!(
+            parse_config_setting(&values, "injection.maxItems", "5"),
+            Ok(json!(5))
+        );
+        assert_eq!(
+            parse_config_setting(&values, "autoCapture.enabled", "off"),
+            Ok(json!(false))
+        );
+        assert_eq!(
+            parse_config_setting(&values, "scope.detectors", "hg, git"),
+            Ok(json!(["hg", "git"]))
+        );
+        assert_eq!(
+            parse_config_setting(&values, "secrets", "redact"),
+            Ok(json!("redact"))
+        );
+        for (key, raw) in [
+            ("injection.maxItems", "0"),
+            ("injection.maxItems", "lots"),
+            ("autoCapture.scope", "galaxy"),
+            ("autoCapture.llm.minConfidence", "1.5"),
+            ("scope.detectors", "svn"),
+            ("injection.maxItemz", "5"),
+        ] {
+            let error = parse_config_setting(&values, key, raw).expect_err(key);
+            assert!(error.contains(key), "{error}");
+        }
+    }
+
+    #[test]
+    fn schema_describes_every_section_without_required_keys() {
+        let schema = config_schema();
+        let text = schema.to_string();
+        assert!(!text.contains("\"required\""));
+        for section in [
+            "injection",
+            "autoCapture",
+            "llmCompaction",
+            "secrets",
+            "scope",
+        ] {
+            assert!(schema["properties"].get(section).is_some(), "{section}");
+        }
+        assert!(schema["properties"]["secrets"]["anyOf"].is_array());
+    }
+
+    #[test]
+    fn save_replaces_atomically_and_refuses_stale_etags() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let config_path = temp.path().join("config.json");
+        let layer = load_config_layer_at(&config_path).expect("default layer");
+        let loaded = layer.etag.clone().expect("etag");
+        assert_eq!(config_etag(&config_path).as_deref(), Some(loaded.as_str()));
+
+        fs::write(&config_path, r#"{"listLimit": 9}"#).expect("concurrent edit");
+        let stale = save_config_values_at(&config_path, &json!({"listLimit": 3}), Some(&loaded));
+        assert!(stale.is_err());
+        assert_eq!(
+            fs::read_to_string(&config_path).expect("read config"),
+            r#"{"listLimit": 9}"#
+        );
+
+        let current = config_etag(&config_path).expect("current etag");
+        let saved = save_config_values_at(&config_path, &json!({"listLimit": 3}), Some(&current))
+            .expect("save with current etag");
+        assert_eq!(config_etag(&config_path), Some(saved));
+        let names = fs::read_dir(temp.path())
+            .expect("read dir")
+            .filter_map(Result::ok)
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["config.json".to_string()]);
+    }
+
+    #[test]
+    fn old_documents_migrate_in_memory_and_on_request_with_a_backup() {
+        assert_eq!(CONFIG_VERSION, CONFIG_MIGRATIONS.len() as u64 + 1);
+        let mut values = json!({"secrets": "redact", "injection": {"maxItem": 4}});
+        let changes = migrate_config_values(&mut values);
+        assert_eq!(
+            changes,
+            vec![r#"v1 -> v2: secrets = "redact" -> secrets.policy = "redact""#.to_string()]
+        );
+        assert_eq!(values["secrets"], json!({"policy": "redact"}));
+        assert_eq!(values["configVersion"], json!(CONFIG_VERSION));
+        assert!(migrate_config_values(&mut values).is_empty());
+        assert_eq!(unknown_config_keys(&values), vec!["injection.maxItem"]);
+
+        let temp = tempfile::tempdir().expect("tempdir");
+        let config_path = temp.path().join("config.json");
+        let original = r#"{"secrets": "redact", "listLimit": 7, "listLimt": 8}"#;
+        fs::write(&config_path, original).expect("write v1 config");
+        let config = load_config_file_at(&config_path).expect("load v1 config");
+        assert_eq!(config.secrets.policy, crate::types::SecretPolicy::Redact);
+        assert_eq!(config.list_limit, 7);
+        assert_eq!(fs::read_to_string(&config_path).expect("read"), original);
+
+        let dry_run = migrate_config_file_at(&config_path, true).expect("dry run");
+        assert_eq!(
+            (dry_run.from_version, dry_run.to_version),
+            (1, CONFIG_VERSION)
+        );
+        assert_eq!(dry_run.changes.len(), 1);
+        assert_eq!(dry_run.unknown_keys, vec!["listLimt"]);
+        assert!(dry_run.backup.is_none());
+        assert_eq!(fs::read_to_string(&config_path).expect("read"), original);
+
+        let migrated = migrate_config_file_at(&config_path, false).expect("migrate");
+        let backup = migrated.backup.expect("backup path");
+        assert!(backup.contains("config.v1-") && backup.ends_with(".json.bak"));
+        assert_eq!(fs::read_to_string(&backup).expect("read backup"), original);
+        let saved: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&config_path).expect("read"))
+                .expect("saved json");
+        assert_eq!(saved["configVersion"], json!(CONFIG_VERSION));
+        assert_eq!(saved["secrets"], json!({"policy": "redact"}));
+        assert_eq!(saved["listLimt"], json!(8));
+
+        let again = migrate_config_file_at(&config_path, false).expect("migrate again");
+        assert_eq!(again.from_version, CONFIG_VERSION);
+        assert!(again.changes.is_empty() && again.backup.is_none());
+
+        fs::write(&config_path, r#"{"configVersion": 99}"#).expect("write newer config");
+        assert!(migrate_config_file_at(&config_path, false).is_err());
+    }
+
+    #[test]
+    fn toml_migration_keeps_comments_and_key_order() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let config_path = temp.path().join("config.toml");
+        fs::write(
+            &config_path,
+            "# my settings\nsecrets = \"redact\" # keep strict\nlistLimit = 7\n\n[injection]\n# fewer items\nmaxItems = 4\n",
+        )
+        .expect("write toml");
+
+        let migrated = migrate_config_file_at(&config_path, false).expect("migrate");
+        assert_eq!(migrated.changes.len(), 1);
+        let text = fs::read_to_string(&config_path).expect("read");
+        for kept in [
+            "# my settings",
+            "# keep strict",
+            "# fewer items",
+            "maxItems = 4",
+        ] {
+            assert!(text.contains(kept), "{kept} missing from:\n{text}");
+        }
+        let secrets = text.find("secrets").expect("secrets");
+        assert!(secrets < text.find("listLimit").expect("listLimit"));
+        assert!(text.contains(&format!("configVersion = {CONFIG_VERSION}")));
+        let layer = load_config_layer_at(&config_path).expect("layer");
+        assert_eq!(layer.values["secrets"], json!({"policy": "redact"}));
+    }
+
+    #[test]
+    fn edits_keep_invalid_keys_and_toml_comments_on_disk() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let config_path = temp.path().join("config.toml");
+        fs::write(
+            &config_path,
+            "# my settings\nsearchLimit = 5 # keep short\nlistLimit = 7\nsecrets = \"redact\"\n\n[injection]\n# fewer items\nmaxItems = \"lots\"\nmaxChars = 900\n",
+        )
+        .expect("write toml");
+        let layer = load_config_layer_at(&config_path).expect("layer");
+        assert!(layer.values.pointer("/injection/maxItems").is_none());
+
+        let (layer, changed) = edit_config_file_at(
+            &config_path,
+            layer.etag.as_deref().expect("etag"),
+            &[
+                ConfigEdit::Set("injection.maxChars".to_string(), json!(800)),
+                ConfigEdit::Unset("listLimit".to_string()),
+            ],
+        )
+        .expect("edit");
+        assert!(changed);
+        let text = fs::read_to_string(&config_path).expect("read");
+        for kept in [
+            "# my settings",
+            "# fewer items",
+            "maxItems = \"lots\"",
+            "maxChars = 800",
+        ] {
+            assert!(text.contains(kept), "{kept} missing from:\n{text}");
+        }
+        assert!(!text.contains("listLimit"));
+        assert!(text.contains(&format!("configVersion = {CONFIG_VERSION}")));
+        assert_eq!(layer.values["secrets"], json!({"policy": "redact"}));
+        assert_eq!(layer.values["injection"], json!({"maxChars": 800}));
+
+        let (_, changed) = edit_config_file_at(
+            &config_path,
+            layer.etag.as_deref().expect("etag"),
+            &[ConfigEdit::Unset("retention.eventDays".to_string())],
+        )
+        .expect("noop unset");
+        assert!(!changed);
+        assert!(edit_config_file_at(&config_path, "stale", &[]).is_err());
+
+        let json_path = temp.path().join("config.json");
+        fs::write(
+            &json_path,
+            r#"{"configVersion": 2, "listLimit": "x", "searchLimit": 5}"#,
+        )
+        .expect("write json");
+        let layer = load_config_layer_at(&json_path).expect("json layer");
+        edit_config_file_at(
+            &json_path,
+            layer.etag.as_deref().expect("etag"),
+            &[ConfigEdit::Set("searchLimit".to_string(), json!(6))],
+        )
+        .expect("json edit");
+        let saved: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&json_path).expect("read")).expect("json");
+        assert_eq!(saved["listLimit"], json!("x"));
+        assert_eq!(saved["searchLimit"], json!(6));
+    }
 }
@@ -0,0 +1,192 @@
+use anyhow::{Context, Result, anyhow, bail};
+use argon2::Argon2;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, rand_core::RngCore};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const KEY_ENV: &str = "CODEX_MEMORY_KEY";
+pub const KEY_FILE_ENV: &str = "CODEX_MEMORY_KEY_FILE";
+pub const PASSPHRASE_ENV: &str = "CODEX_MEMORY_PASSPHRASE";
+pub const CIPHER_NAME: &str = "xchacha20poly1305";
+
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 24;
+const SALT_LEN: usize = 16;
+
+#[derive(Clone)]
+pub enum KeySource {
+    Key([u8; 32]),
+    Passphrase(String),
+}
+
+impl KeySource {
+    #[must_use]
+    pub const fn kdf(&self) -> &'static str {
+        match self {
+            Self::Key(_) => "none",
+            Self::Passphrase(_) => "argon2id",
+        }
+    }
+}
+
+fn parse_key(raw: &[u8]) -> Result<[u8; 32]> {
+    if let Ok(bytes) = <[u8; 32]>::try_from(raw) {
+        return Ok(bytes);
+    }
+    let text = std::str::from_utf8(raw)
+        .map_err(|_| anyhow!("key must be 32 raw bytes, base64, or 64 hex characters"))?
+        .trim();
+    let decoded = if text.len() == 64 && text.chars().all(|c| c.is_ascii_hexdigit()) {
+        (0..32)
+            .map(|index| u8::from_str_radix(&text[index * 2..index * 2 + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| anyhow!("invalid hex key"))?
+    } else {
+        STANDARD
+            .decode(text)
+            .map_err(|_| anyhow!("key must be 32 raw bytes, base64, or 64 hex characters"))?
+    };
+    <[u8; 32]>::try_from(decoded.as_slice())
+        .map_err(|_| anyhow!("key must decode to 32 bytes, got {}", decoded.len()))
+}
+
+fn read_key_file(path: &Path) -> Result<[u8; 32]> {
+    let raw =
+        fs::read(path).with_context(|| format!("read encryption key file {}", path.display()))?;
+    parse_key(&raw).with_context(|| format!("encryption key file {}", path.display()))
+}
+
+pub fn resolve_key_source(config_key_file: Option<&Path>) -> Result<Option<KeySource>> {
+    if let Ok(raw) = std::env::var(KEY_ENV)
+        && !raw.trim().is_empty()
+    {
+        return Ok(Some(KeySource::Key(
+            parse_key(raw.as_bytes()).context(KEY_ENV)?,
+        )));
+    }
+    if let Ok(path) = std::env::var(KEY_FILE_ENV)
+        && !path.trim().is_empty()
+    {
+        return Ok(Some(KeySource::Key(read_key_file(&PathBuf::from(
+            path.trim(),
+        ))?)));
+    }
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV)
+        && !passphrase.is_empty()
+    {
+        return Ok(Some(KeySource::Passphrase(passphrase)));
+    }
+    config_key_file
+        .map(|path| read_key_file(path).map(KeySource::Key))
+        .transpose()
+}
+
+pub fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0_u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|error| anyhow!("derive key from passphrase: {error}"))?;
+    Ok(key)
+}
+
+#[must_use]
+pub fn generate_salt() -> String {
+    let mut salt = [0_u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    STANDARD.encode(salt)
+}
+
+#[must_use]
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+pub struct ContentCipher {
+    cipher: XChaCha20Poly1305,
+}
+
+impl ContentCipher {
+    #[must_use]
+    pub fn new(key: &[u8; 32]) -> Self {
+        Self {
+            cipher: XChaCha20Poly1305::new(key.into()),
+        }
+    }
+
+    pub fn from_source(source: &KeySource, salt: Option<&str>) -> Result<Self> {
+        match source {
+            KeySource::Key(key) => Ok(Self::new(key)),
+            KeySource::Passphrase(passphrase) => {
+                let salt = salt.ok_or_else(|| anyhow!("missing key derivation salt"))?;
+                let salt = STANDARD
+                    .decode(salt)
+                    .context("invalid key derivation salt")?;
+                Ok(Self::new(&derive_key(passphrase, &salt)?))
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn encrypt(&self, plaintext: &str) -> String {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .expect("xchacha20poly1305 encryption is infallible for in-memory buffers");
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        format!("{ENCRYPTED_PREFIX}{}", STANDARD.encode(sealed))
+    }
+
+    pub fn decrypt(&self, value: &str) -> Result<String> {
+        let Some(encoded) = value.strip_prefix(ENCRYPTED_PREFIX) else {
+            return Ok(value.to_string());
+        };
+        let sealed = STANDARD
+            .decode(encoded)
+            .context("encrypted value is not valid base64")?;
+        if sealed.len() < NONCE_LEN {
+            bail!("encrypted value is truncated");
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("decryption failed: wrong key or corrupted value"))?;
+        String::from_utf8(plaintext).context("decrypted value is not UTF-8")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ContentCipher, is_encrypted, parse_key};
+
+    #[test]
+    fn round_trips_and_rejects_the_wrong_key() {
+        let cipher = ContentCipher::new(&[7_u8; 32]);
+        let sealed = cipher.encrypt("use pnpm in this repo");
+        assert!(is_encrypted(&sealed));
+        assert_ne!(sealed, cipher.encrypt("use pnpm in this repo"));
+        assert_eq!(
+            cipher.decrypt(&sealed).expect("decrypt"),
+            "use pnpm in this repo"
+        );
+        assert_eq!(cipher.decrypt("plain").expect("passthrough"), "plain");
+        assert!(ContentCipher::new(&[8_u8; 32]).decrypt(&sealed).is_err());
+    }
+
+    #[test]
+    fn parses_base64_hex_and_raw_keys() {
+        let hex = "07".repeat(32);
+        assert_eq!(parse_key(hex.as_bytes()).expect("hex"), [7_u8; 32]);
+        assert_eq!(
+            parse_key(b"BwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwc=\n").expect("base64"),
+            [7_u8; 32]
+        );
+        assert_eq!(parse_key(&[7_u8; 32]).expect("raw"), [7_u8; 32]);
+        assert!(parse_key(b"too-short").is_err());
+    }
+}
@@ -1,11 +1,15 @@
 pub mod agents_sync;
 pub mod autocapture;
+pub mod backfill;
 pub mod commands;
 pub mod config;
+pub mod crypto;
 pub mod llm;
+pub mod locale;
 pub mod paths;
 pub mod render;
 pub mod scope;
+pub mod secrets;
 pub mod service;
 pub mod store;
 pub mod types;
@@ -14,7 +18,8 @@ pub mod utils;
 pub use config::MemoryConfig;
 pub use service::MemoryService;
 pub use types::{
-    AddMemoryInput, AddMemoryResult, AutoCaptureCandidate, AutoCaptureConfig, CompactionMode,
-    CompactionResult, MemoryCategory, MemoryRow, MemoryStats, ResolveIdResult, ScopeInfo,
-    ScopeTarget, SyncAgentsResult,
+    AddMemoryInput, AddMemoryResult, AutoCaptureCandidate, AutoCaptureConfig, AutoCaptureLlmConfig,
+    AutoCaptureRule, CaptureAction, CompactionMode, CompactionResult, MemoryCategory, MemoryRow,
+    MemoryStats, ResolveIdResult, ScopeInfo, ScopeTarget, SyncAgentsResult, SyncJobState,
+    SyncJobStatus,
 };
@@ -0,0 +1,331 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    En,
+    Nb,
+    De,
+    Fr,
+    Es,
+}
+
+pub const ALL_LANGUAGES: [Language; 5] = [
+    Language::En,
+    Language::Nb,
+    Language::De,
+    Language::Fr,
+    Language::Es,
+];
+
+impl Language {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Nb => "nb",
+            Self::De => "de",
+            Self::Fr => "fr",
+            Self::Es => "es",
+        }
+    }
+
+    const fn index(self) -> usize {
+        match self {
+            Self::En => 0,
+            Self::Nb => 1,
+            Self::De => 2,
+            Self::Fr => 3,
+            Self::Es => 4,
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "en" => Ok(Self::En),
+            "nb" | "no" => Ok(Self::Nb),
+            "de" => Ok(Self::De),
+            "fr" => Ok(Self::Fr),
+            "es" => Ok(Self::Es),
+            other => Err(format!(
+                "Unknown language '{other}'. Allowed: auto, en, nb, de, fr, es"
+            )),
+        }
+    }
+}
+
+pub struct LanguagePatterns {
+    pub remember: Regex,
+    pub preference: Regex,
+    pub forget: Regex,
+    pub assistant_marker: Regex,
+    pub preference_keywords: &'static [&'static str],
+    pub workflow_keywords: &'static [&'static str],
+    pub constraint_keywords: &'static [&'static str],
+    stopwords: &'static [&'static str],
+    letters: &'static [char],
+}
+
+fn build(
+    remember: &str,
+    preference: &str,
+    forget: &str,
+    assistant_marker: &str,
+) -> (Regex, Regex, Regex, Regex) {
+    (
+        Regex::new(remember).expect("valid regex"),
+        Regex::new(preference).expect("valid regex"),
+        Regex::new(forget).expect("valid regex"),
+        Regex::new(assistant_marker).expect("valid regex"),
+    )
+}
+
+fn all_patterns() -> &'static [LanguagePatterns; 5] {
+    static PATTERNS: OnceLock<[LanguagePatterns; 5]> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        let en = build(
+            r"(?i)(?:please\s+)?remember(?:\s+that)?\s+(.+)",
+            r"(?i)(?:my\s+preference\s+is|i\s+prefer)\s+(.+)",
+            r"(?i)^(?:please\s+)?forget(?:\s+(?:that|about))?\s+(.+)$",
+            r"(?i)^(?:memory|remember)\s*:\s*(.+)$",
+        );
+        let nb = build(
+            r"(?i)(?:husk(?:\s+på)?|ikke\s+glem)(?:\s+at)?\s+(.+)",
+            r"(?i)(?:jeg\s+foretrekker|min\s+preferanse\s+er|jeg\s+liker\s+best)\s+(.+)",
+            r"(?i)^(?:vennligst\s+)?glem(?:\s+at|\s+det\s+med)?\s+(.+)$",
+            r"(?i)^(?:husk|minne)\s*:\s*(.+)$",
+        );
+        let de = build(
+            r"(?i)(?:bitte\s+)?(?:merk\s+dir|merke\s+dir|denk\s+daran|vergiss\s+nicht),?(?:\s+dass)?\s+(.+)",
+            r"(?i)(?:ich\s+bevorzuge|ich\s+mag\s+lieber|meine\s+präferenz\s+ist|mir\s+ist\s+lieber)\s+(.+)",
+            r"(?i)^(?:bitte\s+)?vergiss,?\s+(?:dass|das\s+mit|den|die|das)\s+(.+)$",
+            r"(?i)^(?:merke|erinnerung|gedächtnis)\s*:\s*(.+)$",
+        );
+        let fr = build(
+            r"(?i)(?:souviens-toi|rappelle-toi|retiens|n'oublie\s+pas|n’oublie\s+pas)(?:\s+que|\s+qu'|\s+qu’)?\s*(.+)",
+            r"(?i)(?:je\s+préfère|ma\s+préférence\s+est)\s+(.+)",
+            r"(?i)^(?:s'il\s+te\s+plaît,?\s+)?oublie(?:z)?\s+(?:que|qu'|qu’|ça|le\s+fait\s+que)\s*(.+)$",
+            r"(?i)^(?:mémoire|à\s+retenir)\s*:\s*(.+)$",
+        );
+        let es = build(
+            r"(?i)(?:recuerda|acuérdate\s+de|no\s+olvides)(?:\s+que)?\s+(.+)",
+            r"(?i)(?:prefiero|mi\s+preferencia\s+es)\s+(.+)",
+            r"(?i)^(?:por\s+favor,?\s+)?olvid(?:a|e)\s+(?:que|lo\s+de)\s+(.+)$",
+            r"(?i)^(?:memoria|recuerda)\s*:\s*(.+)$",
+        );
+
+        [
+            LanguagePatterns {
+                remember: en.0,
+                preference: en.1,
+                forget: en.2,
+                assistant_marker: en.3,
+                preference_keywords: &["prefer", "preference", "like", "dislike"],
+                workflow_keywords: &[
+                    "always", "usually", "workflow", "run", "command", "format", "style",
+                ],
+                constraint_keywords: &[
+                    "never", "must", "mustn't", "do not", "don't", "avoid", "required", "forbid",
+                ],
+                stopwords: &[
+                    "the", "and", "is", "that", "we", "to", "of", "with", "remember", "prefer",
+                    "use", "always", "never",
+                ],
+                letters: &[],
+            },
+            LanguagePatterns {
+                remember: nb.0,
+                preference: nb.1,
+                forget: nb.2,
+                assistant_marker: nb.3,
+                preference_keywords: &["foretrekker", "preferanse", "liker"],
+                workflow_keywords: &[
+                    "alltid", "vanligvis", "arbeidsflyt", "kjør", "kommando", "format", "stil",
+                ],
+                constraint_keywords: &[
+                    "aldri", "må", "ikke", "unngå", "påkrevd", "forbudt",
+                ],
+                stopwords: &[
+                    "og", "ikke", "jeg", "er", "det", "at", "som", "på", "vi", "med", "husk",
+                    "bruk", "alltid", "aldri", "å",
+                ],
+                letters: &['æ', 'ø', 'å'],
+            },
+            LanguagePatterns {
+                remember: de.0,
+                preference: de.1,
+                forget: de.2,
+                assistant_marker: de.3,
+                preference_keywords: &["bevorzuge", "präferenz", "lieber", "mag"],
+                workflow_keywords: &[
+                    "immer",
+                    "normalerweise",
+                    "arbeitsablauf",
+                    "ausführen",
+                    "befehl",
+                    "format",
+                    "stil",
+                ],
+                constraint_keywords: &[
+                    "niemals", "nie", "muss", "nicht", "vermeide", "erforderlich", "verboten",
+                ],
+                stopwords: &[
+                    "und", "nicht", "ich", "ist", "das", "dass", "wir", "mit", "der", "die",
+                    "bitte", "immer", "verwenden", "niemals",
+                ],
+                letters: &['ä', 'ö', 'ü', 'ß'],
+            },
+            LanguagePatterns {
+                remember: fr.0,
+                preference: fr.1,
+                forget: fr.2,
+                assistant_marker: fr.3,
+                preference_keywords: &["préfère", "préférence", "aime"],
+                workflow_keywords: &[
+                    "toujours",
+                    "habituellement",
+                    "flux de travail",
+                    "lance",
+                    "commande",
+                    "format",
+                    "style",
+                ],
+                constraint_keywords: &[
+                    "jamais", "doit", "ne pas", "évite", "obligatoire", "interdit",
+                ],
+                stopwords: &[
+                    "et", "pas", "je", "est", "que", "nous", "avec", "le", "les", "des",
+                    "toujours", "utilise", "jamais",
+                ],
+                letters: &['é', 'è', 'ê', 'ç', 'à', 'ù'],
+            },
+            LanguagePatterns {
+                remember: es.0,
+                preference: es.1,
+                forget: es.2,
+                assistant_marker: es.3,
+                preference_keywords: &["prefiero", "preferencia", "gusta"],
+                workflow_keywords: &[
+                    "siempre",
+                    "normalmente",
+                    "flujo de trabajo",
+                    "ejecuta",
+                    "comando",
+                    "formato",
+                    "estilo",
+                ],
+                constraint_keywords: &[
+                    "nunca", "debe", "no ", "evita", "obligatorio", "prohibido",
+                ],
+                stopwords: &[
+                    "y", "no", "yo", "es", "que", "nosotros", "con", "el", "los", "las",
+                    "siempre", "usa", "nunca", "para",
+                ],
+                letters: &['ñ', '¿', '¡', 'á', 'í', 'ó', 'ú'],
+            },
+        ]
+    })
+}
+
+#[must_use]
+pub fn patterns(language: Language) -> &'static LanguagePatterns {
+    &all_patterns()[language.index()]
+}
+
+#[must_use]
+pub fn detect_language(text: &str) -> Language {
+    let lower = text.to_lowercase();
+    let words = lower
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>();
+
+    let mut best = Language::En;
+    let mut best_score = 0_usize;
+    for language in ALL_LANGUAGES {
+        let set = patterns(language);
+        let word_hits = words
+            .iter()
+            .filter(|word| set.stopwords.contains(word))
+            .count();
+        let letter_hits = lower.chars().filter(|c| set.letters.contains(c)).count();
+        let score = word_hits + letter_hits * 2;
+        if score > best_score {
+            best = language;
+            best_score = score;
+        }
+    }
+    best
+}
+
+#[must_use]
+pub fn languages_for_message(text: &str, configured: &[String]) -> Vec<Language> {
+    let mut languages = Vec::new();
+    for code in configured {
+        let language = if code == "auto" {
+            detect_language(text)
+        } else {
+            match code.parse::<Language>() {
+                Ok(language) => language,
+                Err(_) => continue,
+            }
+        };
+        if !languages.contains(&language) {
+            languages.push(language);
+        }
+    }
+    if configured.iter().any(|code| code == "auto") && !languages.contains(&Language::En) {
+        languages.push(Language::En);
+    }
+    if languages.is_empty() {
+        languages.push(Language::En);
+    }
+    languages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Language, detect_language, languages_for_message};
+
+    #[test]
+    fn detects_language_from_stopwords_and_letters() {
+        assert_eq!(
+            detect_language("husk at vi alltid kjører tester"),
+            Language::Nb
+        );
+        assert_eq!(
+            detect_language("Bitte merk dir, dass wir immer pnpm verwenden"),
+            Language::De
+        );
+        assert_eq!(
+            detect_language("Je préfère les commits courts"),
+            Language::Fr
+        );
+        assert_eq!(
+            detect_language("Recuerda que siempre usamos pnpm"),
+            Language::Es
+        );
+        assert_eq!(detect_language("remember that we use pnpm"), Language::En);
+    }
+
+    #[test]
+    fn auto_mode_keeps_english_as_fallback() {
+        let auto = vec!["auto".to_string()];
+        assert_eq!(
+            languages_for_message("husk at vi bruker pnpm", &auto),
+            vec![Language::Nb, Language::En]
+        );
+        let fixed = vec!["de".to_string()];
+        assert_eq!(
+            languages_for_message("anything", &fixed),
+            vec![Language::De]
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[must_use]
 pub fn resolve_codex_home() -> PathBuf {
@@ -25,5 +25,30 @@ pub fn get_database_path() -> PathBuf {
 
 #[must_use]
 pub fn get_config_path() -> PathBuf {
-    get_memory_dir().join("config.json")
+    global_config_path(&get_memory_dir())
+}
+
+#[must_use]
+pub fn global_config_path(memory_dir: &Path) -> PathBuf {
+    let toml_path = memory_dir.join("config.toml");
+    if toml_path.exists() {
+        toml_path
+    } else {
+        memory_dir.join("config.json")
+    }
+}
+
+#[must_use]
+pub fn resolve_user_path(raw: &str, base_dir: &Path) -> PathBuf {
+    if let Some(rest) = raw.strip_prefix("~/")
+        && let Some(home) = dirs::home_dir()
+    {
+        return home.join(rest);
+    }
+    let path = PathBuf::from(raw);
+    if path.is_absolute() {
+        path
+    } else {
+        base_dir.join(path)
+    }
 }
@@ -1,26 +1,64 @@
-use crate::config::MemoryConfig;
-use crate::types::{MemoryRow, MemoryStats, ScopeInfo};
-use crate::utils::{format_memory_scope, now_iso};
+use crate::autocapture::CaptureRules;
+use crate::config::{ConfigMigration, ConfigOrigin, MemoryConfig};
+use crate::secrets::{SecretFinding, mask_secret};
+use crate::types::{
+    AutoCaptureRule, MemoryRow, MemoryStats, ScopeEntry, ScopeInfo, SyncJobState, SyncJobStatus,
+};
+use crate::utils::{format_memory_scope, label_memory_scope, now_iso, truncate_chars};
+use serde_json::Value;
+use std::collections::HashMap;
 
 #[must_use]
-pub fn render_rows(rows: &[MemoryRow], scope_info: &ScopeInfo) -> String {
+pub fn render_rows(
+    rows: &[MemoryRow],
+    scope_info: &ScopeInfo,
+    labels: &HashMap<String, String>,
+) -> String {
     if rows.is_empty() {
         return "No active memories.".to_string();
     }
 
     rows.iter()
         .map(|row| {
-            let scope = format_memory_scope(&row.scope, &scope_info.scope);
+            let scope = label_memory_scope(&row.scope, &scope_info.scope, labels);
             let pin = if row.pinned { " [pinned]" } else { "" };
+            let pending = if row.status == "pending" {
+                " [pending]"
+            } else {
+                ""
+            };
             format!(
-                "- {} ({}/{}){}\n  {}",
-                row.id, scope, row.category, pin, row.content
+                "- {} ({}/{}){}{}\n  {}",
+                row.id, scope, row.category, pin, pending, row.content
             )
         })
         .collect::<Vec<_>>()
         .join("\n")
 }
 
+#[must_use]
+pub fn format_scope_info(scope_info: &ScopeInfo) -> String {
+    let mut lines = vec![
+        format!("Scope: {}", scope_info.scope),
+        format!("- Kind: {}", scope_info.kind),
+        format!("- Identifier: {}", scope_info.identifier),
+        format!("- Root: {}", scope_info.root),
+    ];
+    if let (Some(package), Some(package_scope)) = (&scope_info.package, &scope_info.package_scope) {
+        lines.push(format!("- Package: {package} ({package_scope})"));
+    }
+    if let (Some(branch), Some(branch_scope)) = (&scope_info.branch, &scope_info.branch_scope) {
+        lines.push(format!("- Branch: {branch} ({branch_scope})"));
+    }
+    if let Some(org_scope) = &scope_info.org_scope {
+        lines.push(format!("- Org: {org_scope}"));
+    }
+    for alias in &scope_info.aliases {
+        lines.push(format!("- Alias: {alias}"));
+    }
+    lines.join("\n")
+}
+
 #[must_use]
 pub fn build_injection_block(
     rows: &[MemoryRow],
@@ -34,7 +72,7 @@ pub fn build_injection_block(
 
     let header_lines = vec![
         "## Extra Memory (Codex)".to_string(),
-        "Use these as stable user/project facts. Prefer project scope over global when they conflict."
+        "Use these as stable user/project facts. Prefer package and branch over project, project over org, and org over global when they conflict."
             .to_string(),
     ];
     let header_len = header_lines.join("\n").chars().count();
@@ -78,35 +116,323 @@ pub fn format_stats(stats: &MemoryStats) -> String {
         "Persistent memory stats".to_string(),
         String::new(),
         format!("- Active: {}", stats.active),
+        format!("- Pending review: {}", stats.pending),
         format!("- Pinned: {}", stats.pinned),
         format!("- Project scope: {}", stats.project),
+        format!("- Org scope: {}", stats.org),
         format!("- Global scope: {}", stats.global),
+        format!("- Auto-capture hashes tracked: {}", stats.processed_hashes),
         format!(
             "- FTS search: {}",
             if stats.has_fts {
                 "enabled"
+            } else if stats.encrypted {
+                "disabled (encrypted; decrypt-and-filter)"
             } else {
                 "fallback (LIKE)"
             }
         ),
+        format!(
+            "- Encryption at rest: {}",
+            if stats.encrypted { "on" } else { "off" }
+        ),
     ]
+    .into_iter()
+    .chain(stats.scope_detection.as_ref().map(|timing| {
+        format!(
+            "- Scope detection: {} lookups, {} cached, last detect {:.1} ms, avg {:.1} ms detected / {:.2} ms cached",
+            timing.lookups,
+            timing.cache_hits,
+            timing.last_detect_ms,
+            timing.avg_detect_ms,
+            timing.avg_cached_ms
+        )
+    }))
+    .chain(stats.by_scope.iter().map(|entry| {
+        format!(
+            "- Scope {}: {} active, {} pending",
+            entry.label, entry.active, entry.pending
+        )
+    }))
+    .collect::<Vec<_>>()
     .join("\n")
 }
 
 #[must_use]
-pub fn format_auto_capture_status(config: &MemoryConfig) -> String {
-    [
+pub fn format_scopes(entries: &[ScopeEntry], scope_info: &ScopeInfo) -> String {
+    if entries.is_empty() {
+        return "No known scopes.".to_string();
+    }
+    let mut lines = vec!["Known memory scopes".to_string(), String::new()];
+    for entry in entries {
+        let current = if entry.scope == scope_info.scope
+            || scope_info.package_scope.as_deref() == Some(entry.scope.as_str())
+            || scope_info.branch_scope.as_deref() == Some(entry.scope.as_str())
+        {
+            " [current]"
+        } else {
+            ""
+        };
+        lines.push(format!(
+            "- {}{current}: {} active, {} pending",
+            entry.label, entry.active, entry.pending
+        ));
+        lines.push(format!("  {}", entry.scope));
+        if let Some(root) = &entry.root {
+            lines.push(format!(
+                "  {} at {root}, last seen {}",
+                entry.kind.as_deref().unwrap_or("unknown"),
+                entry.last_seen.as_deref().unwrap_or("never")
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+#[must_use]
+pub fn format_secret_findings(
+    text: &str,
+    findings: &[SecretFinding],
+    outcome: &str,
+    config_errors: &[String],
+) -> String {
+    let mut lines = vec![format!("Secret scan: {outcome}"), String::new()];
+    if findings.is_empty() {
+        lines.push("No detector matched.".to_string());
+    }
+    for finding in findings {
+        let detail = finding
+            .detail
+            .as_ref()
+            .map(|detail| format!(" [{detail}]"))
+            .unwrap_or_default();
+        lines.push(format!(
+            "- {} ({}) at {}..{}: {}{detail}{}",
+            finding.detector,
+            finding.kind.as_str(),
+            finding.start,
+            finding.end,
+            mask_secret(&text[finding.start..finding.end]),
+            if finding.allowlisted {
+                " (allowlisted)"
+            } else {
+                ""
+            }
+        ));
+    }
+    if !config_errors.is_empty() {
+        lines.push(String::new());
+        lines.push("Config errors (these entries are ignored until fixed):".to_string());
+        lines.extend(config_errors.iter().map(|error| format!("- {error}")));
+    }
+    lines.join("\n")
+}
+
+#[must_use]
+pub fn format_audit_report(report: &Value) -> String {
+    let totals = &report["totals"];
+    let count = |key: &str| totals[key].as_u64().unwrap_or(0);
+    let mut lines = vec![
+        format!(
+            "Memory audit: {} of {} memories and {} of {} events flagged",
+            count("memories_flagged"),
+            count("memories_scanned"),
+            count("events_flagged"),
+            count("events_scanned"),
+        ),
+        String::new(),
+    ];
+
+    let findings = report["findings"].as_array().cloned().unwrap_or_default();
+    if findings.is_empty() {
+        lines.push("No secrets or PII found.".to_string());
+    }
+    for finding in &findings {
+        let detectors = finding["detectors"]
+            .as_array()
+            .map(|names| {
+                names
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .unwrap_or_default();
+        let action = finding["action"].as_str().unwrap_or("reported");
+        let line = if finding["source"] == "event" {
+            format!(
+                "- event #{} ({}) for {}: {detectors} -> {action}",
+                finding["event_id"],
+                finding["event_action"].as_str().unwrap_or(""),
+                finding["memory_id"].as_str().unwrap_or(""),
+            )
+        } else {
+            format!(
+                "- memory {} ({}, {}): {detectors} -> {action}",
+                finding["id"].as_str().unwrap_or(""),
+                finding["scope"].as_str().unwrap_or(""),
+                finding["status"].as_str().unwrap_or(""),
+            )
+        };
+        lines.push(line);
+    }
+
+    if !findings.is_empty() && report["remediation"] == "none" {
+        lines.push(String::new());
+        lines.push("Run /memory audit --redact or --delete to remediate.".to_string());
+    }
+    if let Some(path) = report["report_path"].as_str() {
+        lines.push(format!("Report written to {path}"));
+    }
+    lines.join("\n")
+}
+
+#[must_use]
+pub fn format_sync_status(job: Option<&SyncJobStatus>) -> String {
+    let Some(job) = job else {
+        return "No AGENTS.md sync has run in this server session.".to_string();
+    };
+
+    let mut lines = vec![
+        "AGENTS.md sync status".to_string(),
+        String::new(),
+        format!("- Job: #{}", job.job_id),
+        format!(
+            "- State: {}",
+            match job.state {
+                SyncJobState::Pending => "pending (LLM compaction in progress)",
+                SyncJobState::Completed => "completed",
+                SyncJobState::Failed => "failed",
+            }
+        ),
+        format!("- Path: {}", job.agents_path),
+        format!(
+            "- Mode: {}",
+            serde_json::to_value(&job.mode)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default()
+        ),
+        format!("- Started: {}", job.started_at.to_rfc3339()),
+    ];
+    if let Some(finished_at) = job.finished_at {
+        lines.push(format!("- Finished: {}", finished_at.to_rfc3339()));
+    }
+    if let Some(model) = &job.model {
+        lines.push(format!("- Model: {model}"));
+    }
+    if let Some(reason) = &job.reason {
+        lines.push(format!("- Reason: {reason}"));
+    }
+    lines.join("\n")
+}
+
+fn format_capture_rule(index: usize, rule: &AutoCaptureRule, error: Option<&str>) -> String {
+    let mut parts = vec![
+        format!("{}.", index + 1),
+        format!("/{}/", rule.pattern),
+        format!("role={}", rule.role),
+    ];
+    if let Some(category) = &rule.category {
+        parts.push(format!("category={category}"));
+    }
+    if let Some(scope) = &rule.scope {
+        parts.push(format!("scope={scope}"));
+    }
+    if let Some(tag) = &rule.tag {
+        parts.push(format!("tag={tag}"));
+    }
+    if let Some(error) = error {
+        parts.push(format!("[invalid: {error}]"));
+    }
+    parts.join(" ")
+}
+
+#[must_use]
+pub fn format_config(origins: &[ConfigOrigin], with_origin: bool) -> String {
+    let mut lines = vec!["Memory config".to_string(), String::new()];
+    for entry in origins {
+        let value = truncate_chars(&entry.value.to_string(), 80);
+        if with_origin {
+            let origin = match &entry.source {
+                Some(source) => format!("{}: {source}", entry.origin),
+                None => entry.origin.clone(),
+            };
+            lines.push(format!("- {} = {value} ({origin})", entry.key));
+        } else {
+            lines.push(format!("- {} = {value}", entry.key));
+        }
+    }
+    lines.join("\n")
+}
+
+#[must_use]
+pub fn format_config_migration(migration: &ConfigMigration) -> String {
+    let mut lines = vec![if migration.from_version == migration.to_version {
+        format!(
+            "{} is already at config version {}.",
+            migration.path, migration.to_version
+        )
+    } else if migration.dry_run {
+        format!(
+            "Dry run: {} would be upgraded from config version {} to {}.",
+            migration.path, migration.from_version, migration.to_version
+        )
+    } else {
+        format!(
+            "Upgraded {} from config version {} to {}.",
+            migration.path, migration.from_version, migration.to_version
+        )
+    }];
+    if !migration.changes.is_empty() {
+        lines.push(String::new());
+        lines.push("Changes:".to_string());
+        lines.extend(migration.changes.iter().map(|change| format!("- {change}")));
+    }
+    if !migration.unknown_keys.is_empty() {
+        lines.push(String::new());
+        lines.push("Unknown keys (ignored, kept in the file):".to_string());
+        lines.extend(migration.unknown_keys.iter().map(|key| format!("- {key}")));
+    }
+    if let Some(backup) = &migration.backup {
+        lines.push(String::new());
+        lines.push(format!("Backup: {backup}"));
+    }
+    lines.join("\n")
+}
+
+#[must_use]
+pub fn format_auto_capture_status(config: &MemoryConfig, rules: &CaptureRules) -> String {
+    let mut lines = vec![
         "Auto-capture status".to_string(),
         String::new(),
         format!(
             "- Enabled: {}",
-            if config.auto_capture.enabled {
-                "on"
-            } else {
+            if !config.auto_capture.enabled {
                 "off"
+            } else if config.auto_capture.review {
+                "review (captures wait in /memory review)"
+            } else {
+                "on"
             }
         ),
         format!("- Scope: {}", config.auto_capture.scope.as_str()),
+        format!(
+            "- Languages: {}",
+            if config
+                .auto_capture
+                .languages
+                .iter()
+                .any(|code| code == "auto")
+            {
+                format!(
+                    "{} (auto detects en, nb, de, fr, es per message)",
+                    config.auto_capture.languages.join(", ")
+                )
+            } else {
+                config.auto_capture.languages.join(", ")
+            }
+        ),
         format!(
             "- Max captures per turn: {}",
             config.auto_capture.max_per_turn
@@ -115,17 +441,52 @@ pub fn format_auto_capture_status(config: &MemoryConfig) -> String {
             "- Capture length: {}-{} chars",
             config.auto_capture.min_chars, config.auto_capture.max_chars
         ),
+        format!(
+            "- LLM classification: {}",
+            if config.auto_capture.llm.enabled {
+                format!(
+                    "on ({}, budget {} tokens, min confidence {:.2})",
+                    config.auto_capture.llm.model,
+                    config.auto_capture.llm.max_input_tokens,
+                    config.auto_capture.llm.min_confidence
+                )
+            } else {
+                "off".to_string()
+            }
+        ),
         String::new(),
-        "Heuristic mode: explicit patterns only.".to_string(),
+        "Heuristic mode: explicit patterns, with optional LLM proposals.".to_string(),
         "- Captures user statements like 'remember ...' and 'I prefer ...'".to_string(),
         "- Captures assistant lines prefixed with 'Memory:' or 'Remember:'".to_string(),
+        "- Treats 'forget ...' as a retraction and 'we switched from X to Y' as a correction"
+            .to_string(),
+        "- LLM proposals are labelled 'llm:' and regex matches 'regex:' in each reason".to_string(),
         "- Uses dedupe + secret filtering before write".to_string(),
-    ]
-    .join("\n")
+    ];
+
+    lines.push(String::new());
+    if config.auto_capture.rules.is_empty() {
+        lines.push("Custom rules: none (add autoCapture.rules in config)".to_string());
+    } else {
+        lines.push(format!(
+            "Custom rules ({}):",
+            config.auto_capture.rules.len()
+        ));
+        lines.extend(
+            config
+                .auto_capture
+                .rules
+                .iter()
+                .enumerate()
+                .map(|(index, rule)| format_capture_rule(index, rule, rules.error_for(index))),
+        );
+    }
+
+    lines.join("\n")
 }
 
 #[must_use]
-pub fn format_export_markdown(rows: &[MemoryRow]) -> String {
+pub fn format_export_markdown(rows: &[MemoryRow], labels: &HashMap<String, String>) -> String {
     let mut lines = Vec::new();
     lines.push("# Codex Extra Memory Export".to_string());
     lines.push(String::new());
@@ -138,7 +499,10 @@ pub fn format_export_markdown(rows: &[MemoryRow]) -> String {
     }
 
     for (scope, entries) in grouped {
-        lines.push(format!("## {scope}"));
+        match labels.get(&scope) {
+            Some(label) => lines.push(format!("## {label} ({scope})")),
+            None => lines.push(format!("## {scope}")),
+        }
         lines.push(String::new());
         for row in entries {
             lines.push(format!(
@@ -1,7 +1,14 @@
-use crate::types::ScopeInfo;
+use crate::types::{ScopeDetectionStats, ScopeInfo};
 use crate::utils::sha256;
+use std::collections::HashMap;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+use toml_edit::DocumentMut;
+
+pub const SCOPE_MARKER_FILE: &str = ".codex-memory.toml";
 
 fn normalize_git_remote_identifier(remote: &str) -> String {
     let trimmed = remote.trim();
@@ -29,10 +36,19 @@ fn normalize_git_remote_identifier(remote: &str) -> String {
     trimmed.trim_end_matches(".git").to_string()
 }
 
-fn git_stdout(cwd: &Path, args: &[&str]) -> Option<String> {
-    let output = Command::new("git")
-        .arg("-C")
-        .arg(cwd)
+#[must_use]
+pub fn org_scope_from_remote(normalized_remote: &str) -> Option<String> {
+    let rest = normalized_remote.strip_prefix("https://")?;
+    let mut parts = rest.split('/').filter(|part| !part.is_empty());
+    let host = parts.next()?;
+    let owner = parts.next()?;
+    parts.next()?;
+    Some(format!("org:{host}/{owner}"))
+}
+
+fn command_stdout(program: &str, cwd: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new(program)
+        .current_dir(cwd)
         .args(args)
         .output()
         .ok()?;
@@ -48,31 +64,476 @@ fn git_stdout(cwd: &Path, args: &[&str]) -> Option<String> {
     }
 }
 
+fn git_stdout(cwd: &Path, args: &[&str]) -> Option<String> {
+    command_stdout("git", cwd, args)
+}
+
+fn read_toml(path: &Path) -> Option<DocumentMut> {
+    fs::read_to_string(path).ok()?.parse::<DocumentMut>().ok()
+}
+
+fn dir_name(dir: &Path) -> Option<String> {
+    dir.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+}
+
+fn marker_package_name(dir: &Path) -> Option<String> {
+    let path = dir.join(SCOPE_MARKER_FILE);
+    if !path.is_file() {
+        return None;
+    }
+    read_toml(&path)
+        .and_then(|doc| doc.get("package")?.as_str().map(str::to_string))
+        .or_else(|| dir_name(dir))
+}
+
+fn cargo_package_name(dir: &Path) -> Option<String> {
+    let doc = read_toml(&dir.join("Cargo.toml"))?;
+    doc.get("package")?
+        .get("name")?
+        .as_str()
+        .map(str::to_string)
+}
+
+fn npm_package_name(dir: &Path) -> Option<String> {
+    let raw = fs::read_to_string(dir.join("package.json")).ok()?;
+    let parsed = serde_json::from_str::<serde_json::Value>(&raw).ok()?;
+    parsed
+        .get("name")?
+        .as_str()
+        .map(str::to_string)
+        .or_else(|| dir_name(dir))
+}
+
+fn go_module_name(dir: &Path) -> Option<String> {
+    let raw = fs::read_to_string(dir.join("go.mod")).ok()?;
+    raw.lines()
+        .find_map(|line| line.trim().strip_prefix("module "))
+        .map(|module| module.trim().trim_matches('"').to_string())
+}
+
+fn package_name_at(dir: &Path) -> Option<String> {
+    marker_package_name(dir)
+        .or_else(|| cargo_package_name(dir))
+        .or_else(|| npm_package_name(dir))
+        .or_else(|| go_module_name(dir))
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+#[must_use]
+pub fn detect_sub_project(cwd: &Path, root: &Path) -> Option<(String, PathBuf)> {
+    cwd.ancestors()
+        .take_while(|dir| *dir != root && dir.starts_with(root))
+        .find_map(|dir| package_name_at(dir).map(|name| (name, dir.to_path_buf())))
+}
+
+#[must_use]
+pub fn branch_scope(project_scope: &str, branch: &str) -> String {
+    format!("{project_scope}@{branch}")
+}
+
+fn main_worktree_root(common_dir: &Path) -> Option<PathBuf> {
+    let common_dir = common_dir.canonicalize().ok()?;
+    if common_dir.file_name()? != ".git" {
+        return None;
+    }
+    common_dir.parent().map(Path::to_path_buf)
+}
+
+fn git_head_branch(git_dir: &Path) -> Option<String> {
+    fs::read_to_string(git_dir.join("HEAD"))
+        .ok()?
+        .trim()
+        .strip_prefix("ref: refs/heads/")
+        .map(str::to_string)
+}
+
+pub const DEFAULT_SCOPE_DETECTORS: &[&str] = &["git", "jj", "hg"];
+
+#[derive(Debug, Clone)]
+pub struct VcsCheckout {
+    pub root: PathBuf,
+    pub identity_root: PathBuf,
+    pub remote: Option<String>,
+    pub branch: Option<String>,
+    pub watch: Vec<PathBuf>,
+}
+
+pub trait ScopeDetector {
+    fn name(&self) -> &'static str;
+    fn detect(&self, cwd: &Path) -> Option<VcsCheckout>;
+}
+
+pub struct GitDetector;
+
+impl ScopeDetector for GitDetector {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn detect(&self, cwd: &Path) -> Option<VcsCheckout> {
+        let output = git_stdout(
+            cwd,
+            &[
+                "rev-parse",
+                "--show-toplevel",
+                "--absolute-git-dir",
+                "--git-common-dir",
+            ],
+        )?;
+        let mut lines = output.lines().map(str::trim);
+        let root = PathBuf::from(lines.next()?);
+        let git_dir = PathBuf::from(lines.next()?);
+        let common_dir = cwd.join(lines.next()?);
+        Some(VcsCheckout {
+            identity_root: main_worktree_root(&common_dir).unwrap_or_else(|| root.clone()),
+            remote: git_stdout(&root, &["config", "--get", "remote.origin.url"]),
+            branch: git_head_branch(&git_dir),
+            watch: vec![git_dir.join("HEAD"), common_dir.join("config")],
+            root,
+        })
+    }
+}
+
+pub struct JjDetector;
+
+fn parse_jj_remotes(output: &str) -> Option<String> {
+    let remotes = output
+        .lines()
+        .filter_map(|line| line.trim().split_once(char::is_whitespace))
+        .map(|(name, url)| (name, url.trim()))
+        .collect::<Vec<_>>();
+    remotes
+        .iter()
+        .find(|(name, _)| *name == "origin")
+        .or_else(|| remotes.first())
+        .map(|(_, url)| (*url).to_string())
+}
+
+impl ScopeDetector for JjDetector {
+    fn name(&self) -> &'static str {
+        "jj"
+    }
+
+    fn detect(&self, cwd: &Path) -> Option<VcsCheckout> {
+        let root = PathBuf::from(command_stdout("jj", cwd, &["root"])?);
+        Some(VcsCheckout {
+            identity_root: root.clone(),
+            remote: command_stdout("jj", &root, &["git", "remote", "list"])
+                .and_then(|output| parse_jj_remotes(&output)),
+            branch: None,
+            watch: vec![
+                root.join(".jj/repo/store/git/config"),
+                root.join(".git/config"),
+            ],
+            root,
+        })
+    }
+}
+
+pub struct HgDetector;
+
+impl ScopeDetector for HgDetector {
+    fn name(&self) -> &'static str {
+        "hg"
+    }
+
+    fn detect(&self, cwd: &Path) -> Option<VcsCheckout> {
+        let root = PathBuf::from(command_stdout("hg", cwd, &["root"])?);
+        Some(VcsCheckout {
+            identity_root: root.clone(),
+            remote: command_stdout("hg", &root, &["paths", "default"]),
+            branch: command_stdout("hg", &root, &["branch"]),
+            watch: vec![root.join(".hg/hgrc"), root.join(".hg/branch")],
+            root,
+        })
+    }
+}
+
+#[must_use]
+pub fn scope_detector(name: &str) -> Option<Box<dyn ScopeDetector>> {
+    match name {
+        "git" => Some(Box::new(GitDetector)),
+        "jj" => Some(Box::new(JjDetector)),
+        "hg" => Some(Box::new(HgDetector)),
+        _ => None,
+    }
+}
+
+const SCOPE_CACHE_TTL: Duration = Duration::from_secs(30);
+
+type Fingerprint = Vec<Option<SystemTime>>;
+
+struct CachedCheckout {
+    checkout: Option<(&'static str, VcsCheckout)>,
+    fingerprint: Fingerprint,
+    cached_at: Instant,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct DetectionTimings {
+    lookups: u64,
+    cache_hits: u64,
+    detect_total: Duration,
+    cached_total: Duration,
+    last_detect: Duration,
+}
+
+static SCOPE_CACHE: OnceLock<Mutex<HashMap<(PathBuf, String), CachedCheckout>>> = OnceLock::new();
+static DETECTION_TIMINGS: OnceLock<Mutex<DetectionTimings>> = OnceLock::new();
+
+fn fingerprint(paths: &[PathBuf]) -> Fingerprint {
+    paths
+        .iter()
+        .map(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+        .collect()
+}
+
+fn detect_checkout<S: AsRef<str>>(
+    cwd: &Path,
+    detectors: &[S],
+) -> Option<(&'static str, VcsCheckout)> {
+    detectors
+        .iter()
+        .filter_map(|name| scope_detector(name.as_ref()))
+        .find_map(|detector| detector.detect(cwd).map(|found| (detector.name(), found)))
+}
+
+fn cached_checkout<S: AsRef<str>>(
+    cwd: &Path,
+    detectors: &[S],
+) -> (Option<(&'static str, VcsCheckout)>, bool) {
+    let key = (
+        cwd.to_path_buf(),
+        detectors
+            .iter()
+            .map(AsRef::as_ref)
+            .collect::<Vec<_>>()
+            .join(","),
+    );
+    let cache = SCOPE_CACHE.get_or_init(Mutex::default);
+    if let Ok(entries) = cache.lock()
+        && let Some(entry) = entries.get(&key)
+        && entry.cached_at.elapsed() < SCOPE_CACHE_TTL
+        && entry.fingerprint
+            == fingerprint(
+                entry
+                    .checkout
+                    .as_ref()
+                    .map_or(&[][..], |(_, checkout)| &checkout.watch),
+            )
+    {
+        return (entry.checkout.clone(), true);
+    }
+
+    let checkout = detect_checkout(cwd, detectors);
+    let entry = CachedCheckout {
+        fingerprint: fingerprint(
+            checkout
+                .as_ref()
+                .map_or(&[][..], |(_, checkout)| &checkout.watch),
+        ),
+        checkout: checkout.clone(),
+        cached_at: Instant::now(),
+    };
+    if let Ok(mut entries) = cache.lock() {
+        entries.insert(key, entry);
+    }
+    (checkout, false)
+}
+
+fn record_detection(cache_hit: bool, elapsed: Duration) {
+    let Ok(mut timings) = DETECTION_TIMINGS.get_or_init(Mutex::default).lock() else {
+        return;
+    };
+    timings.lookups += 1;
+    if cache_hit {
+        timings.cache_hits += 1;
+        timings.cached_total += elapsed;
+    } else {
+        timings.detect_total += elapsed;
+        timings.last_detect = elapsed;
+    }
+}
+
+fn average_ms(total: Duration, count: u64) -> f64 {
+    if count == 0 {
+        return 0.0;
+    }
+    total.as_secs_f64() * 1000.0 / count as f64
+}
+
+#[must_use]
+pub fn scope_detection_stats() -> ScopeDetectionStats {
+    let timings = DETECTION_TIMINGS
+        .get_or_init(Mutex::default)
+        .lock()
+        .map(|timings| *timings)
+        .unwrap_or_default();
+    let detections = timings.lookups - timings.cache_hits;
+    ScopeDetectionStats {
+        lookups: timings.lookups,
+        cache_hits: timings.cache_hits,
+        last_detect_ms: timings.last_detect.as_secs_f64() * 1000.0,
+        avg_detect_ms: average_ms(timings.detect_total, detections),
+        avg_cached_ms: average_ms(timings.cached_total, timings.cache_hits),
+    }
+}
+
+#[must_use]
+pub fn package_scope(project_scope: &str, package: &str) -> String {
+    format!("{project_scope}#{package}")
+}
+
+#[derive(Debug, Default)]
+struct ScopeMarker {
+    scope_id: Option<String>,
+    aliases: Vec<String>,
+}
+
+fn read_scope_marker(dir: &Path) -> Option<ScopeMarker> {
+    let doc = read_toml(&dir.join(SCOPE_MARKER_FILE))?;
+    let scope_id = doc
+        .get("scope_id")
+        .and_then(|item| item.as_str())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string);
+    let aliases = doc
+        .get("aliases")
+        .and_then(|item| item.as_array())
+        .map(|array| {
+            array
+                .iter()
+                .filter_map(|value| value.as_str())
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(ScopeMarker { scope_id, aliases })
+}
+
+fn project_scope_for(kind: &str, identifier: &str) -> String {
+    format!("project:{}", sha256(&format!("{kind}:{identifier}")))
+}
+
+#[must_use]
+pub fn is_remote_url(raw: &str) -> bool {
+    let raw = raw.trim();
+    if let Some(rest) = raw.strip_prefix("git@") {
+        return rest
+            .split_once(':')
+            .is_some_and(|(host, repo)| !host.is_empty() && !repo.trim_matches('/').is_empty());
+    }
+    url::Url::parse(raw).is_ok_and(|url| {
+        url.host_str().is_some_and(|host| !host.is_empty())
+            && !url.path().trim_matches('/').is_empty()
+    })
+}
+
+#[must_use]
+pub fn resolve_scope_alias(alias: &str) -> String {
+    let alias = alias.trim();
+    if alias.starts_with("project:") {
+        alias.to_string()
+    } else if Path::new(alias).is_absolute() {
+        project_scope_for("path", alias)
+    } else {
+        project_scope_for("git", &normalize_git_remote_identifier(alias))
+    }
+}
+
+#[must_use]
+pub fn project_label(scope_info: &ScopeInfo) -> String {
+    match scope_info.kind.as_str() {
+        "config" => scope_info.identifier.clone(),
+        "path" => scope_info.root.clone(),
+        _ => scope_info
+            .identifier
+            .trim_start_matches("https://")
+            .to_string(),
+    }
+}
+
 #[must_use]
 pub fn detect_project_scope(workspace_dir: &Path) -> ScopeInfo {
+    detect_project_scope_with(workspace_dir, DEFAULT_SCOPE_DETECTORS)
+}
+
+#[must_use]
+pub fn detect_project_scope_with<S: AsRef<str>>(
+    workspace_dir: &Path,
+    detectors: &[S],
+) -> ScopeInfo {
     let cwd = workspace_dir
         .canonicalize()
         .unwrap_or_else(|_| workspace_dir.to_path_buf());
 
     let mut root = cwd.clone();
     let mut kind = "path".to_string();
+    let mut hash_kind = "path";
     let mut identifier = cwd.to_string_lossy().to_string();
+    let mut marker = None;
+    let mut org_scope = None;
+    let mut branch = None;
 
-    if let Some(git_root) = git_stdout(&cwd, &["rev-parse", "--show-toplevel"]) {
-        let root_path = PathBuf::from(git_root);
-        root.clone_from(&root_path);
-        identifier = root_path.to_string_lossy().to_string();
+    let started = Instant::now();
+    let (checkout, cache_hit) = cached_checkout(&cwd, detectors);
 
-        if let Some(remote) = git_stdout(&root, &["config", "--get", "remote.origin.url"]) {
-            kind = "git".to_string();
+    if let Some((detector, checkout)) = checkout {
+        kind = detector.to_string();
+        root.clone_from(&checkout.root);
+        identifier = checkout.identity_root.to_string_lossy().to_string();
+        branch = checkout.branch;
+
+        if let Some(remote) = checkout.remote {
+            hash_kind = "git";
             identifier = normalize_git_remote_identifier(&remote);
+            org_scope = org_scope_from_remote(&identifier);
         }
+        marker = read_scope_marker(&root);
+    } else if let Some((dir, found)) = cwd.ancestors().find_map(|dir| {
+        read_scope_marker(dir)
+            .filter(|found| found.scope_id.is_some())
+            .map(|found| (dir.to_path_buf(), found))
+    }) {
+        root = dir;
+        identifier = root.to_string_lossy().to_string();
+        marker = Some(found);
     }
 
-    let scope_hash = sha256(&format!("{kind}:{identifier}"));
+    let marker = marker.unwrap_or_default();
+    let mut aliases = marker
+        .aliases
+        .iter()
+        .map(|alias| resolve_scope_alias(alias))
+        .collect::<Vec<_>>();
+    if let Some(scope_id) = marker.scope_id {
+        aliases.insert(0, project_scope_for(hash_kind, &identifier));
+        kind = "config".to_string();
+        hash_kind = "config";
+        identifier = scope_id;
+    }
+    let scope = project_scope_for(hash_kind, &identifier);
+    aliases.retain(|alias| alias != &scope);
+    aliases.dedup();
+    let sub_project = detect_sub_project(&cwd, &root);
+    record_detection(cache_hit, started.elapsed());
 
     ScopeInfo {
-        scope: format!("project:{scope_hash}"),
+        package_scope: sub_project
+            .as_ref()
+            .map(|(package, _)| package_scope(&scope, package)),
+        package: sub_project.as_ref().map(|(package, _)| package.clone()),
+        package_root: sub_project.map(|(_, dir)| dir.to_string_lossy().to_string()),
+        branch_scope: branch.as_ref().map(|branch| branch_scope(&scope, branch)),
+        branch,
+        aliases,
+        org_scope,
+        scope,
         kind,
         identifier,
         root: root.to_string_lossy().to_string(),
@@ -81,7 +542,11 @@ pub fn detect_project_scope(workspace_dir: &Path) -> ScopeInfo {
 
 #[cfg(test)]
 mod tests {
-    use super::normalize_git_remote_identifier;
+    use super::{
+        detect_project_scope, detect_sub_project, is_remote_url, normalize_git_remote_identifier,
+        org_scope_from_remote, parse_jj_remotes, resolve_scope_alias,
+    };
+    use std::fs;
 
     #[test]
     fn normalizes_git_remote_variants() {
@@ -94,4 +559,120 @@ mod tests {
             "https://github.com/OpenAI/codex"
         );
     }
+
+    #[test]
+    fn prefers_origin_in_jj_remote_list() {
+        assert_eq!(
+            parse_jj_remotes(
+                "backup git@example.com:acme/api.git\norigin https://github.com/acme/api.git\n"
+            )
+            .as_deref(),
+            Some("https://github.com/acme/api.git")
+        );
+        assert_eq!(
+            parse_jj_remotes("upstream git@github.com:acme/api.git").as_deref(),
+            Some("git@github.com:acme/api.git")
+        );
+        assert_eq!(parse_jj_remotes(""), None);
+    }
+
+    #[test]
+    fn derives_org_scope_from_remote_owner() {
+        assert_eq!(
+            org_scope_from_remote(&normalize_git_remote_identifier(
+                "git@github.com:OpenAI/codex.git"
+            ))
+            .as_deref(),
+            Some("org:github.com/OpenAI")
+        );
+        assert_eq!(
+            org_scope_from_remote("https://gitlab.com/acme/platform/api").as_deref(),
+            Some("org:gitlab.com/acme")
+        );
+        assert_eq!(org_scope_from_remote("https://example.com/solo"), None);
+        assert_eq!(org_scope_from_remote("/srv/repos/app"), None);
+    }
+
+    #[test]
+    fn detects_nearest_sub_project_below_the_repo_root() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let root = temp.path();
+        fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\"]\n",
+        )
+        .expect("root manifest");
+        let crate_dir = root.join("crates/api/src");
+        fs::create_dir_all(&crate_dir).expect("crate dir");
+        fs::write(
+            root.join("crates/api/Cargo.toml"),
+            "[package]\nname = \"api\"\n",
+        )
+        .expect("crate manifest");
+        let web = root.join("apps/web");
+        fs::create_dir_all(&web).expect("web dir");
+        fs::write(web.join("package.json"), r#"{"name": "@acme/web"}"#).expect("package.json");
+        let svc = root.join("services/billing");
+        fs::create_dir_all(&svc).expect("svc dir");
+        fs::write(
+            svc.join("go.mod"),
+            "module github.com/acme/billing\n\ngo 1.22\n",
+        )
+        .expect("go.mod");
+        let docs = root.join("docs/guide");
+        fs::create_dir_all(&docs).expect("docs dir");
+        fs::write(
+            root.join("docs/.codex-memory.toml"),
+            "package = \"handbook\"\n",
+        )
+        .expect("marker");
+
+        let detected = |dir: &std::path::Path| detect_sub_project(dir, root).map(|(name, _)| name);
+        assert_eq!(detected(&crate_dir).as_deref(), Some("api"));
+        assert_eq!(detected(&web).as_deref(), Some("@acme/web"));
+        assert_eq!(detected(&svc).as_deref(), Some("github.com/acme/billing"));
+        assert_eq!(detected(&docs).as_deref(), Some("handbook"));
+        assert_eq!(detected(root), None);
+    }
+
+    #[test]
+    fn marker_scope_id_survives_moves_and_keeps_old_scopes_as_aliases() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let first = temp.path().join("first");
+        let moved = temp.path().join("moved/checkout");
+        for dir in [&first, &moved] {
+            fs::create_dir_all(dir.join("src")).expect("create checkout");
+            fs::write(
+                dir.join(".codex-memory.toml"),
+                "scope_id = \"acme-platform\"\naliases = [\"git@github.com:old-org/platform.git\"]\n",
+            )
+            .expect("marker");
+        }
+
+        let a = detect_project_scope(&first.join("src"));
+        let b = detect_project_scope(&moved);
+        assert_eq!(a.kind, "config");
+        assert_eq!(a.identifier, "acme-platform");
+        assert_eq!(a.scope, b.scope);
+        assert!(a.root.ends_with("first"));
+        assert!(
+            a.aliases
+                .contains(&resolve_scope_alias("https://github.com/old-org/platform"))
+        );
+        assert_eq!(
+            resolve_scope_alias("project:abc"),
+            "project:abc".to_string()
+        );
+        assert!(is_remote_url("git@github.com:acme/web.git"));
+        assert!(is_remote_url("https://github.com/acme/web"));
+        for raw in [
+            "myrepo",
+            "global",
+            "project:abc@main",
+            "org:x/y",
+            "git@github.com:",
+        ] {
+            assert!(!is_remote_url(raw), "{raw}");
+        }
+    }
 }
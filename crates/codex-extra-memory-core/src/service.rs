@@ -1,28 +1,56 @@
 use crate::agents_sync::sync_agents_file;
-use crate::autocapture::{extract_auto_capture_candidates, get_agent_end_messages};
-use crate::commands::{AutoMode, COMMAND_HELP, ExportFormat, MemoryCommand, parse_memory_command};
-use crate::config::{MemoryConfig, load_config_file_at, save_config_file_at};
-use crate::llm::{LlmSummaryRequest, summarize_memories_with_llm};
-use crate::paths::get_memory_dir;
+use crate::autocapture::{
+    CaptureRules, budget_llm_capture_messages, candidates_from_llm_proposals,
+    collect_agent_messages, compile_capture_rules, extract_auto_capture_candidates,
+    get_agent_end_messages, merge_auto_capture_candidates,
+};
+use crate::backfill::{find_session_files, parse_session_file};
+use crate::commands::{
+    AuditArgs, AuditRemediation, AutoMode, BackfillArgs, COMMAND_HELP, ConfigAction, ExportFormat,
+    MemoryCommand, ReviewAction, ReviewTargets, ScopeAction, ScopeSpec, TransferArgs,
+    parse_memory_command,
+};
+use crate::config::{
+    CONFIG_VERSION_KEY, ConfigEdit, ConfigFileStamp, ConfigLayer, ConfigOrigin, LayeredConfig,
+    MemoryConfig, config_etag, config_key_matches, config_schema, edit_config_file_at,
+    is_config_key, load_layered_config, migrate_config_file_at, parse_config_setting,
+    reload_layered_config, repo_config_layer,
+};
+use crate::crypto::{KEY_ENV, KEY_FILE_ENV, PASSPHRASE_ENV, resolve_key_source};
+use crate::llm::{
+    LlmCaptureProposal, LlmCaptureRequest, LlmSummaryRequest, classify_capture_with_llm,
+    screen_summary, summarize_memories_with_llm,
+};
+use crate::paths::{get_memory_dir, global_config_path, resolve_codex_home, resolve_user_path};
 use crate::render::{
-    build_injection_block, format_auto_capture_status, format_export_markdown, format_stats,
-    render_rows,
+    build_injection_block, format_audit_report, format_auto_capture_status, format_config,
+    format_config_migration, format_export_markdown, format_scope_info, format_scopes,
+    format_secret_findings, format_stats, format_sync_status, render_rows,
 };
-use crate::scope::detect_project_scope;
+use crate::scope::{
+    SCOPE_MARKER_FILE, branch_scope, detect_project_scope_with, is_remote_url, project_label,
+    resolve_scope_alias, scope_detection_stats,
+};
+use crate::secrets::{SecretFinding, SecretScanner, mask_secret};
 use crate::store::MemoryStore;
 use crate::types::{
-    AddMemoryInput, AddMemoryResult, CompactionMode, CompactionResult, MemoryCategory, PagedResult,
-    ResolveIdResult, ScopeInfo, ScopeTarget, SyncAgentsResult,
+    AddMemoryInput, AddMemoryResult, AutoCaptureCandidate, CaptureAction, CompactionMode,
+    CompactionResult, MemoryCategory, MemoryRow, PagedResult, ResolveIdResult, ReviewOutcome,
+    ScopeInfo, ScopeTarget, SyncAgentsResult, SyncJobState, SyncJobStatus, TransferOutcome,
+};
+use crate::utils::{
+    format_memory_scope, label_memory_scope, now_iso, now_utc, sha256, truncate_chars,
 };
-use crate::utils::{format_memory_scope, now_iso, truncate_chars};
 use anyhow::{Context, Result};
 use base64::Engine;
 use serde_json::{Value, json};
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
 const MAX_AUTO_CAPTURE_TRACKED_HASHES: usize = 5_000;
+const SCOPE_LAST_SEEN_REFRESH: Duration = Duration::from_secs(60 * 60);
 
 fn ok(action: &str, data: Value) -> Value {
     json!({
@@ -63,16 +91,121 @@ fn cursor_decode(cursor: Option<&str>) -> Result<usize> {
 }
 
 fn current_scopes(scope_info: &ScopeInfo) -> Vec<String> {
-    vec![scope_info.scope.clone(), "global".to_string()]
+    scope_info
+        .package_scope
+        .iter()
+        .chain(scope_info.branch_scope.iter())
+        .cloned()
+        .chain([scope_info.scope.clone()])
+        .chain(scope_info.aliases.iter().cloned())
+        .chain(scope_info.org_scope.iter().cloned())
+        .chain(["global".to_string()])
+        .collect()
 }
 
 fn scope_from_target(scope_info: &ScopeInfo, target: ScopeTarget) -> String {
     match target {
+        ScopeTarget::Branch => scope_info
+            .branch_scope
+            .clone()
+            .unwrap_or_else(|| scope_info.scope.clone()),
+        ScopeTarget::Package => scope_info
+            .package_scope
+            .clone()
+            .unwrap_or_else(|| scope_info.scope.clone()),
         ScopeTarget::Project => scope_info.scope.clone(),
+        ScopeTarget::Org => scope_info
+            .org_scope
+            .clone()
+            .unwrap_or_else(|| scope_info.scope.clone()),
         ScopeTarget::Global => "global".to_string(),
     }
 }
 
+fn unavailable_target(scope_info: &ScopeInfo, target: ScopeTarget) -> Option<&'static str> {
+    match target {
+        ScopeTarget::Package if scope_info.package_scope.is_none() => {
+            Some("No sub-project detected for this directory. Use --project or --global.")
+        }
+        ScopeTarget::Branch if scope_info.branch_scope.is_none() => {
+            Some("No git branch checked out in this directory. Use --project or --global.")
+        }
+        ScopeTarget::Org if scope_info.org_scope.is_none() => {
+            Some("No git remote owner detected for this directory. Use --project or --global.")
+        }
+        _ => None,
+    }
+}
+
+fn prefer_nearest_scope(rows: Vec<MemoryRow>, scopes: &[String]) -> Vec<MemoryRow> {
+    let rank = |scope: &str| {
+        scopes
+            .iter()
+            .position(|candidate| candidate == scope)
+            .unwrap_or(scopes.len())
+    };
+    let mut nearest = HashMap::<String, usize>::new();
+    for row in &rows {
+        let entry = nearest
+            .entry(row.content_hash.clone())
+            .or_insert(usize::MAX);
+        *entry = (*entry).min(rank(&row.scope));
+    }
+    rows.into_iter()
+        .filter(|row| nearest.get(&row.content_hash) == Some(&rank(&row.scope)))
+        .collect()
+}
+
+fn injectable_rows(rows: Vec<MemoryRow>, scopes: &[String]) -> Vec<MemoryRow> {
+    let rows = rows
+        .into_iter()
+        .filter(|row| row.scope != "global" || row.pinned)
+        .collect();
+    prefer_nearest_scope(rows, scopes)
+}
+
+const CORRECTION_STOPWORDS: &[&str] = &[
+    "a", "about", "all", "an", "and", "are", "at", "be", "by", "for", "i", "in", "is", "it", "my",
+    "of", "on", "our", "that", "the", "to", "use", "using", "we", "with",
+];
+
+fn correction_query(target: &str) -> String {
+    target
+        .split(|c: char| !c.is_alphanumeric() && c != '_' && c != '-')
+        .map(str::to_lowercase)
+        .filter(|word| word.len() >= 2 && !CORRECTION_STOPWORDS.contains(&word.as_str()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Corrections below this overlap with the matched memory go to review instead.
+const RETRACT_MIN_SIMILARITY: f64 = 0.5;
+
+fn correction_similarity(target: &str, content: &str) -> f64 {
+    let target = correction_query(target);
+    let content = correction_query(content);
+    let target = target.split_whitespace().collect::<Vec<_>>();
+    let content = content.split_whitespace().collect::<Vec<_>>();
+    if target.is_empty() || content.is_empty() {
+        return 0.0;
+    }
+    let shared = target
+        .iter()
+        .filter(|word| {
+            content
+                .iter()
+                .any(|other| other.starts_with(**word) || word.starts_with(other))
+        })
+        .count();
+    2.0 * shared as f64 / (target.len() + content.len()) as f64
+}
+
+fn unknown_scope_message(raw: &str) -> String {
+    format!(
+        "Unknown scope '{raw}'. Use current, global, org:<host>/<owner>, a scope or label from /memory scopes, a remote URL, or an absolute path."
+    )
+}
+
 fn cat_for_str(category: Option<String>) -> Result<MemoryCategory> {
     match category {
         Some(v) => v.parse::<MemoryCategory>().map_err(anyhow::Error::msg),
@@ -128,12 +261,127 @@ fn resolve_export_path_within_workspace(
     }
 }
 
+fn audit_matches(text: &str, findings: &[SecretFinding]) -> (Vec<String>, Vec<Value>) {
+    let mut detectors = Vec::new();
+    let mut matches = Vec::new();
+    for finding in findings.iter().filter(|finding| !finding.allowlisted) {
+        if !detectors.contains(&finding.detector) {
+            detectors.push(finding.detector.clone());
+        }
+        matches.push(json!({
+            "detector": finding.detector,
+            "kind": finding.kind,
+            "start": finding.start,
+            "end": finding.end,
+            "preview": mask_secret(&text[finding.start..finding.end]),
+        }));
+    }
+    (detectors, matches)
+}
+
+fn redact_json_strings(value: &mut Value, scanner: &SecretScanner) {
+    match value {
+        Value::String(text) => {
+            let (redacted, detectors) = scanner.redact(text);
+            if !detectors.is_empty() {
+                *text = redacted;
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                redact_json_strings(item, scanner);
+            }
+        }
+        Value::Object(map) => {
+            for item in map.values_mut() {
+                redact_json_strings(item, scanner);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn empty_compaction() -> CompactionResult {
+    CompactionResult {
+        mode: CompactionMode::None,
+        block: String::new(),
+        input_count: 0,
+        output_count: 0,
+        input_chars: 0,
+        output_chars: 0,
+        model: None,
+        reason: None,
+    }
+}
+
+fn key_file_path(config: &MemoryConfig, memory_dir: &Path) -> Option<PathBuf> {
+    config
+        .encryption
+        .key_file
+        .as_deref()
+        .map(|raw| resolve_user_path(raw, memory_dir))
+}
+
+fn compile_configured_rules(config: &MemoryConfig) -> CaptureRules {
+    let rules = compile_capture_rules(&config.auto_capture.rules);
+    for error in rules.error_messages() {
+        eprintln!("codex-extra-memory: {error}; rule is ignored until fixed.");
+    }
+    rules
+}
+
+fn build_secret_scanner(config: &MemoryConfig) -> (SecretScanner, Vec<String>) {
+    let (scanner, errors) = SecretScanner::from_config(&config.secrets);
+    for error in &errors {
+        eprintln!("codex-extra-memory: {error}; entry is ignored until fixed.");
+    }
+    (scanner, errors)
+}
+
+// Anything that changes the synced block changes this: new, removed, edited, or re-pinned rows.
+fn sync_rows_hash(rows: &[MemoryRow]) -> String {
+    let fingerprint = rows
+        .iter()
+        .map(|row| {
+            format!(
+                "{}:{}:{}:{}:{}",
+                row.id,
+                row.content_hash,
+                row.status,
+                row.pinned,
+                row.updated_at.to_rfc3339()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    sha256(&fingerprint)
+}
+
+fn serde_to_value<T: serde::Serialize>(value: T) -> Result<Value> {
+    Ok(serde_json::to_value(value)?)
+}
+
+pub struct PendingLlmCompaction {
+    pub job_id: u64,
+    pub workspace_dir: PathBuf,
+    pub scope_info: ScopeInfo,
+    pub rows: Vec<MemoryRow>,
+    pub rows_hash: String,
+    pub request: LlmSummaryRequest,
+}
+
 pub struct MemoryService {
     store: MemoryStore,
     config: MemoryConfig,
+    capture_rules: CaptureRules,
+    secret_errors: Vec<String>,
     config_path: PathBuf,
-    processed_hashes: HashSet<String>,
-    processed_order: VecDeque<String>,
+    config_layers: LayeredConfig,
+    config_stamp: ConfigFileStamp,
+    workspace_config: Option<(PathBuf, Option<SystemTime>)>,
+    recorded_scope: Option<(ScopeInfo, SystemTime)>,
+    sync_jobs: HashMap<PathBuf, SyncJobStatus>,
+    next_sync_job_id: u64,
 }
 
 impl MemoryService {
@@ -146,18 +394,30 @@ impl MemoryService {
         fs::create_dir_all(memory_dir)
             .with_context(|| format!("create memory dir {}", memory_dir.display()))?;
 
-        let config_path = memory_dir.join("config.json");
+        let config_path = global_config_path(memory_dir);
         let db_path = memory_dir.join("memory.sqlite");
 
-        let config = load_config_file_at(&config_path)?;
-        let store = MemoryStore::open(&db_path)?;
+        let config_stamp = ConfigFileStamp::read(&config_path);
+        let config_layers = load_layered_config(memory_dir)?;
+        let config = config_layers.resolve();
+        let mut store = MemoryStore::open(&db_path)?;
+        let (scanner, secret_errors) = build_secret_scanner(&config);
+        store.set_secret_scanner(scanner);
+        store
+            .unlock(resolve_key_source(key_file_path(&config, memory_dir).as_deref())?.as_ref())?;
 
         Ok(Self {
             store,
+            capture_rules: compile_configured_rules(&config),
+            secret_errors,
             config,
             config_path,
-            processed_hashes: HashSet::new(),
-            processed_order: VecDeque::new(),
+            config_layers,
+            config_stamp,
+            workspace_config: None,
+            recorded_scope: None,
+            sync_jobs: HashMap::new(),
+            next_sync_job_id: 0,
         })
     }
 
@@ -165,26 +425,160 @@ impl MemoryService {
         &self.config
     }
 
-    fn save_config(&self) -> Result<()> {
-        save_config_file_at(&self.config_path, &self.config)
+    fn global_layer(&self) -> Option<&ConfigLayer> {
+        self.config_layers
+            .layers()
+            .iter()
+            .find(|layer| layer.origin == "global")
+    }
+
+    fn global_config_values(&self) -> Value {
+        self.global_layer()
+            .map_or_else(|| json!({}), |layer| layer.values.clone())
+    }
+
+    pub fn reload_config_if_changed(&mut self) -> Result<bool> {
+        let Some(memory_dir) = self.config_path.parent().map(Path::to_path_buf) else {
+            return Ok(false);
+        };
+        let config_path = global_config_path(&memory_dir);
+        let stamp = ConfigFileStamp::read(&config_path);
+        if stamp == self.config_stamp {
+            return Ok(false);
+        }
+        if config_path == self.config_path
+            && config_etag(&config_path) == self.global_layer().and_then(|layer| layer.etag.clone())
+        {
+            self.config_stamp = stamp;
+            return Ok(false);
+        }
+
+        let layers = match reload_layered_config(&memory_dir) {
+            Ok(layers) => layers,
+            Err(error) => {
+                eprintln!("codex-extra-memory: {error:#}; keeping the last good config.");
+                self.config_stamp = stamp;
+                return Ok(false);
+            }
+        };
+        self.config_layers = layers;
+        self.config_path = config_path;
+        self.config_stamp = stamp;
+        self.workspace_config = None;
+        self.apply_config(self.config_layers.resolve());
+        Ok(true)
+    }
+
+    fn set_global_config(&mut self, updates: &[(&str, Value)]) -> Result<()> {
+        let edits = updates
+            .iter()
+            .map(|(key, value)| ConfigEdit::Set((*key).to_string(), value.clone()))
+            .collect::<Vec<_>>();
+        self.update_global_config(&edits).map(|_| ())
+    }
+
+    fn update_global_config(&mut self, edits: &[ConfigEdit]) -> Result<bool> {
+        self.reload_config_if_changed()?;
+        let mut layers = self.config_layers.layers().to_vec();
+        let Some(global) = layers.iter_mut().find(|layer| layer.origin == "global") else {
+            anyhow::bail!("global config layer is not loaded");
+        };
+        let etag = global.etag.clone().unwrap_or_default();
+        let (edited, changed) = edit_config_file_at(&self.config_path, &etag, edits)?;
+        *global = edited;
+        self.config_layers = LayeredConfig::new(layers);
+        self.config_stamp = ConfigFileStamp::read(&self.config_path);
+        self.workspace_config = None;
+        self.apply_config(self.config_layers.resolve());
+        Ok(changed)
+    }
+
+    fn apply_config(&mut self, config: MemoryConfig) {
+        if config.secrets != self.config.secrets {
+            let (scanner, errors) = build_secret_scanner(&config);
+            self.store.set_secret_scanner(scanner);
+            self.secret_errors = errors;
+        }
+        if config.auto_capture.rules != self.config.auto_capture.rules {
+            self.capture_rules = compile_configured_rules(&config);
+        }
+        self.config = config;
+    }
+
+    fn workspace_layers(&self, root: &Path) -> LayeredConfig {
+        self.config_layers.with_repo_layer(repo_config_layer(root))
     }
 
-    fn track_processed_hash(&mut self, hash: String) {
-        if self.processed_hashes.contains(&hash) {
+    fn use_workspace_config(&mut self, root: &Path) {
+        let modified = fs::metadata(root.join(SCOPE_MARKER_FILE))
+            .and_then(|meta| meta.modified())
+            .ok();
+        let key = (root.to_path_buf(), modified);
+        if self.workspace_config.as_ref() == Some(&key) {
             return;
         }
-        self.processed_hashes.insert(hash.clone());
-        self.processed_order.push_back(hash);
+        let config = self.workspace_layers(root).resolve();
+        self.apply_config(config);
+        self.workspace_config = Some(key);
+    }
+
+    fn track_processed_hash(&mut self, hash: &str) -> Result<()> {
+        self.store
+            .track_processed_hash(hash, MAX_AUTO_CAPTURE_TRACKED_HASHES)
+    }
 
-        while self.processed_order.len() > MAX_AUTO_CAPTURE_TRACKED_HASHES {
-            if let Some(first) = self.processed_order.pop_front() {
-                self.processed_hashes.remove(&first);
+    fn detect_scope(&mut self, workspace_dir: &Path) -> ScopeInfo {
+        let scope_info = detect_project_scope_with(workspace_dir, &self.config.scope.detectors);
+        self.use_workspace_config(Path::new(&scope_info.root));
+        let fresh = self.recorded_scope.as_ref().is_some_and(|(recorded, at)| {
+            *recorded == scope_info && at.elapsed().is_ok_and(|age| age < SCOPE_LAST_SEEN_REFRESH)
+        });
+        if !fresh {
+            match self.record_scope(&scope_info) {
+                Ok(()) => self.recorded_scope = Some((scope_info.clone(), SystemTime::now())),
+                Err(error) => eprintln!("codex-extra-memory: failed to record scope: {error}"),
             }
         }
+        scope_info
     }
 
-    fn detect_scope(workspace_dir: &Path) -> ScopeInfo {
-        detect_project_scope(workspace_dir)
+    fn record_scope(&self, scope_info: &ScopeInfo) -> Result<()> {
+        let label = project_label(scope_info);
+        self.store.record_scope(
+            &scope_info.scope,
+            &scope_info.kind,
+            &scope_info.identifier,
+            &scope_info.root,
+            &label,
+        )?;
+        if let (Some(package), Some(package_scope), Some(package_root)) = (
+            &scope_info.package,
+            &scope_info.package_scope,
+            &scope_info.package_root,
+        ) {
+            self.store.record_scope(
+                package_scope,
+                "package",
+                package,
+                package_root,
+                &format!("{label}#{package}"),
+            )?;
+        }
+        if let (Some(branch), Some(branch_scope)) = (&scope_info.branch, &scope_info.branch_scope) {
+            self.store.record_scope(
+                branch_scope,
+                "branch",
+                branch,
+                &scope_info.root,
+                &format!("{label}@{branch}"),
+            )?;
+        }
+        if let Some(org_scope) = &scope_info.org_scope {
+            let org = org_scope.trim_start_matches("org:");
+            self.store
+                .record_scope(org_scope, "org", org, &scope_info.root, org_scope)?;
+        }
+        Ok(())
     }
 
     pub fn execute_command(&mut self, command: &str, workspace_dir: &Path) -> Result<Value> {
@@ -197,6 +591,7 @@ impl MemoryService {
             MemoryCommand::Help => Ok(ok("help", json!({"text": COMMAND_HELP}))),
             MemoryCommand::Refresh => self.refresh(),
             MemoryCommand::Sync => self.sync_agents(workspace_dir),
+            MemoryCommand::SyncStatus => self.sync_status(workspace_dir),
             MemoryCommand::Add(args) => self.add_memory(
                 args.text,
                 Some(args.scope_target),
@@ -205,14 +600,17 @@ impl MemoryService {
                 "user",
             ),
             MemoryCommand::Show => self.show_injection_preview(workspace_dir),
-            MemoryCommand::List { limit, cursor } => {
-                self.list_memories(workspace_dir, limit, cursor)
-            }
+            MemoryCommand::List {
+                limit,
+                cursor,
+                include_pending,
+            } => self.list_memories(workspace_dir, limit, cursor, include_pending),
             MemoryCommand::Search {
                 query,
                 limit,
                 cursor,
-            } => self.search_memories(workspace_dir, query, limit, cursor),
+                include_pending,
+            } => self.search_memories(workspace_dir, query, limit, cursor, include_pending),
             MemoryCommand::Delete { id_or_prefix } => {
                 self.delete_memory(workspace_dir, id_or_prefix)
             }
@@ -221,6 +619,7 @@ impl MemoryService {
                 enabled,
             } => self.pin_memory(workspace_dir, id_or_prefix, enabled),
             MemoryCommand::Auto { mode } => self.auto_capture_mode(mode),
+            MemoryCommand::Review(action) => self.review_memories(workspace_dir, action),
             MemoryCommand::Stats => self.stats(workspace_dir),
             MemoryCommand::Export(args) => self.export_memories(
                 workspace_dir,
@@ -228,6 +627,20 @@ impl MemoryService {
                 args.include_all_scopes,
                 args.output_path_raw,
             ),
+            MemoryCommand::Backfill(args) => self.backfill(workspace_dir, args),
+            MemoryCommand::Audit(args) => self.audit(workspace_dir, args),
+            MemoryCommand::Encrypt => self.encrypt_store(),
+            MemoryCommand::Decrypt => self.decrypt_store(),
+            MemoryCommand::SecretsTest { text } => Ok(self.secrets_test(&text)),
+            MemoryCommand::Scopes => self.list_scopes(workspace_dir),
+            MemoryCommand::Config(action) => self.config_command(workspace_dir, action),
+            MemoryCommand::Move(args) => self.transfer_memory(workspace_dir, args, false),
+            MemoryCommand::Copy(args) => self.transfer_memory(workspace_dir, args, true),
+            MemoryCommand::BranchPromote { branch } => self.promote_branch(workspace_dir, branch),
+            MemoryCommand::Scope(ScopeAction::Show) => Ok(self.scope_show(workspace_dir)),
+            MemoryCommand::Scope(ScopeAction::Migrate { from, to }) => {
+                self.migrate_scope(workspace_dir, &from, &to)
+            }
         }
     }
 
@@ -239,8 +652,11 @@ impl MemoryService {
         workspace_dir: &Path,
         source: &str,
     ) -> Result<Value> {
-        let scope_info = Self::detect_scope(workspace_dir);
+        let scope_info = self.detect_scope(workspace_dir);
         let target_scope = scope.unwrap_or(ScopeTarget::Project);
+        if let Some(message) = unavailable_target(&scope_info, target_scope) {
+            return Ok(err("add", message));
+        }
         let result = self.store.add_memory(AddMemoryInput {
             scope: scope_from_target(&scope_info, target_scope),
             category: category.unwrap_or(MemoryCategory::Other),
@@ -249,12 +665,22 @@ impl MemoryService {
         })?;
 
         match result {
-            AddMemoryResult::Blocked { reason } => Ok(err("add", reason)),
+            AddMemoryResult::Blocked { reason, detectors } => {
+                if detectors.is_empty() {
+                    Ok(err("add", reason))
+                } else {
+                    Ok(err(
+                        "add",
+                        format!("{reason} Detectors: {}.", detectors.join(", ")),
+                    ))
+                }
+            }
             AddMemoryResult::Added {
                 id,
                 scope,
                 category,
                 content,
+                redacted,
             } => Ok(ok(
                 "add",
                 json!({
@@ -264,6 +690,7 @@ impl MemoryService {
                     "scope_label": format_memory_scope(&scope, &scope_info.scope),
                     "category": category,
                     "content": content,
+                    "redacted": redacted,
                 }),
             )),
             AddMemoryResult::Deduped {
@@ -271,6 +698,7 @@ impl MemoryService {
                 scope,
                 category,
                 content,
+                redacted,
             } => Ok(ok(
                 "add",
                 json!({
@@ -280,22 +708,27 @@ impl MemoryService {
                     "scope_label": format_memory_scope(&scope, &scope_info.scope),
                     "category": category,
                     "content": content,
+                    "redacted": redacted,
                 }),
             )),
         }
     }
 
     pub fn list_memories(
-        &self,
+        &mut self,
         workspace_dir: &Path,
         limit: Option<usize>,
         cursor: Option<String>,
+        include_pending: bool,
     ) -> Result<Value> {
-        let scope_info = Self::detect_scope(workspace_dir);
+        let scope_info = self.detect_scope(workspace_dir);
         let scopes = current_scopes(&scope_info);
         let limit = limit.unwrap_or(self.config.list_limit).min(200);
         let offset = cursor_decode(cursor.as_deref())?;
-        let (items, has_more) = self.store.list_memories(&scopes, limit, offset)?;
+        let (items, has_more) =
+            self.store
+                .list_memories(&scopes, limit, offset, include_pending)?;
+        let items = prefer_nearest_scope(items, &scopes);
         let next_cursor = has_more.then(|| cursor_encode(offset + limit));
 
         let page = PagedResult {
@@ -309,28 +742,32 @@ impl MemoryService {
             "list",
             json!({
                 "page": page,
-                "rendered": render_rows(&items, &scope_info),
+                "rendered": render_rows(&items, &scope_info, &self.store.scope_labels()?),
             }),
         ))
     }
 
     pub fn search_memories(
-        &self,
+        &mut self,
         workspace_dir: &Path,
         query: String,
         limit: Option<usize>,
         cursor: Option<String>,
+        include_pending: bool,
     ) -> Result<Value> {
         if query.trim().is_empty() {
             return Ok(err("search", "query must not be empty"));
         }
 
-        let scope_info = Self::detect_scope(workspace_dir);
+        let scope_info = self.detect_scope(workspace_dir);
         let scopes = current_scopes(&scope_info);
         let limit = limit.unwrap_or(self.config.search_limit).min(200);
         let offset = cursor_decode(cursor.as_deref())?;
 
-        let (items, has_more) = self.store.search_memories(&scopes, &query, limit, offset)?;
+        let (items, has_more) =
+            self.store
+                .search_memories(&scopes, &query, limit, offset, include_pending)?;
+        let items = prefer_nearest_scope(items, &scopes);
         let next_cursor = has_more.then(|| cursor_encode(offset + limit));
 
         let page = PagedResult {
@@ -348,14 +785,14 @@ impl MemoryService {
                 "rendered": if items.is_empty() {
                     "No memory matched query.".to_string()
                 } else {
-                    render_rows(&items, &scope_info)
+                    render_rows(&items, &scope_info, &self.store.scope_labels()?)
                 },
             }),
         ))
     }
 
     pub fn delete_memory(&mut self, workspace_dir: &Path, id_or_prefix: String) -> Result<Value> {
-        let scope_info = Self::detect_scope(workspace_dir);
+        let scope_info = self.detect_scope(workspace_dir);
         let scopes = current_scopes(&scope_info);
 
         match self.store.resolve_id(&id_or_prefix, Some(&scopes))? {
@@ -385,7 +822,7 @@ impl MemoryService {
         id_or_prefix: String,
         enabled: bool,
     ) -> Result<Value> {
-        let scope_info = Self::detect_scope(workspace_dir);
+        let scope_info = self.detect_scope(workspace_dir);
         let scopes = current_scopes(&scope_info);
 
         match self.store.resolve_id(&id_or_prefix, Some(&scopes))? {
@@ -412,10 +849,11 @@ impl MemoryService {
         }
     }
 
-    pub fn stats(&self, workspace_dir: &Path) -> Result<Value> {
-        let scope_info = Self::detect_scope(workspace_dir);
+    pub fn stats(&mut self, workspace_dir: &Path) -> Result<Value> {
+        let scope_info = self.detect_scope(workspace_dir);
         let scopes = current_scopes(&scope_info);
-        let stats = self.store.get_stats(&scopes)?;
+        let mut stats = self.store.get_stats(&scopes)?;
+        stats.scope_detection = Some(scope_detection_stats());
 
         Ok(ok(
             "stats",
@@ -426,140 +864,755 @@ impl MemoryService {
         ))
     }
 
-    pub fn show_injection_preview(&mut self, workspace_dir: &Path) -> Result<Value> {
-        let scope_info = Self::detect_scope(workspace_dir);
-        let candidates = self.store.get_injection_candidates(
-            &scope_info.scope,
-            self.config.injection.max_items.saturating_mul(4).max(20),
-        )?;
-
-        let filtered = candidates
-            .into_iter()
-            .filter(|row| row.scope == scope_info.scope || (row.scope == "global" && row.pinned))
-            .collect::<Vec<_>>();
+    pub fn encrypt_store(&mut self) -> Result<Value> {
+        if self.store.encryption_status()?.is_some() {
+            return Ok(err("encrypt", "Memory database is already encrypted."));
+        }
+        let memory_dir = self
+            .config_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let Some(source) = resolve_key_source(key_file_path(&self.config, &memory_dir).as_deref())?
+        else {
+            return Ok(err(
+                "encrypt",
+                format!(
+                    "No encryption key configured. Set {KEY_ENV}, {KEY_FILE_ENV}, {PASSPHRASE_ENV}, or encryption.keyFile."
+                ),
+            ));
+        };
 
-        let block = build_injection_block(
-            &filtered,
-            &scope_info,
-            self.config.injection.max_items,
-            self.config.injection.max_chars,
-        );
+        let (memories, events) = self.store.encrypt_database(&source)?;
+        Ok(ok(
+            "encrypt",
+            json!({
+                "memories": memories,
+                "events": events,
+                "kdf": source.kdf(),
+                "rendered": format!(
+                    "Encrypted {memories} memories and {events} event payloads. Full-text search is disabled; search now decrypts and filters."
+                ),
+            }),
+        ))
+    }
 
+    pub fn decrypt_store(&mut self) -> Result<Value> {
+        if self.store.encryption_status()?.is_none() {
+            return Ok(err("decrypt", "Memory database is not encrypted."));
+        }
+        let (memories, events) = self.store.decrypt_database()?;
         Ok(ok(
-            "show",
+            "decrypt",
             json!({
+                "memories": memories,
+                "events": events,
+                "rendered": format!(
+                    "Decrypted {memories} memories and {events} event payloads. Full-text search is rebuilt."
+                ),
+            }),
+        ))
+    }
+
+    #[must_use]
+    pub fn scope_show(&mut self, workspace_dir: &Path) -> Value {
+        let scope_info = self.detect_scope(workspace_dir);
+        ok(
+            "scope",
+            json!({
+                "rendered": format_scope_info(&scope_info),
                 "scope": scope_info,
-                "candidate_count": filtered.len(),
-                "block": block,
+            }),
+        )
+    }
+
+    pub fn list_scopes(&mut self, workspace_dir: &Path) -> Result<Value> {
+        let scope_info = self.detect_scope(workspace_dir);
+        let scopes = self.store.list_scopes(None)?;
+        Ok(ok(
+            "scopes",
+            json!({
+                "current": scope_info.scope,
+                "rendered": format_scopes(&scopes, &scope_info),
+                "scopes": scopes,
             }),
         ))
     }
 
-    pub fn auto_capture_mode(&mut self, mode: AutoMode) -> Result<Value> {
-        match mode {
-            AutoMode::Status => Ok(ok(
-                "auto",
-                json!({
-                    "enabled": self.config.auto_capture.enabled,
-                    "scope": self.config.auto_capture.scope,
-                    "rendered": format_auto_capture_status(&self.config),
-                }),
-            )),
-            AutoMode::On => {
-                self.config.auto_capture.enabled = true;
-                self.save_config()?;
-                Ok(ok("auto", json!({"enabled": true})))
-            }
-            AutoMode::Off => {
-                self.config.auto_capture.enabled = false;
-                self.save_config()?;
-                Ok(ok("auto", json!({"enabled": false})))
+    fn config_command(&mut self, workspace_dir: &Path, action: ConfigAction) -> Result<Value> {
+        self.reload_config_if_changed()?;
+        match action {
+            ConfigAction::Show { origin } => Ok(self.config_show(workspace_dir, origin)),
+            ConfigAction::Get { key } => Ok(self.config_get(workspace_dir, &key)),
+            ConfigAction::Set { key, value } => self.config_set(workspace_dir, &key, &value),
+            ConfigAction::Unset { key } => self.config_unset(workspace_dir, &key),
+            ConfigAction::Migrate { dry_run } => self.config_migrate(dry_run),
+            ConfigAction::Schema => {
+                let schema = config_schema();
+                Ok(ok(
+                    "config",
+                    json!({
+                        "rendered": serde_json::to_string_pretty(&schema)?,
+                        "schema": schema,
+                    }),
+                ))
             }
         }
     }
 
-    pub fn refresh(&mut self) -> Result<Value> {
-        self.store.refresh(&self.config)?;
-        Ok(ok("refresh", json!({"refreshed": true})))
+    fn config_origins(&mut self, workspace_dir: &Path, key: &str) -> Vec<ConfigOrigin> {
+        let scope_info = self.detect_scope(workspace_dir);
+        self.workspace_layers(Path::new(&scope_info.root))
+            .origins(&self.config)
+            .into_iter()
+            .filter(|entry| config_key_matches(key, &entry.key))
+            .collect()
     }
 
-    pub fn export_memories(
-        &self,
-        workspace_dir: &Path,
-        format: ExportFormat,
-        include_all_scopes: bool,
-        output_path_raw: String,
-    ) -> Result<Value> {
-        let scope_info = Self::detect_scope(workspace_dir);
-        let scopes = current_scopes(&scope_info);
-
-        let output_path =
-            match resolve_export_path_within_workspace(workspace_dir, format, &output_path_raw) {
-                Ok(path) => path,
-                Err(message) => return Ok(err("export", message)),
-            };
+    fn config_value(&self, key: &str) -> Value {
+        serde_json::to_value(&self.config)
+            .ok()
+            .and_then(|config| {
+                config
+                    .pointer(&format!("/{}", key.replace('.', "/")))
+                    .cloned()
+            })
+            .unwrap_or(Value::Null)
+    }
 
-        let entries = if include_all_scopes {
-            self.store.export_active_memories(None)?
-        } else {
-            self.store.export_active_memories(Some(&scopes))?
-        };
+    pub fn config_get(&mut self, workspace_dir: &Path, key: &str) -> Value {
+        if !is_config_key(key) {
+            return err("config", format!("Unknown config key '{key}'."));
+        }
+        let origins = self.config_origins(workspace_dir, key);
+        ok(
+            "config",
+            json!({
+                "key": key,
+                "value": self.config_value(key),
+                "rendered": format_config(&origins, true),
+                "origins": origins,
+            }),
+        )
+    }
 
-        let stats = if include_all_scopes {
-            let all_scopes = entries
-                .iter()
-                .map(|row| row.scope.clone())
-                .collect::<HashSet<_>>();
-            self.store
-                .get_stats(&all_scopes.into_iter().collect::<Vec<String>>())?
-        } else {
-            self.store.get_stats(&scopes)?
+    pub fn config_set(&mut self, workspace_dir: &Path, key: &str, raw: &str) -> Result<Value> {
+        let value = match parse_config_setting(&self.global_config_values(), key, raw) {
+            Ok(value) => value,
+            Err(message) => return Ok(err("config", message)),
         };
-
-        let payload = match format {
-            ExportFormat::Json => serde_json::to_string_pretty(&json!({
-                "schema_version": 1,
-                "generated_at": now_iso(),
-                "workspace_root": workspace_dir.to_string_lossy(),
-                "project_scope": scope_info.scope,
-                "include_all_scopes": include_all_scopes,
-                "stats_snapshot": stats,
-                "entries": entries,
-            }))?,
-            ExportFormat::Markdown => format_export_markdown(&entries),
+        self.set_global_config(&[(key, value.clone())])?;
+        let origins = self.config_origins(workspace_dir, key);
+        let overridden = origins
+            .iter()
+            .find(|entry| entry.origin == "repo" || entry.origin == "env")
+            .map(|entry| {
+                format!(
+                    "{}: {}",
+                    entry.origin,
+                    entry.source.clone().unwrap_or_default()
+                )
+            });
+        let rendered = match &overridden {
+            Some(layer) => format!(
+                "Set {key} = {value} in {}. It is still overridden here by {layer}.",
+                self.config_path.display()
+            ),
+            None => format!("Set {key} = {value} in {}.", self.config_path.display()),
         };
+        Ok(ok(
+            "config",
+            json!({
+                "key": key,
+                "value": value,
+                "effective": self.config_value(key),
+                "overridden_by": overridden,
+                "rendered": rendered,
+            }),
+        ))
+    }
 
-        if let Some(parent) = output_path.parent() {
-            fs::create_dir_all(parent)
-                .with_context(|| format!("create export directory {}", parent.display()))?;
+    pub fn config_migrate(&mut self, dry_run: bool) -> Result<Value> {
+        let migration = migrate_config_file_at(&self.config_path, dry_run)?;
+        if migration.from_version != migration.to_version && !dry_run {
+            self.config_stamp = ConfigFileStamp::default();
+            self.reload_config_if_changed()?;
         }
-        fs::write(&output_path, payload)
-            .with_context(|| format!("write export {}", output_path.display()))?;
+        Ok(ok(
+            "config",
+            json!({
+                "migration": migration,
+                "rendered": format_config_migration(&migration),
+            }),
+        ))
+    }
 
+    pub fn config_unset(&mut self, workspace_dir: &Path, key: &str) -> Result<Value> {
+        if !is_config_key(key) {
+            return Ok(err("config", format!("Unknown config key '{key}'.")));
+        }
+        if key == CONFIG_VERSION_KEY {
+            return Ok(err(
+                "config",
+                format!("{CONFIG_VERSION_KEY} is managed by `/memory config migrate`."),
+            ));
+        }
+        let removed = self.update_global_config(&[ConfigEdit::Unset(key.to_string())])?;
+        let origins = self.config_origins(workspace_dir, key);
+        let effective = self.config_value(key);
+        let origin = origins
+            .iter()
+            .find(|entry| entry.key == key)
+            .map_or("mixed", |entry| entry.origin.as_str());
+        let rendered = if removed {
+            format!(
+                "Unset {key} in {}. It now resolves to {effective} ({origin}).",
+                self.config_path.display()
+            )
+        } else {
+            format!(
+                "{key} is not set in {}. It resolves to {effective} ({origin}).",
+                self.config_path.display()
+            )
+        };
         Ok(ok(
-            "export",
+            "config",
             json!({
-                "count": entries.len(),
-                "format": format.as_str(),
-                "path": output_path,
+                "key": key,
+                "removed": removed,
+                "effective": effective,
+                "origins": origins,
+                "rendered": rendered,
             }),
         ))
     }
 
-    fn deterministic_compaction_block(
-        &self,
-        scope_info: &ScopeInfo,
-        rows: &[crate::types::MemoryRow],
-    ) -> String {
-        let header = [
-            "## Extra Memory (Codex)",
-            "Compacted memory summary (deterministic fallback).",
-        ]
-        .join("\n");
+    pub fn memory_config_typed(
+        &mut self,
+        workspace_dir: &Path,
+        action: Option<String>,
+        key: Option<String>,
+        value: Option<String>,
+        origin: Option<bool>,
+        dry_run: Option<bool>,
+    ) -> Result<Value> {
+        let key = || {
+            key.clone()
+                .context("key is required for this config action")
+        };
+        let action = match action.as_deref().unwrap_or("show") {
+            "show" => ConfigAction::Show {
+                origin: origin.unwrap_or(false),
+            },
+            "get" => ConfigAction::Get { key: key()? },
+            "set" => ConfigAction::Set {
+                key: key()?,
+                value: value.context("value is required for set")?,
+            },
+            "unset" => ConfigAction::Unset { key: key()? },
+            "schema" => ConfigAction::Schema,
+            "migrate" => ConfigAction::Migrate {
+                dry_run: dry_run.unwrap_or(false),
+            },
+            other => anyhow::bail!(
+                "unknown config action '{other}'; expected show, get, set, unset, schema or migrate"
+            ),
+        };
+        self.config_command(workspace_dir, action)
+    }
 
-        let mut lines = vec![header];
-        let mut chars = lines[0].chars().count();
-        let mut selected = 0_usize;
+    pub fn config_show(&mut self, workspace_dir: &Path, with_origin: bool) -> Value {
+        let scope_info = self.detect_scope(workspace_dir);
+        let layers = self.workspace_layers(Path::new(&scope_info.root));
+        let origins = layers.origins(&self.config);
+        let mut data = json!({
+            "config": self.config,
+            "layers": layers
+                .layers()
+                .iter()
+                .map(|layer| json!({"origin": layer.origin, "source": layer.source}))
+                .collect::<Vec<_>>(),
+            "rendered": format_config(&origins, with_origin),
+        });
+        if with_origin {
+            data["origins"] = json!(origins);
+        }
+        ok("config", data)
+    }
+
+    pub fn transfer_memory(
+        &mut self,
+        workspace_dir: &Path,
+        args: TransferArgs,
+        copy: bool,
+    ) -> Result<Value> {
+        let action = if copy { "copy" } else { "move" };
+        let scope_info = self.detect_scope(workspace_dir);
+        let scopes = current_scopes(&scope_info);
+        let to = match args.target {
+            ScopeSpec::Target(target) => {
+                if let Some(message) = unavailable_target(&scope_info, target) {
+                    return Ok(err(action, message));
+                }
+                scope_from_target(&scope_info, target)
+            }
+            ScopeSpec::Raw(raw) => match self.resolve_named_scope(&scope_info, &raw)? {
+                Some(scope) => scope,
+                None => return Ok(err(action, unknown_scope_message(&raw))),
+            },
+        };
+
+        let id = match self.store.resolve_id(&args.id_or_prefix, Some(&scopes))? {
+            ResolveIdResult::Missing => return Ok(err(action, "Memory not found.")),
+            ResolveIdResult::Ambiguous { candidates } => {
+                return Ok(err(
+                    action,
+                    format!(
+                        "Multiple memories match '{}': {}",
+                        args.id_or_prefix,
+                        candidates.join(", ")
+                    ),
+                ));
+            }
+            ResolveIdResult::Ok { id } => id,
+        };
+
+        let outcome = if copy {
+            self.store.copy_memory(&id, &to)?
+        } else {
+            self.store.move_memory(&id, &to)?
+        };
+        let labels = self.store.scope_labels()?;
+        let label = |scope: &str| label_memory_scope(scope, &scope_info.scope, &labels);
+        let rendered = match &outcome {
+            TransferOutcome::Missing { .. } => return Ok(err(action, "Memory not found.")),
+            TransferOutcome::Unchanged { id, scope } => {
+                format!("Memory {id} is already in {}.", label(scope))
+            }
+            TransferOutcome::Moved { id, from, to } => {
+                format!("Moved {id} from {} to {}.", label(from), label(to))
+            }
+            TransferOutcome::Copied {
+                id, source_id, to, ..
+            } => format!("Copied {source_id} to {} as {id}.", label(to)),
+            TransferOutcome::Deduped { id, to, .. } if copy => {
+                format!("{} already holds this memory as {id}.", label(to))
+            }
+            TransferOutcome::Deduped {
+                id, source_id, to, ..
+            } => format!(
+                "{} already holds this memory as {id}; {source_id} was merged into it.",
+                label(to)
+            ),
+        };
+        let mut data = serde_json::to_value(&outcome)?;
+        data["rendered"] = json!(rendered);
+        Ok(ok(action, data))
+    }
+
+    pub fn promote_branch(
+        &mut self,
+        workspace_dir: &Path,
+        branch: Option<String>,
+    ) -> Result<Value> {
+        let scope_info = self.detect_scope(workspace_dir);
+        let Some(branch) = branch.or_else(|| scope_info.branch.clone()) else {
+            return Ok(err(
+                "branch",
+                "No git branch checked out. Name the branch to promote.",
+            ));
+        };
+        let from = branch_scope(&scope_info.scope, &branch);
+        let (moved, deduped) =
+            self.store
+                .migrate_scope(&from, &scope_info.scope, "branch_promoted")?;
+        Ok(ok(
+            "branch",
+            json!({
+                "branch": branch,
+                "from": from,
+                "to": scope_info.scope,
+                "moved": moved,
+                "deduped": deduped,
+                "rendered": format!(
+                    "Promoted {moved} memories from branch {branch} into the project scope. {deduped} duplicates were merged into existing memories."
+                ),
+            }),
+        ))
+    }
+
+    fn resolve_named_scope(&self, scope_info: &ScopeInfo, raw: &str) -> Result<Option<String>> {
+        let raw = raw.trim();
+        if raw == "current" {
+            return Ok(Some(scope_info.scope.clone()));
+        }
+        if raw == "global" || raw.starts_with("org:") {
+            return Ok(Some(raw.to_string()));
+        }
+        let known = self
+            .store
+            .list_scopes(None)?
+            .into_iter()
+            .find(|entry| entry.scope == raw || entry.label == raw);
+        if let Some(entry) = known {
+            return Ok(Some(entry.scope));
+        }
+        if Path::new(raw).is_absolute() || is_remote_url(raw) {
+            return Ok(Some(resolve_scope_alias(raw)));
+        }
+        Ok(None)
+    }
+
+    pub fn migrate_scope(&mut self, workspace_dir: &Path, from: &str, to: &str) -> Result<Value> {
+        let scope_info = self.detect_scope(workspace_dir);
+        let Some(from) = self.resolve_named_scope(&scope_info, from)? else {
+            return Ok(err("scope", unknown_scope_message(from)));
+        };
+        let Some(to) = self.resolve_named_scope(&scope_info, to)? else {
+            return Ok(err("scope", unknown_scope_message(to)));
+        };
+        if from == to {
+            return Ok(err("scope", "Source and target scopes are the same."));
+        }
+        if to.starts_with(&format!("{from}#")) || to.starts_with(&format!("{from}@")) {
+            return Ok(err(
+                "scope",
+                "The target is a sub-scope of the source; migrate it the other way.",
+            ));
+        }
+        let (moved, deduped) = self.store.migrate_scope(&from, &to, "scope_migrated")?;
+        // The source's registry rows are gone; the next detection records them again.
+        self.recorded_scope = None;
+        Ok(ok(
+            "scope",
+            json!({
+                "from": from,
+                "to": to,
+                "moved": moved,
+                "deduped": deduped,
+                "rendered": format!(
+                    "Moved {moved} memories from {from} to {to}. {deduped} duplicates were merged into existing memories."
+                ),
+            }),
+        ))
+    }
+
+    pub fn audit(&mut self, workspace_dir: &Path, args: AuditArgs) -> Result<Value> {
+        let report_path = match args.report_path.as_deref() {
+            Some(raw) => {
+                match resolve_export_path_within_workspace(workspace_dir, ExportFormat::Json, raw) {
+                    Ok(path) => Some(path),
+                    Err(message) => return Ok(err("audit", message)),
+                }
+            }
+            None => None,
+        };
+
+        let rows = self.store.audit_memories(args.include_inactive)?;
+        let events = self.store.audit_events()?;
+        let scanner = self.store.secret_scanner();
+        let policy = scanner.policy;
+
+        let mut flagged_events = Vec::new();
+        for event in &events {
+            let (detectors, matches) = audit_matches(&event.payload, &scanner.scan(&event.payload));
+            if detectors.is_empty() {
+                continue;
+            }
+            let redacted = match serde_json::from_str::<Value>(&event.payload) {
+                Ok(mut payload) => {
+                    redact_json_strings(&mut payload, scanner);
+                    serde_json::to_string(&payload)?
+                }
+                Err(_) => scanner.redact(&event.payload).0,
+            };
+            flagged_events.push((event, detectors, matches, redacted));
+        }
+
+        let mut flagged_rows = Vec::new();
+        for row in &rows {
+            let (detectors, matches) = audit_matches(&row.content, &scanner.scan(&row.content));
+            if detectors.is_empty() {
+                continue;
+            }
+            let redacted = scanner.redact(&row.content).0;
+            flagged_rows.push((row, detectors, matches, redacted));
+        }
+
+        let mut by_detector: std::collections::BTreeMap<String, usize> =
+            std::collections::BTreeMap::new();
+        let mut findings = Vec::new();
+        let mut redacted_count = 0_usize;
+        let mut deleted_count = 0_usize;
+
+        let apply = |store: &mut MemoryStore| -> Result<()> {
+            for (event, detectors, matches, redacted) in flagged_events {
+                let action = match args.remediation {
+                    AuditRemediation::None => "reported",
+                    AuditRemediation::Redact => {
+                        store.set_event_payload(event.id, Some(&redacted))?;
+                        redacted_count += 1;
+                        "redacted"
+                    }
+                    AuditRemediation::Delete => {
+                        store.set_event_payload(event.id, None)?;
+                        deleted_count += 1;
+                        "payload_removed"
+                    }
+                };
+                for detector in &detectors {
+                    *by_detector.entry(detector.clone()).or_default() += 1;
+                }
+                findings.push(json!({
+                    "source": "event",
+                    "event_id": event.id,
+                    "memory_id": event.memory_id,
+                    "event_action": event.action,
+                    "timestamp": event.timestamp,
+                    "detectors": detectors,
+                    "matches": matches,
+                    "action": action,
+                }));
+            }
+
+            for (row, detectors, matches, redacted) in flagged_rows {
+                let action = match args.remediation {
+                    AuditRemediation::None => "reported",
+                    AuditRemediation::Redact => {
+                        if store.redact_memory_content(&row.id, &redacted, &detectors)? {
+                            redacted_count += 1;
+                            "redacted"
+                        } else {
+                            deleted_count += 1;
+                            "deleted_duplicate"
+                        }
+                    }
+                    AuditRemediation::Delete => {
+                        store.purge_memory(&row.id, &detectors)?;
+                        deleted_count += 1;
+                        "deleted"
+                    }
+                };
+                for detector in &detectors {
+                    *by_detector.entry(detector.clone()).or_default() += 1;
+                }
+                findings.push(json!({
+                    "source": "memory",
+                    "id": row.id,
+                    "scope": row.scope,
+                    "status": row.status,
+                    "category": row.category,
+                    "detectors": detectors,
+                    "matches": matches,
+                    "action": action,
+                }));
+            }
+            Ok(())
+        };
+        if args.remediation == AuditRemediation::None {
+            apply(&mut self.store)?;
+        } else {
+            self.store.remediate(apply)?;
+        }
+
+        let memories_flagged = findings
+            .iter()
+            .filter(|finding| finding["source"] == "memory")
+            .count();
+        let mut report = json!({
+            "schema_version": 1,
+            "generated_at": now_iso(),
+            "policy": policy.as_str(),
+            "include_inactive": args.include_inactive,
+            "remediation": args.remediation.as_str(),
+            "totals": {
+                "memories_scanned": rows.len(),
+                "events_scanned": events.len(),
+                "memories_flagged": memories_flagged,
+                "events_flagged": findings.len() - memories_flagged,
+                "redacted": redacted_count,
+                "deleted": deleted_count,
+            },
+            "by_detector": by_detector,
+            "findings": findings,
+        });
+
+        if let Some(path) = &report_path {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("create report directory {}", parent.display()))?;
+            }
+            fs::write(path, serde_json::to_string_pretty(&report)?)
+                .with_context(|| format!("write audit report {}", path.display()))?;
+        }
+
+        report["report_path"] = json!(report_path);
+        report["rendered"] = json!(format_audit_report(&report));
+        Ok(ok("audit", report))
+    }
+
+    #[must_use]
+    pub fn secrets_test(&self, text: &str) -> Value {
+        let scanner = self.store.secret_scanner();
+        let findings = scanner.scan(text);
+        let (outcome, stored) = match scanner.sanitize(text) {
+            Ok(sanitized) if sanitized.redacted.is_empty() => ("allowed", Some(sanitized.text)),
+            Ok(sanitized) => ("redacted", Some(sanitized.text)),
+            Err(_) if findings.iter().any(|f| !f.allowlisted) => ("blocked", None),
+            Err(_) => ("invalid", None),
+        };
+
+        ok(
+            "secrets_test",
+            json!({
+                "policy": scanner.policy.as_str(),
+                "outcome": outcome,
+                "findings": findings,
+                "stored_text": stored,
+                "config_errors": self.secret_errors,
+                "rendered": format_secret_findings(text, &findings, outcome, &self.secret_errors),
+            }),
+        )
+    }
+
+    pub fn show_injection_preview(&mut self, workspace_dir: &Path) -> Result<Value> {
+        let scope_info = self.detect_scope(workspace_dir);
+        let scopes = current_scopes(&scope_info);
+        let candidates = self.store.get_injection_candidates(
+            &scopes,
+            self.config.injection.max_items.saturating_mul(4).max(20),
+        )?;
+
+        let filtered = injectable_rows(candidates, &scopes);
+
+        let block = build_injection_block(
+            &filtered,
+            &scope_info,
+            self.config.injection.max_items,
+            self.config.injection.max_chars,
+        );
+
+        Ok(ok(
+            "show",
+            json!({
+                "scope": scope_info,
+                "candidate_count": filtered.len(),
+                "block": block,
+            }),
+        ))
+    }
+
+    pub fn auto_capture_mode(&mut self, mode: AutoMode) -> Result<Value> {
+        match mode {
+            AutoMode::Status => Ok(ok(
+                "auto",
+                json!({
+                    "enabled": self.config.auto_capture.enabled,
+                    "review": self.config.auto_capture.review,
+                    "scope": self.config.auto_capture.scope,
+                    "rule_errors": self.capture_rules.error_messages(),
+                    "rendered": format_auto_capture_status(&self.config, &self.capture_rules),
+                }),
+            )),
+            AutoMode::On => {
+                self.set_global_config(&[
+                    ("autoCapture.enabled", json!(true)),
+                    ("autoCapture.review", json!(false)),
+                ])?;
+                Ok(ok("auto", json!({"enabled": true, "review": false})))
+            }
+            AutoMode::Review => {
+                self.set_global_config(&[
+                    ("autoCapture.enabled", json!(true)),
+                    ("autoCapture.review", json!(true)),
+                ])?;
+                Ok(ok("auto", json!({"enabled": true, "review": true})))
+            }
+            AutoMode::Off => {
+                self.set_global_config(&[("autoCapture.enabled", json!(false))])?;
+                Ok(ok("auto", json!({"enabled": false})))
+            }
+        }
+    }
+
+    pub fn refresh(&mut self) -> Result<Value> {
+        self.store.refresh(&self.config)?;
+        Ok(ok("refresh", json!({"refreshed": true})))
+    }
+
+    pub fn export_memories(
+        &mut self,
+        workspace_dir: &Path,
+        format: ExportFormat,
+        include_all_scopes: bool,
+        output_path_raw: String,
+    ) -> Result<Value> {
+        let scope_info = self.detect_scope(workspace_dir);
+        let scopes = current_scopes(&scope_info);
+
+        let output_path =
+            match resolve_export_path_within_workspace(workspace_dir, format, &output_path_raw) {
+                Ok(path) => path,
+                Err(message) => return Ok(err("export", message)),
+            };
+
+        let entries = if include_all_scopes {
+            self.store.export_active_memories(None)?
+        } else {
+            self.store.export_active_memories(Some(&scopes))?
+        };
+
+        let stats = if include_all_scopes {
+            let all_scopes = entries
+                .iter()
+                .map(|row| row.scope.clone())
+                .collect::<HashSet<_>>();
+            self.store
+                .get_stats(&all_scopes.into_iter().collect::<Vec<String>>())?
+        } else {
+            self.store.get_stats(&scopes)?
+        };
+
+        let payload = match format {
+            ExportFormat::Json => serde_json::to_string_pretty(&json!({
+                "schema_version": 1,
+                "generated_at": now_iso(),
+                "workspace_root": workspace_dir.to_string_lossy(),
+                "project_scope": scope_info.scope,
+                "include_all_scopes": include_all_scopes,
+                "stats_snapshot": stats,
+                "entries": entries,
+            }))?,
+            ExportFormat::Markdown => format_export_markdown(&entries, &self.store.scope_labels()?),
+        };
+
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("create export directory {}", parent.display()))?;
+        }
+        fs::write(&output_path, payload)
+            .with_context(|| format!("write export {}", output_path.display()))?;
+
+        Ok(ok(
+            "export",
+            json!({
+                "count": entries.len(),
+                "format": format.as_str(),
+                "path": output_path,
+            }),
+        ))
+    }
+
+    fn deterministic_compaction_block(&self, scope_info: &ScopeInfo, rows: &[MemoryRow]) -> String {
+        let header = [
+            "## Extra Memory (Codex)",
+            "Compacted memory summary (deterministic fallback).",
+        ]
+        .join("\n");
+
+        let mut lines = vec![header];
+        let mut chars = lines[0].chars().count();
+        let mut selected = 0_usize;
 
         for row in rows {
             if selected >= self.config.injection.max_items {
@@ -585,11 +1638,32 @@ impl MemoryService {
         }
     }
 
-    fn compact_block_for_agents(
-        &mut self,
+    fn deterministic_compaction_result(
+        &self,
         scope_info: &ScopeInfo,
-        rows: &[crate::types::MemoryRow],
+        rows: &[MemoryRow],
+        mode: CompactionMode,
+        model: Option<String>,
+        reason: Option<String>,
     ) -> CompactionResult {
+        let block = self.deterministic_compaction_block(scope_info, rows);
+        CompactionResult {
+            mode,
+            block: block.clone(),
+            input_count: rows.len(),
+            output_count: block.lines().filter(|line| line.starts_with("- ")).count(),
+            input_chars: rows.iter().map(|row| row.content.chars().count()).sum(),
+            output_chars: block.chars().count(),
+            model,
+            reason,
+        }
+    }
+
+    fn uncompacted_result(
+        &self,
+        scope_info: &ScopeInfo,
+        rows: &[MemoryRow],
+    ) -> Option<CompactionResult> {
         let raw_block = build_injection_block(
             rows,
             scope_info,
@@ -602,138 +1676,162 @@ impl MemoryService {
             .sum::<usize>();
 
         let over_budget = rows.len() > self.config.injection.max_items
-            || rows
-                .iter()
-                .map(|row| row.content.chars().count())
-                .sum::<usize>()
-                > self.config.injection.max_chars;
-
-        if !raw_block.is_empty() && !over_budget {
-            return CompactionResult {
-                mode: CompactionMode::None,
-                block: raw_block.clone(),
-                input_count: rows.len(),
-                output_count: rows.len().min(self.config.injection.max_items),
-                input_chars,
-                output_chars: raw_block.chars().count(),
-                model: None,
-                reason: None,
-            };
+            || input_chars > self.config.injection.max_chars;
+
+        if raw_block.is_empty() || over_budget {
+            return None;
         }
 
-        if self.config.llm_compaction.enabled {
-            let llm_request = LlmSummaryRequest {
-                model: self.config.llm_compaction.model.clone(),
-                timeout_ms: self.config.llm_compaction.timeout_ms,
-                max_output_chars: self
-                    .config
-                    .llm_compaction
-                    .max_output_chars
-                    .min(self.config.injection.max_chars.saturating_sub(100)),
-            };
+        Some(CompactionResult {
+            mode: CompactionMode::None,
+            block: raw_block.clone(),
+            input_count: rows.len(),
+            output_count: rows.len().min(self.config.injection.max_items),
+            input_chars,
+            output_chars: raw_block.chars().count(),
+            model: None,
+            reason: None,
+        })
+    }
 
-            match summarize_memories_with_llm(rows, &llm_request) {
-                Ok(Some(summary)) if !summary.trim().is_empty() => {
-                    let mut block_lines = vec![
-                        "## Extra Memory (Codex)".to_string(),
-                        format!("Compacted memory summary via {}.", llm_request.model),
-                    ];
-
-                    let mut used = block_lines.join("\n").chars().count();
-                    let mut output_count = 0_usize;
-                    for line in summary
-                        .lines()
-                        .map(str::trim)
-                        .filter(|line| !line.is_empty())
-                    {
-                        if output_count >= self.config.injection.max_items {
-                            break;
-                        }
-                        let normalized = if line.starts_with("- ") {
-                            line.to_string()
-                        } else {
-                            format!("- {line}")
-                        };
-                        let needed = normalized.chars().count() + 1;
-                        if used + needed > self.config.injection.max_chars {
-                            continue;
-                        }
-                        block_lines.push(normalized);
-                        used += needed;
-                        output_count += 1;
-                    }
+    fn llm_summary_request(&self) -> LlmSummaryRequest {
+        LlmSummaryRequest {
+            model: self.config.llm_compaction.model.clone(),
+            timeout_ms: self.config.llm_compaction.timeout_ms,
+            max_output_chars: self
+                .config
+                .llm_compaction
+                .max_output_chars
+                .min(self.config.injection.max_chars.saturating_sub(100)),
+            prompt_version: self.config.llm_compaction.prompt.version,
+            system_prompt: self.config.llm_compaction.prompt.system.clone(),
+            user_prompt_template: self.config.llm_compaction.prompt.user_template.clone(),
+        }
+    }
 
-                    let block = block_lines.join("\n");
-                    if output_count > 0 {
-                        return CompactionResult {
-                            mode: CompactionMode::Llm,
-                            block: block.clone(),
-                            input_count: rows.len(),
-                            output_count,
-                            input_chars,
-                            output_chars: block.chars().count(),
-                            model: Some(llm_request.model),
-                            reason: None,
-                        };
-                    }
-                }
-                Ok(None | Some(_)) => {}
-                Err(error) => {
-                    let block = self.deterministic_compaction_block(scope_info, rows);
-                    return CompactionResult {
-                        mode: CompactionMode::LlmFallback,
-                        block: block.clone(),
-                        input_count: rows.len(),
-                        output_count: block.lines().filter(|line| line.starts_with("- ")).count(),
-                        input_chars,
-                        output_chars: block.chars().count(),
-                        model: Some(self.config.llm_compaction.model.clone()),
-                        reason: Some(error.to_string()),
-                    };
-                }
+    fn llm_compaction_result(
+        &self,
+        scope_info: &ScopeInfo,
+        rows: &[MemoryRow],
+        request: &LlmSummaryRequest,
+        outcome: Result<Option<String>>,
+    ) -> Option<CompactionResult> {
+        let summary = match outcome {
+            Ok(Some(summary)) if !summary.trim().is_empty() => summary,
+            Ok(None | Some(_)) => return None,
+            Err(error) => {
+                return Some(self.deterministic_compaction_result(
+                    scope_info,
+                    rows,
+                    CompactionMode::LlmFallback,
+                    Some(request.model.clone()),
+                    Some(error.to_string()),
+                ));
+            }
+        };
+
+        let screened = screen_summary(&summary, self.store.secret_scanner());
+        let mut block_lines = vec![
+            "## Extra Memory (Codex)".to_string(),
+            format!("Compacted memory summary via {}.", request.model),
+        ];
+
+        let mut used = block_lines.join("\n").chars().count();
+        let mut output_count = 0_usize;
+        for line in &screened.lines {
+            if output_count >= self.config.injection.max_items {
+                break;
+            }
+            let normalized = if line.starts_with("- ") {
+                line.clone()
+            } else {
+                format!("- {line}")
+            };
+            let needed = normalized.chars().count() + 1;
+            if used + needed > self.config.injection.max_chars {
+                continue;
             }
+            block_lines.push(normalized);
+            used += needed;
+            output_count += 1;
         }
 
-        let block = self.deterministic_compaction_block(scope_info, rows);
-        CompactionResult {
-            mode: CompactionMode::Deterministic,
+        let screening_reason = (!screened.rejected.is_empty()).then(|| {
+            format!(
+                "dropped {} suspicious summary line(s): {}",
+                screened.rejected.len(),
+                screened.rejected.join("; ")
+            )
+        });
+
+        if output_count == 0 {
+            return screening_reason.map(|reason| {
+                self.deterministic_compaction_result(
+                    scope_info,
+                    rows,
+                    CompactionMode::LlmFallback,
+                    Some(request.model.clone()),
+                    Some(reason),
+                )
+            });
+        }
+
+        let block = block_lines.join("\n");
+        Some(CompactionResult {
+            mode: CompactionMode::Llm,
             block: block.clone(),
             input_count: rows.len(),
-            output_count: block.lines().filter(|line| line.starts_with("- ")).count(),
-            input_chars,
+            output_count,
+            input_chars: rows.iter().map(|row| row.content.chars().count()).sum(),
             output_chars: block.chars().count(),
-            model: None,
-            reason: None,
+            model: Some(request.model.clone()),
+            reason: screening_reason,
+        })
+    }
+
+    fn compact_block_for_agents(
+        &mut self,
+        scope_info: &ScopeInfo,
+        rows: &[MemoryRow],
+    ) -> CompactionResult {
+        if let Some(result) = self.uncompacted_result(scope_info, rows) {
+            return result;
+        }
+
+        if self.config.llm_compaction.enabled {
+            let request = self.llm_summary_request();
+            let outcome = summarize_memories_with_llm(rows, &request);
+            if let Some(result) = self.llm_compaction_result(scope_info, rows, &request, outcome) {
+                return result;
+            }
         }
+
+        self.deterministic_compaction_result(
+            scope_info,
+            rows,
+            CompactionMode::Deterministic,
+            None,
+            None,
+        )
     }
 
-    pub fn sync_agents(&mut self, workspace_dir: &Path) -> Result<Value> {
-        let scope_info = Self::detect_scope(workspace_dir);
+    fn select_sync_rows(&self, scope_info: &ScopeInfo) -> Result<Vec<MemoryRow>> {
+        let scopes = current_scopes(scope_info);
         let candidates = self.store.get_injection_candidates(
-            &scope_info.scope,
+            &scopes,
             self.config.injection.max_items.saturating_mul(4).max(20),
         )?;
 
-        let selected = candidates
-            .into_iter()
-            .filter(|row| row.scope == scope_info.scope || (row.scope == "global" && row.pinned))
-            .collect::<Vec<_>>();
-
-        let compaction = if selected.is_empty() {
-            CompactionResult {
-                mode: CompactionMode::None,
-                block: String::new(),
-                input_count: 0,
-                output_count: 0,
-                input_chars: 0,
-                output_chars: 0,
-                model: None,
-                reason: None,
-            }
-        } else {
-            self.compact_block_for_agents(&scope_info, &selected)
-        };
+        Ok(injectable_rows(candidates, &scopes))
+    }
 
+    fn write_synced_block(
+        &mut self,
+        workspace_dir: &Path,
+        scope_info: &ScopeInfo,
+        selected: usize,
+        compaction: CompactionResult,
+    ) -> Result<SyncAgentsResult> {
         self.store.record_compaction(
             &scope_info.scope,
             compaction.mode.clone(),
@@ -743,8 +1841,9 @@ impl MemoryService {
             compaction.model.as_deref(),
             compaction.reason.as_deref(),
             json!({
-                "selected": selected.len(),
+                "selected": selected,
                 "workspace": workspace_dir,
+                "prompt_version": self.config.llm_compaction.prompt.version,
             }),
         );
 
@@ -756,15 +1855,238 @@ impl MemoryService {
 
         let (changed, agents_path) = sync_agents_file(workspace_dir, block)?;
 
-        let result = SyncAgentsResult {
+        Ok(SyncAgentsResult {
             changed,
             agents_path: agents_path.to_string_lossy().to_string(),
             applied_on_next_session: true,
-            selected_memories: selected.len(),
+            selected_memories: selected,
             compaction,
+        })
+    }
+
+    pub fn sync_agents(&mut self, workspace_dir: &Path) -> Result<Value> {
+        let scope_info = self.detect_scope(workspace_dir);
+        let selected = self.select_sync_rows(&scope_info)?;
+
+        let compaction = if selected.is_empty() {
+            empty_compaction()
+        } else {
+            self.compact_block_for_agents(&scope_info, &selected)
+        };
+
+        let result =
+            self.write_synced_block(workspace_dir, &scope_info, selected.len(), compaction)?;
+
+        Ok(ok("sync", serde_to_value(result)?))
+    }
+
+    pub fn begin_sync_agents(
+        &mut self,
+        workspace_dir: &Path,
+    ) -> Result<(Value, Option<PendingLlmCompaction>)> {
+        let scope_info = self.detect_scope(workspace_dir);
+        let selected = self.select_sync_rows(&scope_info)?;
+
+        let (compaction, request) = if selected.is_empty() {
+            (empty_compaction(), None)
+        } else if let Some(result) = self.uncompacted_result(&scope_info, &selected) {
+            (result, None)
+        } else {
+            (
+                self.deterministic_compaction_result(
+                    &scope_info,
+                    &selected,
+                    CompactionMode::Deterministic,
+                    None,
+                    None,
+                ),
+                self.config
+                    .llm_compaction
+                    .enabled
+                    .then(|| self.llm_summary_request()),
+            )
+        };
+
+        let result =
+            self.write_synced_block(workspace_dir, &scope_info, selected.len(), compaction)?;
+
+        self.next_sync_job_id += 1;
+        let job_id = self.next_sync_job_id;
+        let job = SyncJobStatus {
+            job_id,
+            state: if request.is_some() {
+                SyncJobState::Pending
+            } else {
+                SyncJobState::Completed
+            },
+            agents_path: result.agents_path.clone(),
+            started_at: now_utc(),
+            finished_at: request.is_none().then(now_utc),
+            mode: result.compaction.mode.clone(),
+            model: request.as_ref().map(|r| r.model.clone()),
+            reason: None,
+        };
+        self.sync_jobs
+            .insert(PathBuf::from(&result.agents_path), job.clone());
+
+        let pending = request.map(|request| PendingLlmCompaction {
+            job_id,
+            workspace_dir: workspace_dir.to_path_buf(),
+            scope_info,
+            rows_hash: sync_rows_hash(&selected),
+            rows: selected,
+            request,
+        });
+
+        let mut data = serde_to_value(result)?;
+        data["job"] = serde_to_value(job)?;
+        Ok((ok("sync", data), pending))
+    }
+
+    pub fn finish_sync_agents(
+        &mut self,
+        pending: PendingLlmCompaction,
+        outcome: Result<Option<String>>,
+    ) -> Result<Value> {
+        let agents_path = pending.workspace_dir.join("AGENTS.md");
+        let is_current = self
+            .sync_jobs
+            .get(&agents_path)
+            .is_some_and(|job| job.job_id == pending.job_id);
+        if !is_current {
+            return Ok(ok(
+                "sync_finish",
+                json!({"job_id": pending.job_id, "superseded": true}),
+            ));
+        }
+
+        let current = self.select_sync_rows(&pending.scope_info)?;
+        let rows_changed = sync_rows_hash(&current) != pending.rows_hash;
+        let compaction = if !rows_changed {
+            self.llm_compaction_result(
+                &pending.scope_info,
+                &pending.rows,
+                &pending.request,
+                outcome,
+            )
+        } else if current.is_empty() {
+            Some(empty_compaction())
+        } else {
+            // The summary was built from rows that have since changed, so the
+            // block is rebuilt deterministically from the store instead.
+            Some(self.deterministic_compaction_result(
+                &pending.scope_info,
+                &current,
+                CompactionMode::LlmFallback,
+                Some(pending.request.model.clone()),
+                Some("memories changed while the summary was running".to_string()),
+            ))
+        };
+
+        let (state, mode, reason, written) = match compaction {
+            Some(compaction) if rows_changed || compaction.mode == CompactionMode::Llm => {
+                let mode = compaction.mode.clone();
+                let reason = compaction.reason.clone();
+                match self.write_synced_block(
+                    &pending.workspace_dir,
+                    &pending.scope_info,
+                    current.len(),
+                    compaction,
+                ) {
+                    Ok(result) => (SyncJobState::Completed, mode, reason, Some(result)),
+                    Err(error) => (
+                        SyncJobState::Failed,
+                        CompactionMode::Deterministic,
+                        Some(error.to_string()),
+                        None,
+                    ),
+                }
+            }
+            Some(fallback) => {
+                self.store.record_compaction(
+                    &pending.scope_info.scope,
+                    fallback.mode.clone(),
+                    fallback.input_chars,
+                    fallback.output_chars,
+                    fallback.input_count,
+                    fallback.model.as_deref(),
+                    fallback.reason.as_deref(),
+                    json!({
+                        "selected": pending.rows.len(),
+                        "workspace": pending.workspace_dir,
+                        "prompt_version": pending.request.prompt_version,
+                    }),
+                );
+                (
+                    SyncJobState::Completed,
+                    fallback.mode,
+                    fallback.reason,
+                    None,
+                )
+            }
+            None => (
+                SyncJobState::Completed,
+                CompactionMode::Deterministic,
+                None,
+                None,
+            ),
         };
 
-        Ok(ok("sync", serde_json::to_value(result)?))
+        let job = self
+            .sync_jobs
+            .get_mut(&agents_path)
+            .context("sync job disappeared")?;
+        job.state = state;
+        job.mode = mode;
+        job.reason = reason;
+        job.finished_at = Some(now_utc());
+        let job = job.clone();
+
+        Ok(ok(
+            "sync_finish",
+            json!({
+                "job": job,
+                "result": written,
+            }),
+        ))
+    }
+
+    pub fn sync_status(&self, workspace_dir: &Path) -> Result<Value> {
+        let agents_path = workspace_dir.join("AGENTS.md");
+        let job = self.sync_jobs.get(&agents_path);
+        Ok(ok(
+            "sync_status",
+            json!({
+                "job": job,
+                "rendered": format_sync_status(job),
+            }),
+        ))
+    }
+
+    #[must_use]
+    pub fn llm_capture_request(
+        &mut self,
+        workspace_dir: &Path,
+        event_payload: &Value,
+    ) -> Option<LlmCaptureRequest> {
+        self.detect_scope(workspace_dir);
+        let llm = &self.config.auto_capture.llm;
+        if !self.config.auto_capture.enabled || !llm.enabled {
+            return None;
+        }
+
+        let messages = collect_agent_messages(&get_agent_end_messages(event_payload));
+        let messages = budget_llm_capture_messages(&messages, llm.max_input_tokens);
+        if messages.is_empty() {
+            return None;
+        }
+
+        Some(LlmCaptureRequest {
+            model: llm.model.clone(),
+            timeout_ms: llm.timeout_ms,
+            max_facts: self.config.auto_capture.max_per_turn,
+            messages,
+        })
     }
 
     pub fn capture_candidates(
@@ -772,42 +2094,127 @@ impl MemoryService {
         workspace_dir: &Path,
         event_payload: Value,
         persist: bool,
+    ) -> Result<Value> {
+        let llm_outcome = self
+            .llm_capture_request(workspace_dir, &event_payload)
+            .map(|request| classify_capture_with_llm(&request));
+        self.capture_candidates_with_llm(workspace_dir, event_payload, persist, llm_outcome)
+    }
+
+    pub fn capture_candidates_with_llm(
+        &mut self,
+        workspace_dir: &Path,
+        event_payload: Value,
+        persist: bool,
+        llm_outcome: Option<Result<Option<Vec<LlmCaptureProposal>>>>,
     ) -> Result<Value> {
         let messages = get_agent_end_messages(&event_payload);
-        let candidates = extract_auto_capture_candidates(
+        let processed_hashes = self.store.load_processed_hashes()?;
+        let regex_candidates = extract_auto_capture_candidates(
             &messages,
             &self.config.auto_capture,
-            &self.processed_hashes,
+            &self.capture_rules.compiled,
+            self.store.secret_scanner(),
+            &processed_hashes,
         );
 
-        let scope_info = Self::detect_scope(workspace_dir);
+        let (candidates, llm_status) = match llm_outcome {
+            None => (
+                regex_candidates,
+                json!({"enabled": self.config.auto_capture.llm.enabled, "used": false}),
+            ),
+            Some(Ok(Some(proposals))) => {
+                let llm_candidates = candidates_from_llm_proposals(
+                    &proposals,
+                    &self.config.auto_capture,
+                    self.store.secret_scanner(),
+                    &processed_hashes,
+                );
+                (
+                    merge_auto_capture_candidates(
+                        llm_candidates,
+                        regex_candidates,
+                        self.config.auto_capture.max_per_turn,
+                    ),
+                    json!({"enabled": true, "used": true, "proposals": proposals.len()}),
+                )
+            }
+            Some(Ok(None)) => (
+                regex_candidates,
+                json!({"enabled": true, "used": false, "reason": "OPENAI_API_KEY is not set"}),
+            ),
+            Some(Err(error)) => (
+                regex_candidates,
+                json!({"enabled": true, "used": false, "reason": error.to_string()}),
+            ),
+        };
+
+        let scope_info = self.detect_scope(workspace_dir);
 
         let mut added = 0_usize;
+        let mut queued = 0_usize;
         let mut deduped = 0_usize;
         let mut blocked = 0_usize;
+        let review = self.config.auto_capture.review;
+
+        let mut retracted = 0_usize;
+        let mut superseded = 0_usize;
+        let mut corrections = Vec::new();
+        let apply = persist && self.config.auto_capture.enabled;
+
+        for candidate in &candidates {
+            if candidate.action != CaptureAction::Add {
+                let report = self.apply_correction(&scope_info, candidate, apply, review)?;
+                match report.get("status").and_then(Value::as_str) {
+                    Some("retracted") => retracted += 1,
+                    Some("superseded") => superseded += 1,
+                    Some("added") => added += 1,
+                    Some("queued") => queued += 1,
+                    Some("deduped") => deduped += 1,
+                    Some("blocked") => blocked += 1,
+                    _ => {}
+                }
+                corrections.push(report);
+                continue;
+            }
+            if !apply {
+                continue;
+            }
+
+            let scope = scope_from_target(
+                &scope_info,
+                candidate.scope.unwrap_or(self.config.auto_capture.scope),
+            );
+            let input = AddMemoryInput {
+                scope,
+                category: candidate.category,
+                content: candidate.text.clone(),
+                source: candidate
+                    .tag
+                    .as_ref()
+                    .map_or_else(|| "auto".to_string(), |tag| format!("auto:{tag}")),
+            };
+            let result = if review {
+                self.store.add_pending_memory(input)?
+            } else {
+                self.store.add_memory(input)?
+            };
 
-        if persist && self.config.auto_capture.enabled {
-            for candidate in &candidates {
-                let scope = scope_from_target(&scope_info, self.config.auto_capture.scope);
-                let result = self.store.add_memory(AddMemoryInput {
-                    scope,
-                    category: candidate.category,
-                    content: candidate.text.clone(),
-                    source: "auto".to_string(),
-                })?;
-
-                match result {
-                    AddMemoryResult::Added { .. } => {
+            match result {
+                AddMemoryResult::Added { .. } => {
+                    if review {
+                        queued += 1;
+                    } else {
                         added += 1;
-                        self.track_processed_hash(candidate.hash.clone());
-                    }
-                    AddMemoryResult::Deduped { .. } => {
-                        deduped += 1;
-                        self.track_processed_hash(candidate.hash.clone());
-                    }
-                    AddMemoryResult::Blocked { .. } => {
-                        blocked += 1;
                     }
+                    self.track_processed_hash(&candidate.hash)?;
+                }
+                AddMemoryResult::Deduped { .. } => {
+                    deduped += 1;
+                    self.track_processed_hash(&candidate.hash)?;
+                }
+                AddMemoryResult::Blocked { .. } => {
+                    blocked += 1;
                 }
             }
         }
@@ -817,14 +2224,476 @@ impl MemoryService {
             json!({
                 "enabled": self.config.auto_capture.enabled,
                 "persisted": persist,
+                "review": review,
                 "candidates": candidates,
                 "added": added,
+                "queued": queued,
                 "deduped": deduped,
                 "blocked": blocked,
+                "retracted": retracted,
+                "superseded": superseded,
+                "corrections": corrections,
+                "llm": llm_status,
+            }),
+        ))
+    }
+
+    fn apply_correction(
+        &mut self,
+        scope_info: &ScopeInfo,
+        candidate: &AutoCaptureCandidate,
+        apply: bool,
+        review: bool,
+    ) -> Result<Value> {
+        let target = candidate.target.as_deref().unwrap_or(&candidate.text);
+        let query = correction_query(target);
+        let (hits, _) = if query.is_empty() {
+            (Vec::new(), false)
+        } else {
+            self.store
+                .search_memories(&current_scopes(scope_info), &query, 2, 0, false)?
+        };
+        let matched = (hits.len() == 1).then(|| hits[0].clone());
+        let similarity = matched
+            .as_ref()
+            .map(|row| correction_similarity(target, &row.content));
+        let confident = similarity.is_some_and(|score| score >= RETRACT_MIN_SIMILARITY);
+        let ambiguous = if hits.len() > 1 {
+            hits.iter().map(|row| row.id.clone()).collect()
+        } else {
+            Vec::new()
+        };
+
+        let status = if !apply {
+            "preview"
+        } else if candidate.action == CaptureAction::Retract {
+            match &matched {
+                None if ambiguous.is_empty() => "no_match",
+                None => "ambiguous",
+                Some(row) if review || !confident => {
+                    let input = AddMemoryInput {
+                        scope: row.scope.clone(),
+                        category: row.category,
+                        content: format!("Forget: {}", row.content),
+                        source: "auto".to_string(),
+                    };
+                    match self
+                        .store
+                        .queue_correction(input, CaptureAction::Retract, &row.id)?
+                    {
+                        AddMemoryResult::Added { .. } => "queued",
+                        AddMemoryResult::Deduped { .. } => "deduped",
+                        AddMemoryResult::Blocked { .. } => "blocked",
+                    }
+                }
+                Some(row) => {
+                    if self.store.retract_memory(&row.id, &candidate.text)? {
+                        "retracted"
+                    } else {
+                        "no_match"
+                    }
+                }
+            }
+        } else {
+            let scope = matched.as_ref().map_or_else(
+                || {
+                    scope_from_target(
+                        scope_info,
+                        candidate.scope.unwrap_or(self.config.auto_capture.scope),
+                    )
+                },
+                |row| row.scope.clone(),
+            );
+            let input = AddMemoryInput {
+                scope,
+                category: matched
+                    .as_ref()
+                    .map_or(candidate.category, |row| row.category),
+                content: candidate.text.clone(),
+                source: "auto".to_string(),
+            };
+            let queue = matched.is_some() && (review || !confident);
+            let result = match &matched {
+                Some(row) if queue => {
+                    self.store
+                        .queue_correction(input, CaptureAction::Replace, &row.id)?
+                }
+                None if review => self.store.add_pending_memory(input)?,
+                _ => self.store.add_memory(input)?,
+            };
+            let (id, fallback) = match result {
+                AddMemoryResult::Added { id, .. } => {
+                    (Some(id), if review || queue { "queued" } else { "added" })
+                }
+                AddMemoryResult::Deduped { id, .. } => (Some(id), "deduped"),
+                AddMemoryResult::Blocked { .. } => (None, "blocked"),
+            };
+            match (&matched, id) {
+                (Some(row), Some(id)) if !queue && self.store.supersede_memory(&row.id, &id)? => {
+                    "superseded"
+                }
+                _ => fallback,
+            }
+        };
+
+        if !matches!(status, "preview" | "blocked") {
+            self.track_processed_hash(&candidate.hash)?;
+        }
+
+        Ok(json!({
+            "hash": candidate.hash,
+            "action": candidate.action,
+            "text": candidate.text,
+            "target": target,
+            "matched": matched.map(|row| json!({"id": row.id, "content": row.content})),
+            "ambiguous": ambiguous,
+            "similarity": similarity,
+            "status": status,
+        }))
+    }
+
+    pub fn backfill(&mut self, workspace_dir: &Path, args: BackfillArgs) -> Result<Value> {
+        let sessions_dir = args.sessions_dir.as_deref().map_or_else(
+            || resolve_codex_home().join("sessions"),
+            |raw| {
+                let path = PathBuf::from(raw);
+                if path.is_absolute() {
+                    path
+                } else {
+                    workspace_dir.join(path)
+                }
+            },
+        );
+        if !sessions_dir.is_dir() {
+            return Ok(err(
+                "backfill",
+                format!("Sessions directory not found: {}", sessions_dir.display()),
+            ));
+        }
+        if args.apply && !self.config.auto_capture.enabled {
+            return Ok(err(
+                "backfill",
+                "Auto-capture is off. Run /memory auto on or /memory auto review before --apply.",
+            ));
+        }
+
+        let counters = [
+            "added",
+            "queued",
+            "deduped",
+            "blocked",
+            "retracted",
+            "superseded",
+        ];
+        let mut totals: HashMap<&str, u64> = counters.iter().map(|key| (*key, 0)).collect();
+        let mut total_candidates = 0_usize;
+        let mut already_processed = 0_usize;
+        let mut sessions = Vec::new();
+        let mut skipped = Vec::new();
+
+        for file in find_session_files(&sessions_dir, args.since)? {
+            let path_key = file.to_string_lossy().to_string();
+            let size = fs::metadata(&file).map_or(0, |meta| meta.len());
+            if self.store.backfill_file_size(&path_key)? == Some(size) {
+                already_processed += 1;
+                continue;
+            }
+
+            let transcript = match parse_session_file(&file) {
+                Ok(transcript) => transcript,
+                Err(error) => {
+                    skipped.push(json!({"file": path_key, "reason": error.to_string()}));
+                    continue;
+                }
+            };
+            let Some(cwd) = transcript.cwd.clone() else {
+                skipped.push(json!({"file": path_key, "reason": "no cwd recorded"}));
+                continue;
+            };
+
+            let cwd_path = PathBuf::from(&cwd);
+            let scope_info = self.detect_scope(&cwd_path);
+            let mut candidates = Vec::new();
+            let mut session_counts: HashMap<&str, u64> =
+                counters.iter().map(|key| (*key, 0)).collect();
+            for turn in &transcript.turns {
+                let result = self.capture_candidates_with_llm(
+                    &cwd_path,
+                    json!({"messages": turn}),
+                    args.apply,
+                    None,
+                )?;
+                let data = result.get("data").cloned().unwrap_or(Value::Null);
+                if let Some(found) = data.get("candidates").and_then(Value::as_array) {
+                    candidates.extend(found.iter().cloned());
+                }
+                for key in counters {
+                    let count = data.get(key).and_then(Value::as_u64).unwrap_or(0);
+                    *session_counts.entry(key).or_default() += count;
+                    *totals.entry(key).or_default() += count;
+                }
+            }
+
+            if args.apply {
+                self.store.mark_backfill_file(&path_key, size)?;
+            }
+            total_candidates += candidates.len();
+            sessions.push(json!({
+                "file": path_key,
+                "session_id": transcript.session_id,
+                "cwd": cwd,
+                "scope": scope_info.scope,
+                "turns": transcript.turns.len(),
+                "candidates": candidates,
+                "counts": session_counts,
+            }));
+        }
+
+        let mut lines = vec![format!(
+            "Backfill {} {} session file(s) from {}.",
+            if args.apply { "processed" } else { "previewed" },
+            sessions.len(),
+            sessions_dir.display()
+        )];
+        lines.push(format!("- Candidates found: {total_candidates}"));
+        if args.apply {
+            lines.push(format!(
+                "- Added: {}, queued: {}, deduped: {}, blocked: {}, retracted: {}, superseded: {}",
+                totals["added"],
+                totals["queued"],
+                totals["deduped"],
+                totals["blocked"],
+                totals["retracted"],
+                totals["superseded"]
+            ));
+        } else {
+            lines.push("- Nothing was stored. Re-run with --apply to persist.".to_string());
+        }
+        lines.push(format!(
+            "- Skipped as already processed: {already_processed}"
+        ));
+        if !skipped.is_empty() {
+            lines.push(format!("- Skipped as unreadable: {}", skipped.len()));
+        }
+
+        Ok(ok(
+            "backfill",
+            json!({
+                "sessions_dir": sessions_dir.display().to_string(),
+                "applied": args.apply,
+                "since": args.since.map(|date| date.to_string()),
+                "sessions": sessions,
+                "totals": totals,
+                "candidates": total_candidates,
+                "already_processed": already_processed,
+                "skipped": skipped,
+                "rendered": lines.join("\n"),
+            }),
+        ))
+    }
+
+    fn resolve_review_targets(
+        &self,
+        scopes: &[String],
+        targets: &ReviewTargets,
+    ) -> Result<(Vec<String>, Vec<ReviewOutcome>)> {
+        match targets {
+            ReviewTargets::All => {
+                let (rows, _) = self.store.list_pending_memories(scopes, 10_000, 0)?;
+                Ok((rows.into_iter().map(|row| row.id).collect(), Vec::new()))
+            }
+            ReviewTargets::Ids(raw_ids) => {
+                let mut ids = Vec::new();
+                let mut failures = Vec::new();
+                for raw in raw_ids {
+                    match self
+                        .store
+                        .resolve_id_with_status(raw, Some(scopes), "pending")?
+                    {
+                        ResolveIdResult::Ok { id } => ids.push(id),
+                        ResolveIdResult::Missing => {
+                            failures.push(ReviewOutcome::Missing { id: raw.clone() });
+                        }
+                        ResolveIdResult::Ambiguous { candidates } => {
+                            failures.push(ReviewOutcome::Blocked {
+                                id: raw.clone(),
+                                reason: format!(
+                                    "Multiple pending memories match: {}",
+                                    candidates.join(", ")
+                                ),
+                            });
+                        }
+                    }
+                }
+                Ok((ids, failures))
+            }
+        }
+    }
+
+    pub fn review_memories(&mut self, workspace_dir: &Path, action: ReviewAction) -> Result<Value> {
+        let scope_info = self.detect_scope(workspace_dir);
+        let scopes = current_scopes(&scope_info);
+
+        let (action_name, outcomes) = match action {
+            ReviewAction::List { limit, cursor } => {
+                let limit = limit.unwrap_or(self.config.list_limit).min(200);
+                let offset = cursor_decode(cursor.as_deref())?;
+                let (items, has_more) = self.store.list_pending_memories(&scopes, limit, offset)?;
+                let next_cursor = has_more.then(|| cursor_encode(offset + limit));
+                let rendered = if items.is_empty() {
+                    "No memories pending review.".to_string()
+                } else {
+                    render_rows(&items, &scope_info, &self.store.scope_labels()?)
+                };
+                let page = PagedResult {
+                    items,
+                    next_cursor,
+                    limit,
+                    offset,
+                };
+                return Ok(ok(
+                    "review",
+                    json!({
+                        "action": "list",
+                        "page": page,
+                        "rendered": rendered,
+                    }),
+                ));
+            }
+            ReviewAction::Approve(targets) => {
+                let (ids, mut outcomes) = self.resolve_review_targets(&scopes, &targets)?;
+                for id in ids {
+                    outcomes.push(self.store.approve_pending(&id)?);
+                }
+                ("approve", outcomes)
+            }
+            ReviewAction::Reject(targets) => {
+                let (ids, mut outcomes) = self.resolve_review_targets(&scopes, &targets)?;
+                for id in ids {
+                    outcomes.push(self.store.reject_pending(&id)?);
+                }
+                ("reject", outcomes)
+            }
+            ReviewAction::Recategorize { category, targets } => {
+                let (ids, mut outcomes) = self.resolve_review_targets(&scopes, &targets)?;
+                for id in ids {
+                    outcomes.push(self.store.recategorize_pending(&id, category)?);
+                }
+                ("category", outcomes)
+            }
+            ReviewAction::Edit { id_or_prefix, text } => {
+                let (ids, mut outcomes) =
+                    self.resolve_review_targets(&scopes, &ReviewTargets::Ids(vec![id_or_prefix]))?;
+                for id in ids {
+                    outcomes.push(self.store.edit_pending(&id, &text)?);
+                }
+                ("edit", outcomes)
+            }
+        };
+
+        if outcomes.is_empty() {
+            return Ok(err("review", "No memories pending review."));
+        }
+
+        let rendered = outcomes
+            .iter()
+            .map(|outcome| match outcome {
+                ReviewOutcome::Approved { id } => format!("- {id}: approved"),
+                ReviewOutcome::Deduped { id, existing_id } => {
+                    format!("- {id}: duplicate of active memory {existing_id}, discarded")
+                }
+                ReviewOutcome::Rejected { id } => format!("- {id}: rejected"),
+                ReviewOutcome::Edited { id, content } => format!("- {id}: edited to \"{content}\""),
+                ReviewOutcome::Recategorized { id, category } => {
+                    format!("- {id}: category set to {category}")
+                }
+                ReviewOutcome::Retracted { id, memory_id } => {
+                    format!("- {id}: approved, memory {memory_id} forgotten")
+                }
+                ReviewOutcome::Superseded { id, superseded_id } => {
+                    format!("- {id}: approved, replaces memory {superseded_id}")
+                }
+                ReviewOutcome::Blocked { id, reason } => format!("- {id}: {reason}"),
+                ReviewOutcome::Missing { id } => format!("- {id}: no pending memory found"),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Ok(ok(
+            "review",
+            json!({
+                "action": action_name,
+                "results": outcomes,
+                "rendered": rendered,
             }),
         ))
     }
 
+    pub fn memory_review_typed(
+        &mut self,
+        workspace_dir: &Path,
+        action: Option<String>,
+        ids: Option<Vec<String>>,
+        all: Option<bool>,
+        text: Option<String>,
+        category: Option<String>,
+        limit: Option<usize>,
+        cursor: Option<String>,
+    ) -> Result<Value> {
+        let targets = || -> Result<ReviewTargets> {
+            if all.unwrap_or(false) {
+                return Ok(ReviewTargets::All);
+            }
+            match ids.clone().filter(|ids| !ids.is_empty()) {
+                Some(ids) => Ok(ReviewTargets::Ids(ids)),
+                None => anyhow::bail!("provide ids or set all=true"),
+            }
+        };
+
+        let action = match action.as_deref().unwrap_or("list") {
+            "list" => ReviewAction::List { limit, cursor },
+            "approve" => ReviewAction::Approve(targets()?),
+            "reject" => ReviewAction::Reject(targets()?),
+            "category" | "recategorize" => ReviewAction::Recategorize {
+                category: cat_for_str(Some(
+                    category.context("category is required for recategorize")?,
+                ))?,
+                targets: targets()?,
+            },
+            "edit" => {
+                let id_or_prefix = ids
+                    .and_then(|ids| ids.into_iter().next())
+                    .context("an id is required for edit")?;
+                ReviewAction::Edit {
+                    id_or_prefix,
+                    text: text.context("text is required for edit")?,
+                }
+            }
+            other => anyhow::bail!(
+                "unknown review action '{other}'; expected list, approve, reject, edit or category"
+            ),
+        };
+
+        self.review_memories(workspace_dir, action)
+    }
+
+    pub fn memory_transfer_typed(
+        &mut self,
+        workspace_dir: &Path,
+        id_or_prefix: String,
+        scope: String,
+        copy: bool,
+    ) -> Result<Value> {
+        self.transfer_memory(
+            workspace_dir,
+            TransferArgs {
+                id_or_prefix,
+                target: ScopeSpec::parse(&scope),
+            },
+            copy,
+        )
+    }
+
     pub fn memory_add_typed(
         &mut self,
         workspace_dir: &Path,
@@ -1,12 +1,17 @@
 use crate::config::MemoryConfig;
+use crate::crypto::{CIPHER_NAME, ContentCipher, KeySource, generate_salt, is_encrypted};
+use crate::secrets::{SanitizedText, SecretScanner};
 use crate::types::{
-    AddMemoryInput, AddMemoryResult, CompactionMode, MemoryCategory, MemoryRow, MemoryStats,
-    ResolveIdResult,
+    AddMemoryInput, AddMemoryResult, CaptureAction, CompactionMode, MemoryCategory, MemoryEventRow,
+    MemoryRow, MemoryStats, ResolveIdResult, ReviewOutcome, ScopeEntry, TransferOutcome,
 };
-use crate::utils::{escape_like, normalize_for_hash, now_iso, sanitize_memory_text, sha256};
+use crate::utils::{escape_like, normalize_for_hash, now_iso, sha256};
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use rusqlite::{Connection, OptionalExtension, params, params_from_iter, types::Value};
+use rusqlite::{
+    Connection, OptionalExtension, Transaction, params, params_from_iter, types::Value,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use uuid::Uuid;
 
 fn load_schema_v1() -> &'static str {
@@ -75,11 +80,32 @@ fn with_scopes(scopes: &[String]) -> Vec<Value> {
     scopes.iter().map(|s| Value::Text(s.clone())).collect()
 }
 
+fn status_clause(include_pending: bool) -> &'static str {
+    if include_pending {
+        "status IN ('active', 'pending')"
+    } else {
+        "status = 'active'"
+    }
+}
+
+fn fallback_scope_label(scope: &str) -> String {
+    match scope.strip_prefix("project:") {
+        Some(rest) if rest.len() > 12 && rest.is_char_boundary(12) => {
+            format!("project:{}", &rest[..12])
+        }
+        _ => scope.to_string(),
+    }
+}
+
 pub struct MemoryStore {
     conn: Connection,
     pub has_fts: bool,
+    secret_scanner: SecretScanner,
+    cipher: Option<ContentCipher>,
 }
 
+const KEY_CHECK_PLAINTEXT: &str = "codex-extra-memory";
+
 impl MemoryStore {
     pub fn open(db_path: &std::path::Path) -> Result<Self> {
         let conn = Connection::open(db_path)
@@ -91,12 +117,28 @@ impl MemoryStore {
         let mut store = Self {
             conn,
             has_fts: false,
+            secret_scanner: SecretScanner::builtin(),
+            cipher: None,
         };
         store.migrate()?;
-        store.setup_fts();
+        if store.meta("encryption")?.is_some() {
+            store.conn.pragma_update(None, "secure_delete", "ON")?;
+            store.drop_fts_contents();
+        } else {
+            store.setup_fts();
+        }
         Ok(store)
     }
 
+    pub fn set_secret_scanner(&mut self, scanner: SecretScanner) {
+        self.secret_scanner = scanner;
+    }
+
+    #[must_use]
+    pub const fn secret_scanner(&self) -> &SecretScanner {
+        &self.secret_scanner
+    }
+
     fn migrate(&mut self) -> Result<()> {
         self.conn.execute_batch(
             "
@@ -158,6 +200,130 @@ impl MemoryStore {
             )?;
         }
 
+        if version < 4 {
+            let tx = self.conn.transaction()?;
+            tx.execute_batch(
+                "
+                CREATE TABLE memories_v4 (
+                  id TEXT PRIMARY KEY,
+                  scope TEXT NOT NULL,
+                  category TEXT NOT NULL DEFAULT 'other',
+                  content TEXT NOT NULL,
+                  content_hash TEXT NOT NULL,
+                  status TEXT NOT NULL DEFAULT 'active' CHECK(status IN ('active', 'pending', 'deleted', 'superseded')),
+                  pinned INTEGER NOT NULL DEFAULT 0,
+                  source TEXT NOT NULL DEFAULT 'user',
+                  created_at TEXT NOT NULL,
+                  updated_at TEXT NOT NULL
+                );
+
+                INSERT INTO memories_v4
+                (id, scope, category, content, content_hash, status, pinned, source, created_at, updated_at)
+                SELECT id, scope, category, content, content_hash, status, pinned, source, created_at, updated_at
+                FROM memories;
+
+                DROP TABLE memories;
+                ALTER TABLE memories_v4 RENAME TO memories;
+
+                CREATE UNIQUE INDEX IF NOT EXISTS idx_memories_scope_hash_active
+                ON memories(scope, content_hash)
+                WHERE status = 'active';
+
+                CREATE INDEX IF NOT EXISTS idx_memories_scope_status_updated
+                ON memories(scope, status, pinned DESC, updated_at DESC);
+                ",
+            )?;
+            tx.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (?, ?)",
+                params![4_i64, now_iso()],
+            )?;
+            tx.commit()?;
+        }
+
+        if version < 5 {
+            self.conn.execute_batch(
+                "
+                CREATE TABLE IF NOT EXISTS auto_capture_processed (
+                  seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                  hash TEXT NOT NULL UNIQUE,
+                  processed_at TEXT NOT NULL
+                );
+                ",
+            )?;
+            self.conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (?, ?)",
+                params![5_i64, now_iso()],
+            )?;
+        }
+
+        if version < 6 {
+            self.conn.execute_batch(
+                "
+                CREATE TABLE IF NOT EXISTS pending_actions (
+                  memory_id TEXT PRIMARY KEY,
+                  action TEXT NOT NULL CHECK(action IN ('retract', 'replace')),
+                  target_id TEXT NOT NULL,
+                  created_at TEXT NOT NULL
+                );
+                ",
+            )?;
+            self.conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (?, ?)",
+                params![6_i64, now_iso()],
+            )?;
+        }
+
+        if version < 7 {
+            self.conn.execute_batch(
+                "
+                CREATE TABLE IF NOT EXISTS backfill_files (
+                  path TEXT PRIMARY KEY,
+                  size INTEGER NOT NULL,
+                  processed_at TEXT NOT NULL
+                );
+                ",
+            )?;
+            self.conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (?, ?)",
+                params![7_i64, now_iso()],
+            )?;
+        }
+
+        if version < 8 {
+            self.conn.execute_batch(
+                "
+                CREATE TABLE IF NOT EXISTS store_meta (
+                  key TEXT PRIMARY KEY,
+                  value TEXT NOT NULL
+                );
+                ",
+            )?;
+            self.conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (?, ?)",
+                params![8_i64, now_iso()],
+            )?;
+        }
+
+        if version < 9 {
+            self.conn.execute_batch(
+                "
+                CREATE TABLE IF NOT EXISTS scopes (
+                  scope TEXT PRIMARY KEY,
+                  kind TEXT NOT NULL,
+                  identifier TEXT NOT NULL,
+                  root TEXT NOT NULL,
+                  label TEXT NOT NULL,
+                  first_seen TEXT NOT NULL,
+                  last_seen TEXT NOT NULL
+                );
+                ",
+            )?;
+            self.conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (?, ?)",
+                params![9_i64, now_iso()],
+            )?;
+        }
+
         Ok(())
     }
 
@@ -178,8 +344,240 @@ impl MemoryStore {
         }
     }
 
+    /// Drops and recreates the FTS table empty so no index segment keeps plaintext.
+    fn drop_fts_contents(&mut self) {
+        self.has_fts = false;
+        let _ = self.conn.execute_batch(
+            "
+            DROP TABLE IF EXISTS memories_fts;
+            CREATE VIRTUAL TABLE IF NOT EXISTS memories_fts
+            USING fts5(id UNINDEXED, scope UNINDEXED, category UNINDEXED, content);
+            ",
+        );
+    }
+
+    /// Zeroes freed pages and rewrites the file so removed text does not linger on disk.
+    fn scrub_free_pages(&self) -> Result<()> {
+        self.conn.pragma_update(None, "secure_delete", "ON")?;
+        self.conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        self.conn.execute_batch("VACUUM")?;
+        self.conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
+
+    fn meta(&self, key: &str) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT value FROM store_meta WHERE key = ?",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    #[must_use]
+    pub const fn is_unlocked(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    pub fn encryption_status(&self) -> Result<Option<(String, String)>> {
+        Ok(self
+            .meta("encryption")?
+            .map(|cipher| (cipher, self.meta("kdf").ok().flatten().unwrap_or_default())))
+    }
+
+    pub fn unlock(&mut self, source: Option<&KeySource>) -> Result<()> {
+        let Some((_, kdf)) = self.encryption_status()? else {
+            return Ok(());
+        };
+        let Some(source) = source else {
+            anyhow::bail!(
+                "memory database is encrypted; set CODEX_MEMORY_KEY, CODEX_MEMORY_KEY_FILE, CODEX_MEMORY_PASSPHRASE, or encryption.keyFile"
+            );
+        };
+        if source.kdf() != kdf {
+            anyhow::bail!(
+                "memory database was encrypted with kdf '{kdf}' but the configured key uses '{}'",
+                source.kdf()
+            );
+        }
+        let cipher = ContentCipher::from_source(source, self.meta("kdf_salt")?.as_deref())?;
+        let check = self.meta("key_check")?.unwrap_or_default();
+        match cipher.decrypt(&check) {
+            Ok(value) if value == KEY_CHECK_PLAINTEXT => {}
+            _ => anyhow::bail!("memory encryption key does not match this database"),
+        }
+        self.cipher = Some(cipher);
+        Ok(())
+    }
+
+    fn seal(&self, value: &str) -> String {
+        self.cipher
+            .as_ref()
+            .map_or_else(|| value.to_string(), |cipher| cipher.encrypt(value))
+    }
+
+    fn open_text(&self, value: String) -> Result<String> {
+        match &self.cipher {
+            Some(cipher) => cipher.decrypt(&value),
+            None if is_encrypted(&value) => {
+                anyhow::bail!("memory database is encrypted and no key is loaded")
+            }
+            None => Ok(value),
+        }
+    }
+
+    fn reveal(&self, mut row: MemoryRow) -> Result<MemoryRow> {
+        row.content = self.open_text(row.content)?;
+        Ok(row)
+    }
+
+    fn reveal_rows(&self, rows: Vec<MemoryRow>) -> Result<Vec<MemoryRow>> {
+        rows.into_iter().map(|row| self.reveal(row)).collect()
+    }
+
+    fn rewrite_encrypted_columns(
+        tx: &Transaction,
+        transform: &dyn Fn(&str) -> Result<String>,
+    ) -> Result<(usize, usize)> {
+        let memories = {
+            let mut stmt = tx.prepare("SELECT id, content FROM memories")?;
+            stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?
+        };
+        for (id, content) in &memories {
+            tx.execute(
+                "UPDATE memories SET content = ? WHERE id = ?",
+                params![transform(content)?, id],
+            )?;
+        }
+        let events = {
+            let mut stmt =
+                tx.prepare("SELECT id, payload FROM memory_events WHERE payload IS NOT NULL")?;
+            stmt.query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?
+        };
+        for (id, payload) in &events {
+            tx.execute(
+                "UPDATE memory_events SET payload = ? WHERE id = ?",
+                params![transform(payload)?, id],
+            )?;
+        }
+        let scopes = {
+            let mut stmt = tx.prepare("SELECT scope, identifier, root, label FROM scopes")?;
+            stmt.query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?
+        };
+        for (scope, identifier, root, label) in &scopes {
+            tx.execute(
+                "UPDATE scopes SET identifier = ?, root = ?, label = ? WHERE scope = ?",
+                params![
+                    transform(identifier)?,
+                    transform(root)?,
+                    transform(label)?,
+                    scope
+                ],
+            )?;
+        }
+        let compactions = {
+            let mut stmt =
+                tx.prepare("SELECT id, details FROM memory_compactions WHERE details IS NOT NULL")?;
+            stmt.query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?
+        };
+        for (id, details) in &compactions {
+            tx.execute(
+                "UPDATE memory_compactions SET details = ? WHERE id = ?",
+                params![transform(details)?, id],
+            )?;
+        }
+        Ok((memories.len(), events.len()))
+    }
+
+    pub fn encrypt_database(&mut self, source: &KeySource) -> Result<(usize, usize)> {
+        if self.encryption_status()?.is_some() {
+            anyhow::bail!("memory database is already encrypted");
+        }
+        let salt = matches!(source, KeySource::Passphrase(_)).then(generate_salt);
+        let cipher = ContentCipher::from_source(source, salt.as_deref())?;
+        let tx = self.conn.transaction()?;
+        let counts = Self::rewrite_encrypted_columns(&tx, &|value| {
+            Ok(if is_encrypted(value) {
+                value.to_string()
+            } else {
+                cipher.encrypt(value)
+            })
+        })?;
+
+        let mut meta = vec![
+            ("encryption", CIPHER_NAME.to_string()),
+            ("kdf", source.kdf().to_string()),
+            ("key_check", cipher.encrypt(KEY_CHECK_PLAINTEXT)),
+        ];
+        if let Some(salt) = salt {
+            meta.push(("kdf_salt", salt));
+        }
+        for (key, value) in meta {
+            tx.execute(
+                "INSERT OR REPLACE INTO store_meta (key, value) VALUES (?, ?)",
+                params![key, value],
+            )?;
+        }
+        tx.commit()?;
+        self.cipher = Some(cipher);
+        self.conn.pragma_update(None, "secure_delete", "ON")?;
+        self.drop_fts_contents();
+        self.scrub_free_pages()?;
+        Ok(counts)
+    }
+
+    pub fn decrypt_database(&mut self) -> Result<(usize, usize)> {
+        if self.encryption_status()?.is_none() {
+            anyhow::bail!("memory database is not encrypted");
+        }
+        let Some(cipher) = self.cipher.take() else {
+            anyhow::bail!("memory database is encrypted and no key is loaded");
+        };
+        let decrypted = self.conn.transaction().map_err(anyhow::Error::from).and_then(|tx| {
+            let counts = Self::rewrite_encrypted_columns(&tx, &|value| cipher.decrypt(value))?;
+            tx.execute(
+                "DELETE FROM store_meta WHERE key IN ('encryption', 'kdf', 'kdf_salt', 'key_check')",
+                [],
+            )?;
+            tx.commit()?;
+            Ok(counts)
+        });
+        let counts = match decrypted {
+            Ok(counts) => counts,
+            Err(error) => {
+                self.cipher = Some(cipher);
+                return Err(error);
+            }
+        };
+        self.setup_fts();
+        Ok(counts)
+    }
+
     fn add_event(&mut self, memory_id: &str, action: &str, payload: Option<&serde_json::Value>) {
-        let payload_text = payload.and_then(|p| serde_json::to_string(p).ok());
+        let payload_text = payload
+            .and_then(|p| serde_json::to_string(p).ok())
+            .map(|text| self.seal(&text));
         let _ = self.conn.execute(
             "INSERT INTO memory_events (memory_id, action, timestamp, payload) VALUES (?, ?, ?, ?)",
             params![memory_id, action, now_iso(), payload_text],
@@ -288,10 +686,28 @@ impl MemoryStore {
     }
 
     pub fn add_memory(&mut self, input: AddMemoryInput) -> Result<AddMemoryResult> {
-        let sanitized = match sanitize_memory_text(&input.content) {
-            Ok(text) => text,
+        self.add_memory_with_status(input, "active")
+    }
+
+    pub fn add_pending_memory(&mut self, input: AddMemoryInput) -> Result<AddMemoryResult> {
+        self.add_memory_with_status(input, "pending")
+    }
+
+    fn add_memory_with_status(
+        &mut self,
+        input: AddMemoryInput,
+        status: &str,
+    ) -> Result<AddMemoryResult> {
+        let SanitizedText {
+            text: sanitized,
+            redacted,
+        } = match self.secret_scanner.sanitize(&input.content) {
+            Ok(sanitized) => sanitized,
             Err(reason) => {
-                return Ok(AddMemoryResult::Blocked { reason });
+                return Ok(AddMemoryResult::Blocked {
+                    reason,
+                    detectors: self.secret_scanner.detect(&input.content),
+                });
             }
         };
 
@@ -299,8 +715,8 @@ impl MemoryStore {
         let existing = self
             .conn
             .query_row(
-                "SELECT id, category, content FROM memories WHERE scope = ? AND content_hash = ? AND status = 'active' LIMIT 1",
-                params![input.scope, content_hash],
+                "SELECT id, category, content FROM memories WHERE scope = ? AND content_hash = ? AND status IN ('active', ?) ORDER BY status = 'active' DESC LIMIT 1",
+                params![input.scope, content_hash, status],
                 |row| {
                     Ok((
                         row.get::<_, String>(0)?,
@@ -312,6 +728,7 @@ impl MemoryStore {
             .optional()?;
 
         if let Some((id, category, content)) = existing {
+            let content = self.open_text(content)?;
             self.conn.execute(
                 "UPDATE memories SET updated_at = ? WHERE id = ?",
                 params![now_iso(), id],
@@ -326,6 +743,7 @@ impl MemoryStore {
                 scope: input.scope,
                 category: parse_category(&category),
                 content,
+                redacted,
             });
         }
 
@@ -337,33 +755,35 @@ impl MemoryStore {
             category: input.category,
             content: sanitized.clone(),
             content_hash: content_hash.clone(),
-            status: "active".to_string(),
+            status: status.to_string(),
             pinned: false,
             source: input.source.clone(),
             created_at: parse_ts(&timestamp),
             updated_at: parse_ts(&timestamp),
         };
 
+        let stored_content = self.seal(&row.content);
         let tx = self.conn.transaction()?;
         tx.execute(
             "
             INSERT INTO memories
             (id, scope, category, content, content_hash, status, pinned, source, created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?, 'active', 0, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, 0, ?, ?, ?)
             ",
             params![
                 row.id,
                 row.scope,
                 row.category.as_str(),
-                row.content,
+                stored_content,
                 row.content_hash,
+                row.status,
                 row.source,
                 timestamp,
                 timestamp,
             ],
         )?;
 
-        if self.has_fts {
+        if self.has_fts && status == "active" {
             tx.execute(
                 "INSERT INTO memories_fts (id, scope, category, content) VALUES (?, ?, ?, ?)",
                 params![row.id, row.scope, row.category.as_str(), row.content],
@@ -373,11 +793,16 @@ impl MemoryStore {
 
         self.add_event(
             &id,
-            "added",
+            if status == "pending" {
+                "queued"
+            } else {
+                "added"
+            },
             Some(&serde_json::json!({
                 "scope": input.scope,
                 "category": input.category,
                 "source": input.source,
+                "redacted": redacted,
             })),
         );
 
@@ -386,6 +811,7 @@ impl MemoryStore {
             scope: input.scope,
             category: input.category,
             content: sanitized,
+            redacted,
         })
     }
 
@@ -393,6 +819,15 @@ impl MemoryStore {
         &self,
         id_or_prefix: &str,
         scopes: Option<&[String]>,
+    ) -> Result<ResolveIdResult> {
+        self.resolve_id_with_status(id_or_prefix, scopes, "active")
+    }
+
+    pub fn resolve_id_with_status(
+        &self,
+        id_or_prefix: &str,
+        scopes: Option<&[String]>,
+        status: &str,
     ) -> Result<ResolveIdResult> {
         let normalized = id_or_prefix.trim();
         if normalized.is_empty() {
@@ -405,13 +840,15 @@ impl MemoryStore {
             .unwrap_or_default();
 
         if Uuid::parse_str(normalized).is_ok() {
-            let mut values = vec![Value::Text(normalized.to_string())];
+            let mut values = vec![
+                Value::Text(normalized.to_string()),
+                Value::Text(status.to_string()),
+            ];
             if let Some(scopes) = scope_filter {
                 values.extend(with_scopes(scopes));
             }
-            let sql = format!(
-                "SELECT id FROM memories WHERE id = ? AND status = 'active'{scope_sql} LIMIT 1"
-            );
+            let sql =
+                format!("SELECT id FROM memories WHERE id = ? AND status = ?{scope_sql} LIMIT 1");
             let found = self
                 .conn
                 .query_row(&sql, params_from_iter(values), |row| {
@@ -425,7 +862,10 @@ impl MemoryStore {
         }
 
         let escaped = escape_like(normalized);
-        let mut values = vec![Value::Text(format!("{escaped}%"))];
+        let mut values = vec![
+            Value::Text(format!("{escaped}%")),
+            Value::Text(status.to_string()),
+        ];
         if let Some(scopes) = scope_filter {
             values.extend(with_scopes(scopes));
         }
@@ -435,7 +875,7 @@ impl MemoryStore {
             "
             SELECT id
             FROM memories
-            WHERE id LIKE ? ESCAPE '\\' AND status = 'active'{scope_sql}
+            WHERE id LIKE ? ESCAPE '\\' AND status = ?{scope_sql}
             ORDER BY updated_at DESC
             LIMIT ?
             "
@@ -459,17 +899,19 @@ impl MemoryStore {
         scopes: &[String],
         limit: usize,
         offset: usize,
+        include_pending: bool,
     ) -> Result<(Vec<MemoryRow>, bool)> {
         if scopes.is_empty() {
             return Ok((Vec::new(), false));
         }
 
         let scope_clause = scopes_in_clause(scopes);
+        let status_clause = status_clause(include_pending);
         let sql = format!(
             "
             SELECT id, scope, category, content, content_hash, status, pinned, source, created_at, updated_at
             FROM memories
-            WHERE status = 'active' AND scope IN {scope_clause}
+            WHERE {status_clause} AND scope IN {scope_clause}
             ORDER BY pinned DESC, updated_at DESC
             LIMIT ? OFFSET ?
             "
@@ -489,7 +931,7 @@ impl MemoryStore {
             items.pop();
         }
 
-        Ok((items, has_more))
+        Ok((self.reveal_rows(items)?, has_more))
     }
 
     fn to_fts_query(raw: &str) -> String {
@@ -514,13 +956,15 @@ impl MemoryStore {
         query: &str,
         limit: usize,
         offset: usize,
+        include_pending: bool,
     ) -> Result<(Vec<MemoryRow>, bool)> {
         let cleaned = query.trim();
         if cleaned.is_empty() || scopes.is_empty() {
             return Ok((Vec::new(), false));
         }
 
-        if self.has_fts {
+        // Pending rows are not indexed, so including them falls back to a substring scan.
+        if self.has_fts && !include_pending {
             let fts_query = Self::to_fts_query(cleaned);
             if !fts_query.is_empty() {
                 let scope_clause = scopes_in_clause(scopes);
@@ -551,19 +995,24 @@ impl MemoryStore {
                         items.pop();
                     }
                     if !items.is_empty() {
-                        return Ok((items, has_more));
+                        return Ok((self.reveal_rows(items)?, has_more));
                     }
                 }
             }
         }
 
+        if self.cipher.is_some() {
+            return self.search_decrypted(scopes, cleaned, limit, offset, include_pending);
+        }
+
         let escaped_query = escape_like(cleaned);
         let scope_clause = scopes_in_clause(scopes);
+        let status_clause = status_clause(include_pending);
         let sql = format!(
             "
             SELECT id, scope, category, content, content_hash, status, pinned, source, created_at, updated_at
             FROM memories
-            WHERE status = 'active'
+            WHERE {status_clause}
               AND scope IN {scope_clause}
               AND lower(content) LIKE '%' || lower(?) || '%' ESCAPE '\\'
             ORDER BY pinned DESC, updated_at DESC
@@ -584,6 +1033,54 @@ impl MemoryStore {
         if has_more {
             items.pop();
         }
+        Ok((self.reveal_rows(items)?, has_more))
+    }
+
+    fn search_decrypted(
+        &self,
+        scopes: &[String],
+        query: &str,
+        limit: usize,
+        offset: usize,
+        include_pending: bool,
+    ) -> Result<(Vec<MemoryRow>, bool)> {
+        let scope_clause = scopes_in_clause(scopes);
+        let status_clause = status_clause(include_pending);
+        let sql = format!(
+            "
+            SELECT id, scope, category, content, content_hash, status, pinned, source, created_at, updated_at
+            FROM memories
+            WHERE {status_clause} AND scope IN {scope_clause}
+            ORDER BY pinned DESC, updated_at DESC
+            "
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(params_from_iter(with_scopes(scopes)), row_from_stmt)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let terms = query
+            .to_lowercase()
+            .split_whitespace()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let mut items = Vec::new();
+        for row in rows {
+            let row = self.reveal(row)?;
+            let content = row.content.to_lowercase();
+            if terms.iter().all(|term| content.contains(term.as_str())) {
+                items.push(row);
+            }
+        }
+        let mut items = items
+            .into_iter()
+            .skip(offset)
+            .take(limit + 1)
+            .collect::<Vec<_>>();
+        let has_more = items.len() > limit;
+        if has_more {
+            items.pop();
+        }
         Ok((items, has_more))
     }
 
@@ -601,57 +1098,388 @@ impl MemoryStore {
         Ok(true)
     }
 
-    pub fn set_pinned(&mut self, memory_id: &str, pinned: bool) -> Result<bool> {
+    pub fn retract_memory(&mut self, memory_id: &str, phrase: &str) -> Result<bool> {
         let changes = self.conn.execute(
-            "UPDATE memories SET pinned = ?, updated_at = ? WHERE id = ? AND status = 'active'",
-            params![i64::from(u8::from(pinned)), now_iso(), memory_id],
+            "UPDATE memories SET status = 'deleted', updated_at = ? WHERE id = ? AND status = 'active'",
+            params![now_iso(), memory_id],
         )?;
-        if changes > 0 {
-            self.add_event(memory_id, if pinned { "pinned" } else { "unpinned" }, None);
-            Ok(true)
-        } else {
-            Ok(false)
+        if changes == 0 {
+            return Ok(false);
         }
+
+        self.remove_fts_entry(memory_id);
+        self.add_event(
+            memory_id,
+            "retracted",
+            Some(&serde_json::json!({"phrase": phrase})),
+        );
+        Ok(true)
     }
 
-    pub fn get_injection_candidates(
-        &self,
-        project_scope: &str,
-        limit: usize,
-    ) -> Result<Vec<MemoryRow>> {
-        let mut stmt = self.conn.prepare(
-            "
-            SELECT id, scope, category, content, content_hash, status, pinned, source, created_at, updated_at
-            FROM memories
-            WHERE status = 'active' AND scope IN (?, 'global')
-            ORDER BY CASE
-              WHEN scope = ? AND pinned = 1 THEN 0
-              WHEN scope = 'global' AND pinned = 1 THEN 1
-              WHEN scope = ? THEN 2
-              ELSE 3
-            END,
-            updated_at DESC
-            LIMIT ?
-            ",
+    pub fn supersede_memory(&mut self, memory_id: &str, replacement_id: &str) -> Result<bool> {
+        if memory_id == replacement_id {
+            return Ok(false);
+        }
+        let changes = self.conn.execute(
+            "UPDATE memories SET status = 'superseded', updated_at = ? WHERE id = ? AND status = 'active'",
+            params![now_iso(), memory_id],
         )?;
+        if changes == 0 {
+            return Ok(false);
+        }
 
-        let items = stmt
-            .query_map(
-                params![project_scope, project_scope, project_scope, limit as i64],
-                row_from_stmt,
-            )?
-            .collect::<rusqlite::Result<Vec<_>>>()?;
-        Ok(items)
+        self.remove_fts_entry(memory_id);
+        self.add_event(
+            memory_id,
+            "superseded",
+            Some(&serde_json::json!({"by": replacement_id})),
+        );
+        Ok(true)
+    }
+
+    pub fn queue_correction(
+        &mut self,
+        input: AddMemoryInput,
+        action: CaptureAction,
+        target_id: &str,
+    ) -> Result<AddMemoryResult> {
+        let result = self.add_pending_memory(input)?;
+        if let AddMemoryResult::Added { id, .. } = &result {
+            self.conn.execute(
+                "INSERT OR REPLACE INTO pending_actions (memory_id, action, target_id, created_at) VALUES (?, ?, ?, ?)",
+                params![id, action.as_str(), target_id, now_iso()],
+            )?;
+        }
+        Ok(result)
+    }
+
+    fn pending_action(&self, memory_id: &str) -> Result<Option<(String, String)>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT action, target_id FROM pending_actions WHERE memory_id = ?",
+                params![memory_id],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )
+            .optional()?)
+    }
+
+    pub fn list_pending_memories(
+        &self,
+        scopes: &[String],
+        limit: usize,
+        offset: usize,
+    ) -> Result<(Vec<MemoryRow>, bool)> {
+        if scopes.is_empty() {
+            return Ok((Vec::new(), false));
+        }
+
+        let scope_clause = scopes_in_clause(scopes);
+        let sql = format!(
+            "
+            SELECT id, scope, category, content, content_hash, status, pinned, source, created_at, updated_at
+            FROM memories
+            WHERE status = 'pending' AND scope IN {scope_clause}
+            ORDER BY created_at ASC
+            LIMIT ? OFFSET ?
+            "
+        );
+
+        let mut values = with_scopes(scopes);
+        values.push(Value::Integer((limit as i64) + 1));
+        values.push(Value::Integer(offset as i64));
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut items = stmt
+            .query_map(params_from_iter(values), row_from_stmt)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let has_more = items.len() > limit;
+        if has_more {
+            items.pop();
+        }
+
+        Ok((self.reveal_rows(items)?, has_more))
+    }
+
+    fn get_memory_with_status(&self, memory_id: &str, status: &str) -> Result<Option<MemoryRow>> {
+        self.conn
+            .query_row(
+                "
+                SELECT id, scope, category, content, content_hash, status, pinned, source, created_at, updated_at
+                FROM memories
+                WHERE id = ? AND status = ?
+                ",
+                params![memory_id, status],
+                row_from_stmt,
+            )
+            .optional()?
+            .map(|row| self.reveal(row))
+            .transpose()
+    }
+
+    pub fn approve_pending(&mut self, memory_id: &str) -> Result<ReviewOutcome> {
+        self.in_transaction(|store| store.approve_pending_inner(memory_id))
+    }
+
+    fn approve_pending_inner(&mut self, memory_id: &str) -> Result<ReviewOutcome> {
+        let Some(row) = self.get_memory_with_status(memory_id, "pending")? else {
+            return Ok(ReviewOutcome::Missing {
+                id: memory_id.to_string(),
+            });
+        };
+
+        let pending_action = self.pending_action(&row.id)?;
+        if pending_action.is_some() {
+            self.conn.execute(
+                "DELETE FROM pending_actions WHERE memory_id = ?",
+                params![row.id],
+            )?;
+        }
+
+        if let Some((action, target_id)) = &pending_action
+            && action == "retract"
+        {
+            self.conn.execute(
+                "UPDATE memories SET status = 'deleted', updated_at = ? WHERE id = ?",
+                params![now_iso(), row.id],
+            )?;
+            self.retract_memory(target_id, &row.content)?;
+            self.add_event(
+                &row.id,
+                "approved",
+                Some(&serde_json::json!({"retracted": target_id})),
+            );
+            return Ok(ReviewOutcome::Retracted {
+                id: row.id,
+                memory_id: target_id.clone(),
+            });
+        }
+        let supersedes = pending_action.map(|(_, target_id)| target_id);
+
+        let existing = self
+            .conn
+            .query_row(
+                "SELECT id FROM memories WHERE scope = ? AND content_hash = ? AND status = 'active' LIMIT 1",
+                params![row.scope, row.content_hash],
+                |r| r.get::<_, String>(0),
+            )
+            .optional()?;
+
+        if let Some(existing_id) = existing {
+            self.conn.execute(
+                "UPDATE memories SET status = 'deleted', updated_at = ? WHERE id = ?",
+                params![now_iso(), row.id],
+            )?;
+            self.conn.execute(
+                "UPDATE memories SET updated_at = ? WHERE id = ?",
+                params![now_iso(), existing_id],
+            )?;
+            self.add_event(
+                &row.id,
+                "review_deduped",
+                Some(&serde_json::json!({"existing_id": existing_id})),
+            );
+            if let Some(target_id) = &supersedes {
+                self.supersede_memory(target_id, &existing_id)?;
+            }
+            return Ok(ReviewOutcome::Deduped {
+                id: row.id,
+                existing_id,
+            });
+        }
+
+        self.conn.execute(
+            "UPDATE memories SET status = 'active', updated_at = ? WHERE id = ?",
+            params![now_iso(), row.id],
+        )?;
+        if self.has_fts {
+            self.conn.execute(
+                "INSERT INTO memories_fts (id, scope, category, content) VALUES (?, ?, ?, ?)",
+                params![row.id, row.scope, row.category.as_str(), row.content],
+            )?;
+        }
+
+        self.add_event(&row.id, "approved", None);
+        if let Some(target_id) = supersedes
+            && self.supersede_memory(&target_id, &row.id)?
+        {
+            return Ok(ReviewOutcome::Superseded {
+                id: row.id,
+                superseded_id: target_id,
+            });
+        }
+        Ok(ReviewOutcome::Approved { id: row.id })
+    }
+
+    pub fn reject_pending(&mut self, memory_id: &str) -> Result<ReviewOutcome> {
+        let changes = self.conn.execute(
+            "UPDATE memories SET status = 'deleted', updated_at = ? WHERE id = ? AND status = 'pending'",
+            params![now_iso(), memory_id],
+        )?;
+        if changes == 0 {
+            return Ok(ReviewOutcome::Missing {
+                id: memory_id.to_string(),
+            });
+        }
+
+        self.conn.execute(
+            "DELETE FROM pending_actions WHERE memory_id = ?",
+            params![memory_id],
+        )?;
+        self.add_event(memory_id, "rejected", None);
+        Ok(ReviewOutcome::Rejected {
+            id: memory_id.to_string(),
+        })
+    }
+
+    pub fn edit_pending(&mut self, memory_id: &str, text: &str) -> Result<ReviewOutcome> {
+        let sanitized = match self.secret_scanner.sanitize(text) {
+            Ok(sanitized) => sanitized.text,
+            Err(reason) => {
+                return Ok(ReviewOutcome::Blocked {
+                    id: memory_id.to_string(),
+                    reason,
+                });
+            }
+        };
+
+        let changes = self.conn.execute(
+            "UPDATE memories SET content = ?, content_hash = ?, updated_at = ? WHERE id = ? AND status = 'pending'",
+            params![
+                self.seal(&sanitized),
+                sha256(&normalize_for_hash(&sanitized)),
+                now_iso(),
+                memory_id
+            ],
+        )?;
+        if changes == 0 {
+            return Ok(ReviewOutcome::Missing {
+                id: memory_id.to_string(),
+            });
+        }
+
+        self.add_event(memory_id, "edited", None);
+        Ok(ReviewOutcome::Edited {
+            id: memory_id.to_string(),
+            content: sanitized,
+        })
+    }
+
+    pub fn recategorize_pending(
+        &mut self,
+        memory_id: &str,
+        category: MemoryCategory,
+    ) -> Result<ReviewOutcome> {
+        let changes = self.conn.execute(
+            "UPDATE memories SET category = ?, updated_at = ? WHERE id = ? AND status = 'pending'",
+            params![category.as_str(), now_iso(), memory_id],
+        )?;
+        if changes == 0 {
+            return Ok(ReviewOutcome::Missing {
+                id: memory_id.to_string(),
+            });
+        }
+
+        self.add_event(
+            memory_id,
+            "recategorized",
+            Some(&serde_json::json!({"category": category})),
+        );
+        Ok(ReviewOutcome::Recategorized {
+            id: memory_id.to_string(),
+            category,
+        })
+    }
+
+    pub fn set_pinned(&mut self, memory_id: &str, pinned: bool) -> Result<bool> {
+        let changes = self.conn.execute(
+            "UPDATE memories SET pinned = ?, updated_at = ? WHERE id = ? AND status = 'active'",
+            params![i64::from(u8::from(pinned)), now_iso(), memory_id],
+        )?;
+        if changes > 0 {
+            self.add_event(memory_id, if pinned { "pinned" } else { "unpinned" }, None);
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    pub fn get_injection_candidates(
+        &self,
+        scopes: &[String],
+        limit: usize,
+    ) -> Result<Vec<MemoryRow>> {
+        let local_scopes = scopes
+            .iter()
+            .filter(|scope| scope.as_str() != "global")
+            .collect::<Vec<_>>();
+        let rank_cases = local_scopes
+            .iter()
+            .enumerate()
+            .map(|(index, _)| format!("WHEN ? THEN {index}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let rank = if rank_cases.is_empty() {
+            "0".to_string()
+        } else {
+            format!("CASE scope {rank_cases} ELSE {} END", local_scopes.len())
+        };
+        let sql = format!(
+            "
+            SELECT id, scope, category, content, content_hash, status, pinned, source, created_at, updated_at
+            FROM memories
+            WHERE status = 'active' AND scope IN {}
+            ORDER BY CASE
+              WHEN scope != 'global' AND pinned = 1 THEN 0
+              WHEN scope = 'global' AND pinned = 1 THEN 1
+              WHEN scope != 'global' THEN 2
+              ELSE 3
+            END,
+            {rank},
+            updated_at DESC
+            LIMIT ?
+            ",
+            scopes_in_clause(scopes)
+        );
+
+        let mut values = with_scopes(scopes);
+        values.extend(
+            local_scopes
+                .iter()
+                .map(|scope| Value::Text((*scope).clone())),
+        );
+        values.push(Value::Integer(limit as i64));
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let items = stmt
+            .query_map(params_from_iter(values), row_from_stmt)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        self.reveal_rows(items)
+    }
+
+    fn count_processed_hashes(&self) -> Result<i64> {
+        Ok(self
+            .conn
+            .query_row("SELECT COUNT(*) FROM auto_capture_processed", [], |row| {
+                row.get(0)
+            })?)
     }
 
     pub fn get_stats(&self, scopes: &[String]) -> Result<MemoryStats> {
         if scopes.is_empty() {
             return Ok(MemoryStats {
                 active: 0,
+                pending: 0,
                 pinned: 0,
                 global: 0,
+                org: 0,
                 project: 0,
+                processed_hashes: self.count_processed_hashes()?,
                 has_fts: self.has_fts,
+                encrypted: self.cipher.is_some(),
+                by_scope: Vec::new(),
+                scope_detection: None,
             });
         }
 
@@ -660,6 +1488,9 @@ impl MemoryStore {
         let active_sql = format!(
             "SELECT COUNT(*) FROM memories WHERE status = 'active' AND scope IN {scope_clause}"
         );
+        let pending_sql = format!(
+            "SELECT COUNT(*) FROM memories WHERE status = 'pending' AND scope IN {scope_clause}"
+        );
         let pinned_sql = format!(
             "SELECT COUNT(*) FROM memories WHERE status = 'active' AND pinned = 1 AND scope IN {scope_clause}"
         );
@@ -672,6 +1503,11 @@ impl MemoryStore {
                 .query_row(&active_sql, params_from_iter(with_scopes(scopes)), |row| {
                     row.get(0)
                 })?;
+        let pending: i64 =
+            self.conn
+                .query_row(&pending_sql, params_from_iter(with_scopes(scopes)), |row| {
+                    row.get(0)
+                })?;
         let pinned: i64 =
             self.conn
                 .query_row(&pinned_sql, params_from_iter(with_scopes(scopes)), |row| {
@@ -679,6 +1515,7 @@ impl MemoryStore {
                 })?;
 
         let mut global = 0_i64;
+        let mut org = 0_i64;
         let mut project = 0_i64;
 
         let mut stmt = self.conn.prepare(&by_scope_sql)?;
@@ -690,6 +1527,8 @@ impl MemoryStore {
             let (scope, count) = row?;
             if scope == "global" {
                 global += count;
+            } else if scope.starts_with("org:") {
+                org += count;
             } else {
                 project += count;
             }
@@ -697,13 +1536,128 @@ impl MemoryStore {
 
         Ok(MemoryStats {
             active,
+            pending,
             pinned,
             global,
+            org,
             project,
+            processed_hashes: self.count_processed_hashes()?,
             has_fts: self.has_fts,
+            encrypted: self.cipher.is_some(),
+            by_scope: self.list_scopes(Some(scopes))?,
+            scope_detection: None,
         })
     }
 
+    pub fn record_scope(
+        &self,
+        scope: &str,
+        kind: &str,
+        identifier: &str,
+        root: &str,
+        label: &str,
+    ) -> Result<()> {
+        let now = now_iso();
+        self.conn.execute(
+            "
+            INSERT INTO scopes (scope, kind, identifier, root, label, first_seen, last_seen)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(scope) DO UPDATE SET
+              kind = excluded.kind,
+              identifier = excluded.identifier,
+              root = excluded.root,
+              label = excluded.label,
+              last_seen = excluded.last_seen
+            ",
+            params![
+                scope,
+                kind,
+                self.seal(identifier),
+                self.seal(root),
+                self.seal(label),
+                now,
+                now
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn scope_labels(&self) -> Result<HashMap<String, String>> {
+        let mut stmt = self.conn.prepare("SELECT scope, label FROM scopes")?;
+        let labels = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<(String, String)>>>()?;
+        labels
+            .into_iter()
+            .map(|(scope, label)| Ok((scope, self.open_text(label)?)))
+            .collect()
+    }
+
+    pub fn list_scopes(&self, only: Option<&[String]>) -> Result<Vec<ScopeEntry>> {
+        let mut entries = BTreeMap::<String, ScopeEntry>::new();
+        let mut registered = self.conn.prepare(
+            "SELECT scope, label, kind, identifier, root, first_seen, last_seen FROM scopes",
+        )?;
+        let rows = registered.query_map([], |row| {
+            Ok(ScopeEntry {
+                scope: row.get(0)?,
+                label: row.get(1)?,
+                kind: row.get(2)?,
+                identifier: row.get(3)?,
+                root: row.get(4)?,
+                first_seen: row.get(5)?,
+                last_seen: row.get(6)?,
+                active: 0,
+                pending: 0,
+            })
+        })?;
+        for row in rows {
+            let mut entry = row?;
+            entry.label = self.open_text(entry.label)?;
+            entry.identifier = entry.identifier.map(|v| self.open_text(v)).transpose()?;
+            entry.root = entry.root.map(|v| self.open_text(v)).transpose()?;
+            entries.insert(entry.scope.clone(), entry);
+        }
+
+        let mut counts = self.conn.prepare(
+            "
+            SELECT scope,
+              SUM(CASE WHEN status = 'active' THEN 1 ELSE 0 END),
+              SUM(CASE WHEN status = 'pending' THEN 1 ELSE 0 END)
+            FROM memories
+            GROUP BY scope
+            ",
+        )?;
+        let rows = counts.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (scope, active, pending) = row?;
+            let entry = entries.entry(scope.clone()).or_insert_with(|| ScopeEntry {
+                label: fallback_scope_label(&scope),
+                scope,
+                kind: None,
+                identifier: None,
+                root: None,
+                first_seen: None,
+                last_seen: None,
+                active: 0,
+                pending: 0,
+            });
+            entry.active = active;
+            entry.pending = pending;
+        }
+
+        Ok(entries
+            .into_values()
+            .filter(|entry| only.is_none_or(|scopes| scopes.contains(&entry.scope)))
+            .collect())
+    }
+
     pub fn export_active_memories(&self, scopes: Option<&[String]>) -> Result<Vec<MemoryRow>> {
         match scopes {
             Some([]) => Ok(Vec::new()),
@@ -721,7 +1675,7 @@ impl MemoryStore {
                 let rows = stmt
                     .query_map(params_from_iter(with_scopes(scope_values)), row_from_stmt)?
                     .collect::<rusqlite::Result<Vec<_>>>()?;
-                Ok(rows)
+                self.reveal_rows(rows)
             }
             None => {
                 let mut stmt = self.conn.prepare(
@@ -735,11 +1689,519 @@ impl MemoryStore {
                 let rows = stmt
                     .query_map([], row_from_stmt)?
                     .collect::<rusqlite::Result<Vec<_>>>()?;
-                Ok(rows)
+                self.reveal_rows(rows)
             }
         }
     }
 
+    pub fn audit_memories(&self, include_inactive: bool) -> Result<Vec<MemoryRow>> {
+        let statuses = if include_inactive {
+            "('active', 'pending', 'deleted', 'superseded')"
+        } else {
+            "('active', 'pending')"
+        };
+        let sql = format!(
+            "
+            SELECT id, scope, category, content, content_hash, status, pinned, source, created_at, updated_at
+            FROM memories
+            WHERE status IN {statuses}
+            ORDER BY scope, created_at
+            "
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map([], row_from_stmt)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        self.reveal_rows(rows)
+    }
+
+    pub fn audit_events(&self) -> Result<Vec<MemoryEventRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, memory_id, action, timestamp, payload FROM memory_events WHERE payload IS NOT NULL ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(MemoryEventRow {
+                    id: row.get(0)?,
+                    memory_id: row.get(1)?,
+                    action: row.get(2)?,
+                    timestamp: row.get(3)?,
+                    payload: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows.into_iter()
+            .map(|mut event| {
+                event.payload = self.open_text(event.payload)?;
+                Ok(event)
+            })
+            .collect()
+    }
+
+    pub fn set_event_payload(&mut self, event_id: i64, payload: Option<&str>) -> Result<bool> {
+        let payload = payload.map(|text| self.seal(text));
+        let changes = self.conn.execute(
+            "UPDATE memory_events SET payload = ? WHERE id = ?",
+            params![payload, event_id],
+        )?;
+        Ok(changes > 0)
+    }
+
+    pub fn redact_memory_content(
+        &mut self,
+        memory_id: &str,
+        content: &str,
+        detectors: &[String],
+    ) -> Result<bool> {
+        let Some((scope, category, status)) = self
+            .conn
+            .query_row(
+                "SELECT scope, category, status FROM memories WHERE id = ?",
+                params![memory_id],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                },
+            )
+            .optional()?
+        else {
+            return Ok(false);
+        };
+
+        let content_hash = sha256(&normalize_for_hash(content));
+        if status == "active" {
+            let duplicate = self
+                .conn
+                .query_row(
+                    "SELECT 1 FROM memories WHERE scope = ? AND content_hash = ? AND status = 'active' AND id != ?",
+                    params![scope, content_hash, memory_id],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+            if duplicate {
+                self.purge_memory(memory_id, detectors)?;
+                return Ok(false);
+            }
+        }
+
+        self.conn.execute(
+            "UPDATE memories SET content = ?, content_hash = ?, updated_at = ? WHERE id = ?",
+            params![self.seal(content), content_hash, now_iso(), memory_id],
+        )?;
+        self.remove_fts_entry(memory_id);
+        if self.has_fts && status == "active" {
+            self.conn.execute(
+                "INSERT INTO memories_fts (id, scope, category, content) VALUES (?, ?, ?, ?)",
+                params![memory_id, scope, category, content],
+            )?;
+        }
+        self.add_event(
+            memory_id,
+            "audit_redacted",
+            Some(&serde_json::json!({"detectors": detectors})),
+        );
+        Ok(true)
+    }
+
+    pub fn purge_memory(&mut self, memory_id: &str, detectors: &[String]) -> Result<bool> {
+        let changes = self
+            .conn
+            .execute("DELETE FROM memories WHERE id = ?", params![memory_id])?;
+        if changes == 0 {
+            return Ok(false);
+        }
+
+        self.remove_fts_entry(memory_id);
+        self.conn.execute(
+            "DELETE FROM memory_events WHERE memory_id = ?",
+            params![memory_id],
+        )?;
+        self.conn.execute(
+            "DELETE FROM pending_actions WHERE memory_id = ? OR target_id = ?",
+            params![memory_id, memory_id],
+        )?;
+        self.add_event(
+            memory_id,
+            "audit_purged",
+            Some(&serde_json::json!({"detectors": detectors})),
+        );
+        Ok(true)
+    }
+
+    fn active_id_in_scope(&self, scope: &str, content_hash: &str) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT id FROM memories WHERE scope = ? AND content_hash = ? AND status = 'active'",
+                params![scope, content_hash],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?)
+    }
+
+    fn active_row(&self, memory_id: &str) -> Result<Option<MemoryRow>> {
+        let row = self
+            .conn
+            .query_row(
+                "
+                SELECT id, scope, category, content, content_hash, status, pinned, source, created_at, updated_at
+                FROM memories
+                WHERE id = ? AND status = 'active'
+                ",
+                params![memory_id],
+                row_from_stmt,
+            )
+            .optional()?;
+        row.map(|row| self.reveal(row)).transpose()
+    }
+
+    pub fn move_memory(&mut self, memory_id: &str, to: &str) -> Result<TransferOutcome> {
+        self.in_transaction(|store| store.move_memory_inner(memory_id, to))
+    }
+
+    fn move_memory_inner(&mut self, memory_id: &str, to: &str) -> Result<TransferOutcome> {
+        let Some(row) = self.active_row(memory_id)? else {
+            return Ok(TransferOutcome::Missing {
+                id: memory_id.to_string(),
+            });
+        };
+        if row.scope == to {
+            return Ok(TransferOutcome::Unchanged {
+                id: row.id,
+                scope: row.scope,
+            });
+        }
+
+        if let Some(existing_id) = self.active_id_in_scope(to, &row.content_hash)? {
+            if row.pinned {
+                self.conn.execute(
+                    "UPDATE memories SET pinned = 1 WHERE id = ?",
+                    params![existing_id],
+                )?;
+            }
+            self.conn.execute(
+                "UPDATE memories SET status = 'superseded', updated_at = ? WHERE id = ?",
+                params![now_iso(), row.id],
+            )?;
+            self.remove_fts_entry(&row.id);
+            self.add_event(
+                &row.id,
+                "moved",
+                Some(
+                    &serde_json::json!({"from": row.scope, "to": to, "deduped_into": existing_id}),
+                ),
+            );
+            return Ok(TransferOutcome::Deduped {
+                id: existing_id,
+                source_id: row.id,
+                from: row.scope,
+                to: to.to_string(),
+            });
+        }
+
+        self.conn.execute(
+            "UPDATE memories SET scope = ?, updated_at = ? WHERE id = ?",
+            params![to, now_iso(), row.id],
+        )?;
+        if self.has_fts {
+            self.conn.execute(
+                "UPDATE memories_fts SET scope = ? WHERE id = ?",
+                params![to, row.id],
+            )?;
+        }
+        self.add_event(
+            &row.id,
+            "moved",
+            Some(&serde_json::json!({"from": row.scope, "to": to})),
+        );
+        Ok(TransferOutcome::Moved {
+            id: row.id,
+            from: row.scope,
+            to: to.to_string(),
+        })
+    }
+
+    pub fn copy_memory(&mut self, memory_id: &str, to: &str) -> Result<TransferOutcome> {
+        self.in_transaction(|store| store.copy_memory_inner(memory_id, to))
+    }
+
+    fn copy_memory_inner(&mut self, memory_id: &str, to: &str) -> Result<TransferOutcome> {
+        let Some(row) = self.active_row(memory_id)? else {
+            return Ok(TransferOutcome::Missing {
+                id: memory_id.to_string(),
+            });
+        };
+        if row.scope == to {
+            return Ok(TransferOutcome::Unchanged {
+                id: row.id,
+                scope: row.scope,
+            });
+        }
+
+        if let Some(existing_id) = self.active_id_in_scope(to, &row.content_hash)? {
+            self.add_event(
+                &row.id,
+                "copied",
+                Some(&serde_json::json!({"to": to, "deduped_into": existing_id})),
+            );
+            return Ok(TransferOutcome::Deduped {
+                id: existing_id,
+                source_id: row.id,
+                from: row.scope,
+                to: to.to_string(),
+            });
+        }
+
+        let id = Uuid::new_v4().to_string();
+        let timestamp = now_iso();
+        let stored_content = self.seal(&row.content);
+        self.conn.execute(
+            "
+            INSERT INTO memories
+            (id, scope, category, content, content_hash, status, pinned, source, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, 'active', ?, ?, ?, ?)
+            ",
+            params![
+                id,
+                to,
+                row.category.as_str(),
+                stored_content,
+                row.content_hash,
+                i64::from(row.pinned),
+                row.source,
+                timestamp,
+                timestamp,
+            ],
+        )?;
+        if self.has_fts {
+            self.conn.execute(
+                "INSERT INTO memories_fts (id, scope, category, content) VALUES (?, ?, ?, ?)",
+                params![id, to, row.category.as_str(), row.content],
+            )?;
+        }
+
+        self.add_event(
+            &row.id,
+            "copied",
+            Some(&serde_json::json!({"to": to, "copy_id": id})),
+        );
+        self.add_event(
+            &id,
+            "copied",
+            Some(&serde_json::json!({"from": row.scope, "source_id": row.id})),
+        );
+        Ok(TransferOutcome::Copied {
+            id,
+            source_id: row.id,
+            from: row.scope,
+            to: to.to_string(),
+        })
+    }
+
+    /// Runs audit remediation in one transaction, then rebuilds the FTS index and
+    /// vacuums with `secure_delete` on so the removed text is gone from the file.
+    pub fn remediate<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.conn.pragma_update(None, "secure_delete", "ON")?;
+        let value = self.in_transaction(f)?;
+        if self.has_fts {
+            self.conn.execute(
+                "INSERT INTO memories_fts (memories_fts) VALUES ('rebuild')",
+                [],
+            )?;
+        }
+        self.scrub_free_pages()?;
+        Ok(value)
+    }
+
+    fn in_transaction<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.conn.execute_batch("BEGIN IMMEDIATE")?;
+        match f(self) {
+            Ok(value) => {
+                self.conn.execute_batch("COMMIT")?;
+                Ok(value)
+            }
+            Err(error) => {
+                let _ = self.conn.execute_batch("ROLLBACK");
+                Err(error)
+            }
+        }
+    }
+
+    fn sub_scopes(&self, scope: &str) -> Result<Vec<String>> {
+        let prefix_len = i64::try_from(scope.chars().count() + 1)?;
+        let mut stmt = self.conn.prepare(
+            "
+            SELECT scope FROM memories WHERE scope = ?1 OR substr(scope, 1, ?2) IN (?3, ?4)
+            UNION
+            SELECT scope FROM memory_compactions WHERE scope = ?1 OR substr(scope, 1, ?2) IN (?3, ?4)
+            UNION
+            SELECT scope FROM scopes WHERE scope = ?1 OR substr(scope, 1, ?2) IN (?3, ?4)
+            ",
+        )?;
+        let scopes = stmt
+            .query_map(
+                params![scope, prefix_len, format!("{scope}#"), format!("{scope}@")],
+                |row| row.get::<_, String>(0),
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(scopes)
+    }
+
+    // Moves `from` and its `#package` and `@branch` sub-scopes. Sub-scopes keep their
+    // suffix under a project target and collapse into a global or org target.
+    pub fn migrate_scope(&mut self, from: &str, to: &str, action: &str) -> Result<(usize, usize)> {
+        self.in_transaction(|store| {
+            let mut moved = 0_usize;
+            let mut deduped = 0_usize;
+            for source in store.sub_scopes(from)? {
+                let suffix = &source[from.len()..];
+                let target = if to.starts_with("project:") {
+                    format!("{to}{suffix}")
+                } else {
+                    to.to_string()
+                };
+                let (scope_moved, scope_deduped) =
+                    store.migrate_single_scope(&source, &target, action)?;
+                moved += scope_moved;
+                deduped += scope_deduped;
+                store
+                    .conn
+                    .execute("DELETE FROM scopes WHERE scope = ?", params![source])?;
+            }
+            Ok((moved, deduped))
+        })
+    }
+
+    fn migrate_single_scope(
+        &mut self,
+        from: &str,
+        to: &str,
+        action: &str,
+    ) -> Result<(usize, usize)> {
+        let rows = {
+            let mut stmt = self.conn.prepare(
+                "SELECT id, content_hash, pinned FROM memories WHERE scope = ? AND status = 'active'",
+            )?;
+            stmt.query_map(params![from], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)? == 1,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?
+        };
+
+        let mut moved = 0_usize;
+        let mut deduped = 0_usize;
+        for (id, content_hash, pinned) in rows {
+            if let Some(existing_id) = self.active_id_in_scope(to, &content_hash)? {
+                if pinned {
+                    self.conn.execute(
+                        "UPDATE memories SET pinned = 1 WHERE id = ?",
+                        params![existing_id],
+                    )?;
+                }
+                self.conn.execute(
+                    "UPDATE memories SET scope = ?, status = 'superseded', updated_at = ? WHERE id = ?",
+                    params![to, now_iso(), id],
+                )?;
+                self.remove_fts_entry(&id);
+                self.add_event(
+                    &id,
+                    action,
+                    Some(&serde_json::json!({"from": from, "to": to, "deduped_into": existing_id})),
+                );
+                deduped += 1;
+                continue;
+            }
+
+            self.conn.execute(
+                "UPDATE memories SET scope = ? WHERE id = ?",
+                params![to, id],
+            )?;
+            self.add_event(
+                &id,
+                action,
+                Some(&serde_json::json!({"from": from, "to": to})),
+            );
+            moved += 1;
+        }
+
+        self.conn.execute(
+            "UPDATE memories SET scope = ? WHERE scope = ? AND status != 'active'",
+            params![to, from],
+        )?;
+        self.conn.execute(
+            "UPDATE memory_compactions SET scope = ? WHERE scope = ?",
+            params![to, from],
+        )?;
+        if self.has_fts {
+            self.conn.execute(
+                "UPDATE memories_fts SET scope = ? WHERE scope = ?",
+                params![to, from],
+            )?;
+        }
+        Ok((moved, deduped))
+    }
+
+    pub fn load_processed_hashes(&self) -> Result<HashSet<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT hash FROM auto_capture_processed")?;
+        let hashes = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<HashSet<_>>>()?;
+        Ok(hashes)
+    }
+
+    pub fn track_processed_hash(&mut self, hash: &str, max_tracked: usize) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        let inserted = tx.execute(
+            "INSERT OR IGNORE INTO auto_capture_processed (hash, processed_at) VALUES (?, ?)",
+            params![hash, now_iso()],
+        )?;
+        if inserted > 0 {
+            tx.execute(
+                "
+                DELETE FROM auto_capture_processed
+                WHERE seq <= (
+                  SELECT seq FROM auto_capture_processed
+                  ORDER BY seq DESC
+                  LIMIT 1 OFFSET ?
+                )
+                ",
+                params![max_tracked as i64],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn backfill_file_size(&self, path: &str) -> Result<Option<u64>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT size FROM backfill_files WHERE path = ?",
+                params![path],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?
+            .map(|size| size as u64))
+    }
+
+    pub fn mark_backfill_file(&mut self, path: &str, size: u64) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO backfill_files (path, size, processed_at) VALUES (?, ?, ?)",
+            params![path, size as i64, now_iso()],
+        )?;
+        Ok(())
+    }
+
     pub fn prune_old_events(&mut self, retention_days: u64) -> Result<usize> {
         let cutoff = Utc::now() - chrono::Duration::days(retention_days as i64);
         let changes = self.conn.execute(
@@ -774,7 +2236,7 @@ impl MemoryStore {
                 source_count as i64,
                 model,
                 reason,
-                serde_json::to_string(&details).ok(),
+                serde_json::to_string(&details).ok().map(|text| self.seal(&text)),
                 now_iso(),
             ],
         );
@@ -1,4 +1,5 @@
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 use std::str::FromStr;
@@ -71,10 +72,13 @@ impl FromStr for MemoryCategory {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ScopeTarget {
+    Branch,
+    Package,
     Project,
+    Org,
     Global,
 }
 
@@ -82,7 +86,10 @@ impl ScopeTarget {
     #[must_use]
     pub fn as_str(self) -> &'static str {
         match self {
+            Self::Branch => "branch",
+            Self::Package => "package",
             Self::Project => "project",
+            Self::Org => "org",
             Self::Global => "global",
         }
     }
@@ -93,9 +100,14 @@ impl FromStr for ScopeTarget {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.trim().to_lowercase().as_str() {
+            "branch" => Ok(Self::Branch),
+            "package" => Ok(Self::Package),
             "project" => Ok(Self::Project),
+            "org" => Ok(Self::Org),
             "global" => Ok(Self::Global),
-            _ => Err("Scope must be 'project' or 'global'".to_string()),
+            _ => {
+                Err("Scope must be 'branch', 'package', 'project', 'org', or 'global'".to_string())
+            }
         }
     }
 }
@@ -114,6 +126,15 @@ pub struct MemoryRow {
     pub updated_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryEventRow {
+    pub id: i64,
+    pub memory_id: String,
+    pub action: String,
+    pub timestamp: String,
+    pub payload: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AddMemoryInput {
     pub scope: String,
@@ -130,16 +151,89 @@ pub enum AddMemoryResult {
         scope: String,
         category: MemoryCategory,
         content: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        redacted: Vec<String>,
     },
     Deduped {
         id: String,
         scope: String,
         category: MemoryCategory,
         content: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        redacted: Vec<String>,
+    },
+    Blocked {
+        reason: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        detectors: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "lowercase")]
+pub enum TransferOutcome {
+    Moved {
+        id: String,
+        from: String,
+        to: String,
+    },
+    Copied {
+        id: String,
+        source_id: String,
+        from: String,
+        to: String,
+    },
+    Deduped {
+        id: String,
+        source_id: String,
+        from: String,
+        to: String,
+    },
+    Unchanged {
+        id: String,
+        scope: String,
+    },
+    Missing {
+        id: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "lowercase")]
+pub enum ReviewOutcome {
+    Approved {
+        id: String,
+    },
+    Deduped {
+        id: String,
+        existing_id: String,
+    },
+    Rejected {
+        id: String,
+    },
+    Edited {
+        id: String,
+        content: String,
+    },
+    Recategorized {
+        id: String,
+        category: MemoryCategory,
+    },
+    Retracted {
+        id: String,
+        memory_id: String,
+    },
+    Superseded {
+        id: String,
+        superseded_id: String,
     },
     Blocked {
+        id: String,
         reason: String,
     },
+    Missing {
+        id: String,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -150,21 +244,124 @@ pub enum ResolveIdResult {
     Ambiguous { candidates: Vec<String> },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoCaptureLlmConfig {
+    pub enabled: bool,
+    pub model: String,
+    pub timeout_ms: u64,
+    pub max_input_tokens: usize,
+    pub min_confidence: f64,
+}
+
+impl Default for AutoCaptureLlmConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model: "gpt-5-mini".to_string(),
+            timeout_ms: 8_000,
+            max_input_tokens: 2_000,
+            min_confidence: 0.6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoCaptureRule {
+    pub pattern: String,
+    pub role: String,
+    pub category: Option<String>,
+    pub scope: Option<String>,
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct AutoCaptureConfig {
     pub enabled: bool,
+    pub review: bool,
     pub scope: ScopeTarget,
     pub max_per_turn: usize,
     pub min_chars: usize,
     pub max_chars: usize,
+    pub llm: AutoCaptureLlmConfig,
+    pub rules: Vec<AutoCaptureRule>,
+    pub languages: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ScopeInfo {
     pub scope: String,
     pub kind: String,
     pub identifier: String,
     pub root: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch_scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_root: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretPolicy {
+    #[default]
+    Reject,
+    Redact,
+}
+
+impl SecretPolicy {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Reject => "reject",
+            Self::Redact => "redact",
+        }
+    }
+}
+
+impl FromStr for SecretPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "reject" => Ok(Self::Reject),
+            "redact" => Ok(Self::Redact),
+            other => Err(format!(
+                "Invalid secrets policy '{other}'. Allowed: reject, redact"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureAction {
+    #[default]
+    Add,
+    Retract,
+    Replace,
+}
+
+impl CaptureAction {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Add => "add",
+            Self::Retract => "retract",
+            Self::Replace => "replace",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -173,15 +370,50 @@ pub struct AutoCaptureCandidate {
     pub text: String,
     pub category: MemoryCategory,
     pub reason: String,
+    pub scope: Option<ScopeTarget>,
+    pub tag: Option<String>,
+    pub action: CaptureAction,
+    pub target: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryStats {
     pub active: i64,
+    pub pending: i64,
     pub pinned: i64,
     pub global: i64,
+    #[serde(default)]
+    pub org: i64,
     pub project: i64,
+    pub processed_hashes: i64,
     pub has_fts: bool,
+    pub encrypted: bool,
+    #[serde(default)]
+    pub by_scope: Vec<ScopeEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope_detection: Option<ScopeDetectionStats>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScopeDetectionStats {
+    pub lookups: u64,
+    pub cache_hits: u64,
+    pub last_detect_ms: f64,
+    pub avg_detect_ms: f64,
+    pub avg_cached_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScopeEntry {
+    pub scope: String,
+    pub label: String,
+    pub kind: Option<String>,
+    pub identifier: Option<String>,
+    pub root: Option<String>,
+    pub first_seen: Option<String>,
+    pub last_seen: Option<String>,
+    pub active: i64,
+    pub pending: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -192,7 +424,7 @@ pub struct PagedResult<T> {
     pub offset: usize,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum CompactionMode {
     None,
@@ -221,3 +453,23 @@ pub struct SyncAgentsResult {
     pub selected_memories: usize,
     pub compaction: CompactionResult,
 }
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncJobState {
+    Pending,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncJobStatus {
+    pub job_id: u64,
+    pub state: SyncJobState,
+    pub agents_path: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub mode: CompactionMode,
+    pub model: Option<String>,
+    pub reason: Option<String>,
+}
@@ -1,8 +1,7 @@
 use chrono::{DateTime, Utc};
-use regex::Regex;
 use serde_json::Value;
 use sha2::{Digest, Sha256};
-use std::sync::OnceLock;
+use std::collections::HashMap;
 
 #[must_use]
 pub fn now_utc() -> DateTime<Utc> {
@@ -94,73 +93,42 @@ pub fn parse_boolean(input: Option<Value>, fallback: bool) -> bool {
 
 #[must_use]
 pub fn format_memory_scope(scope: &str, project_scope: &str) -> String {
-    if scope == "global" {
-        "global".to_string()
-    } else if scope == project_scope || scope.starts_with("project:") {
-        "project".to_string()
-    } else {
-        scope.to_string()
+    if scope == "global" || scope.starts_with("org:") {
+        return scope.to_string();
     }
-}
-
-fn secret_patterns() -> &'static [Regex] {
-    static PATTERNS: OnceLock<Vec<Regex>> = OnceLock::new();
-    PATTERNS.get_or_init(|| {
-        [
-            r"\bsk-[A-Za-z0-9]{16,}\b",
-            r"\bghp_[A-Za-z0-9]{20,}\b",
-            r"\bAKIA[0-9A-Z]{16}\b",
-            r"\bAIza[0-9A-Za-z_-]{20,}\b",
-            r"\b(xox[pbar]-[A-Za-z0-9-]{10,})\b",
-            r"\bkey_live_[A-Za-z0-9]{16,}\b",
-            r"-----BEGIN (RSA|EC|OPENSSH|PGP) PRIVATE KEY-----",
-            r"\bBearer\s+[A-Za-z0-9._-]{20,}\b",
-            r#"\b(?:api[_-]?key|token|secret|password)\b\s*[:=]\s*['"]?[A-Za-z0-9._\-/=+]{12,}"#,
-            r"\b(?:postgres(?:ql)?|mysql|mongodb(?:\+srv)?|redis):\/\/[^\s]+",
-        ]
-        .iter()
-        .map(|pattern| Regex::new(pattern).expect("valid secret regex"))
-        .collect()
-    })
-}
-
-fn has_high_entropy_token(value: &str) -> bool {
-    value.split_whitespace().any(|token| {
-        if token.len() < 32 {
-            return false;
-        }
-        let alpha = token.chars().any(char::is_alphabetic);
-        let digit = token.chars().any(char::is_numeric);
-        let special = token
-            .chars()
-            .any(|c| !c.is_alphanumeric() && c != '-' && c != '_');
-        alpha && digit && special
-    })
-}
-
-#[must_use]
-pub fn is_probably_secret(value: &str) -> bool {
-    if secret_patterns()
-        .iter()
-        .any(|pattern| pattern.is_match(value))
-    {
-        return true;
+    let Some(rest) = scope.strip_prefix("project:") else {
+        return if scope == project_scope {
+            "project".to_string()
+        } else {
+            scope.to_string()
+        };
+    };
+    let hash_len = rest
+        .find(|c: char| !c.is_ascii_hexdigit())
+        .unwrap_or(rest.len());
+    match rest[hash_len..].split_at_checked(1) {
+        Some(("#", package)) => format!("package:{package}"),
+        Some(("@", branch)) => format!("branch:{branch}"),
+        _ => "project".to_string(),
     }
-    has_high_entropy_token(value)
 }
 
-pub fn sanitize_memory_text(value: &str) -> Result<String, String> {
-    let text = normalize_content_for_storage(value);
-    if text.is_empty() {
-        return Err("Memory text cannot be empty.".to_string());
-    }
-    if text.chars().count() > 1_200 {
-        return Err("Memory text is too long (max 1200 characters).".to_string());
-    }
-    if is_probably_secret(&text) {
-        return Err("Memory looks like a secret/token. Refusing to store it.".to_string());
+#[must_use]
+pub fn label_memory_scope(
+    scope: &str,
+    project_scope: &str,
+    labels: &HashMap<String, String>,
+) -> String {
+    let own_package = scope
+        .strip_prefix(project_scope)
+        .is_some_and(|rest| rest.starts_with('#') || rest.starts_with('@'));
+    if scope == "global" || scope.starts_with("org:") || scope == project_scope || own_package {
+        return format_memory_scope(scope, project_scope);
     }
-    Ok(text)
+    labels.get(scope).map_or_else(
+        || format_memory_scope(scope, project_scope),
+        |label| format!("project:{label}"),
+    )
 }
 
 #[must_use]
@@ -1,6 +1,7 @@
-use codex_extra_memory_core::MemoryService;
 use codex_extra_memory_core::commands::{ExportFormat, parse_add_args, parse_export_args};
+use codex_extra_memory_core::store::MemoryStore;
 use codex_extra_memory_core::types::ScopeTarget;
+use codex_extra_memory_core::{AddMemoryInput, AddMemoryResult, MemoryCategory, MemoryService};
 use serde_json::{Value, json};
 use std::fs;
 
@@ -64,7 +65,7 @@ fn add_dedupe_and_search_work() {
     );
 
     let search = service
-        .search_memories(&workspace, "pnpm".to_string(), Some(10), None)
+        .search_memories(&workspace, "pnpm".to_string(), Some(10), None, false)
         .expect("search");
     let items = data(&search)
         .get("page")
@@ -74,6 +75,932 @@ fn add_dedupe_and_search_work() {
     assert_eq!(items.len(), 1);
 }
 
+#[test]
+fn package_scope_layers_under_project_and_nearest_wins() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    let package_dir = workspace.join("crates/api");
+    fs::create_dir_all(package_dir.join("src")).expect("create package");
+    fs::write(
+        workspace.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"crates/*\"]\n",
+    )
+    .expect("workspace manifest");
+    fs::write(
+        package_dir.join("Cargo.toml"),
+        "[package]\nname = \"api\"\n",
+    )
+    .expect("package manifest");
+    let status = std::process::Command::new("git")
+        .arg("init")
+        .arg("-q")
+        .arg(&workspace)
+        .status()
+        .expect("git init");
+    assert!(status.success());
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    let at_root = service
+        .execute_command("/memory add --package Use axum for HTTP", &workspace)
+        .expect("add at root");
+    assert_eq!(at_root.get("ok").and_then(Value::as_bool), Some(false));
+
+    service
+        .execute_command("/memory add Use axum for HTTP", &workspace)
+        .expect("add project");
+    service
+        .execute_command("/memory add Run cargo nextest", &workspace)
+        .expect("add project only");
+    let added = service
+        .execute_command(
+            "/memory add --package Use axum for HTTP",
+            &package_dir.join("src"),
+        )
+        .expect("add package");
+    let package_scope = data(&added)
+        .get("scope")
+        .and_then(Value::as_str)
+        .expect("scope")
+        .to_string();
+    assert!(package_scope.starts_with("project:"));
+    assert!(package_scope.ends_with("#api"));
+    assert_eq!(
+        data(&added).get("scope_label").and_then(Value::as_str),
+        Some("package:api")
+    );
+
+    let listed = service
+        .list_memories(&package_dir, Some(20), None, false)
+        .expect("list package");
+    let scopes = data(&listed)["page"]["items"]
+        .as_array()
+        .expect("items")
+        .iter()
+        .map(|item| item["scope"].as_str().expect("scope").to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(scopes.len(), 2);
+    assert!(scopes.contains(&package_scope));
+
+    let preview = service
+        .show_injection_preview(&package_dir)
+        .expect("preview");
+    let block = data(&preview)["block"].as_str().expect("block");
+    assert!(block.contains("[package:api/other] Use axum for HTTP"));
+    assert!(block.contains("[project/other] Run cargo nextest"));
+    assert!(!block.contains("[project/other] Use axum for HTTP"));
+
+    let root_list = service
+        .list_memories(&workspace, Some(20), None, false)
+        .expect("list root");
+    assert_eq!(
+        data(&root_list)["page"]["items"]
+            .as_array()
+            .expect("items")
+            .len(),
+        2
+    );
+}
+
+#[test]
+fn scope_marker_keeps_old_memories_visible_until_migrated() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = git_workspace(
+        temp.path(),
+        "workspace",
+        "git@github.com:old-org/platform.git",
+    );
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    service
+        .execute_command(
+            "/memory add Deploys go through the platform pipeline",
+            &workspace,
+        )
+        .expect("add old");
+    service
+        .execute_command("/memory add Use pnpm workspaces", &workspace)
+        .expect("add old duplicate");
+    let before = service
+        .execute_command("/memory scope", &workspace)
+        .expect("scope");
+    let old_scope = data(&before)["scope"]["scope"]
+        .as_str()
+        .expect("old scope")
+        .to_string();
+
+    fs::write(
+        workspace.join(".codex-memory.toml"),
+        "scope_id = \"acme-platform\"\n",
+    )
+    .expect("marker");
+    let after = service
+        .execute_command("/memory scope", &workspace)
+        .expect("scope");
+    assert_eq!(data(&after)["scope"]["kind"].as_str(), Some("config"));
+    assert_eq!(data(&after)["scope"]["aliases"], json!([old_scope]));
+    let new_scope = data(&after)["scope"]["scope"]
+        .as_str()
+        .expect("new scope")
+        .to_string();
+    assert_ne!(new_scope, old_scope);
+
+    let aliased = service
+        .search_memories(&workspace, "pipeline".to_string(), Some(10), None, false)
+        .expect("search via alias");
+    assert_eq!(
+        data(&aliased)["page"]["items"][0]["scope"],
+        json!(old_scope)
+    );
+
+    service
+        .execute_command("/memory add Use pnpm workspaces", &workspace)
+        .expect("add new duplicate");
+    let migrated = service
+        .execute_command(
+            &format!("/memory scope migrate {old_scope} current"),
+            &workspace,
+        )
+        .expect("migrate");
+    assert_eq!(data(&migrated)["moved"], json!(1));
+    assert_eq!(data(&migrated)["deduped"], json!(1));
+
+    let search = service
+        .search_memories(&workspace, "pipeline".to_string(), Some(10), None, false)
+        .expect("search after migrate");
+    assert_eq!(data(&search)["page"]["items"][0]["scope"], json!(new_scope));
+    let listed = service
+        .list_memories(&workspace, Some(10), None, false)
+        .expect("list after migrate");
+    let items = data(&listed)["page"]["items"].as_array().expect("items");
+    assert_eq!(items.len(), 2);
+    assert!(items.iter().all(|item| item["scope"] == json!(new_scope)));
+
+    let same = service
+        .execute_command(
+            &format!("/memory scope migrate {new_scope} current"),
+            &workspace,
+        )
+        .expect("noop migrate");
+    assert_eq!(same.get("ok").and_then(Value::as_bool), Some(false));
+}
+
+#[test]
+fn scope_migrate_rejects_unknown_targets_and_carries_sub_scopes() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    let other = temp.path().join("other");
+    fs::create_dir_all(&workspace).expect("create workspace");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    let shown = service
+        .execute_command("/memory scope", &workspace)
+        .expect("scope");
+    let project = data(&shown)["scope"]["scope"]
+        .as_str()
+        .expect("scope")
+        .to_string();
+    let mut store = MemoryStore::open(&memory_dir.join("memory.sqlite")).expect("store");
+    for (scope, content) in [
+        (project.clone(), "Project wide fact"),
+        (format!("{project}#api"), "Package fact"),
+        (format!("{project}@feature"), "Branch fact"),
+    ] {
+        store
+            .add_memory(AddMemoryInput {
+                scope,
+                category: MemoryCategory::Fact,
+                content: content.to_string(),
+                source: "user".to_string(),
+            })
+            .expect("add");
+    }
+
+    for target in ["myrepo", "project:does-not-exist"] {
+        let rejected = service
+            .execute_command(
+                &format!("/memory scope migrate current {target}"),
+                &workspace,
+            )
+            .expect("rejected migrate");
+        assert_eq!(rejected["ok"], json!(false), "{target}");
+    }
+
+    let migrated = service
+        .execute_command(
+            &format!("/memory scope migrate current {}", other.display()),
+            &workspace,
+        )
+        .expect("migrate");
+    assert_eq!(data(&migrated)["moved"], json!(3));
+    let target = data(&migrated)["to"].as_str().expect("to").to_string();
+    let scopes = store
+        .list_scopes(None)
+        .expect("scopes")
+        .into_iter()
+        .filter(|entry| entry.active > 0)
+        .map(|entry| entry.scope)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        scopes,
+        vec![
+            target.clone(),
+            format!("{target}#api"),
+            format!("{target}@feature")
+        ]
+    );
+
+    let to_global = service
+        .execute_command(
+            &format!("/memory scope migrate {target} global"),
+            &workspace,
+        )
+        .expect("migrate to global");
+    assert_eq!(data(&to_global)["moved"], json!(3));
+    assert_eq!(data(&to_global)["to"], json!("global"));
+}
+
+fn git_workspace(root: &std::path::Path, name: &str, remote: &str) -> std::path::PathBuf {
+    let workspace = root.join(name);
+    fs::create_dir_all(&workspace).expect("create workspace");
+    git(&workspace, &["init", "-q"]);
+    git(&workspace, &["remote", "add", "origin", remote]);
+    workspace
+}
+
+#[test]
+fn scope_registry_labels_scopes_in_listing_export_and_stats() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let api = git_workspace(temp.path(), "api", "git@github.com:acme/api.git");
+    let web = git_workspace(temp.path(), "web", "https://github.com/acme/web.git");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    service
+        .execute_command("/memory add API uses axum", &api)
+        .expect("add api");
+    service
+        .execute_command("/memory add Web uses vite", &web)
+        .expect("add web");
+    service
+        .execute_command("/memory add --global Prefer small commits", &web)
+        .expect("add global");
+
+    let scopes = service
+        .execute_command("/memory scopes", &api)
+        .expect("scopes");
+    let entries = data(&scopes)["scopes"].as_array().expect("scope entries");
+    let labels = entries
+        .iter()
+        .map(|entry| {
+            (
+                entry["label"].as_str().expect("label").to_string(),
+                entry["active"].as_i64().expect("active"),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert!(labels.contains(&("github.com/acme/api".to_string(), 1)));
+    assert!(labels.contains(&("github.com/acme/web".to_string(), 1)));
+    assert!(labels.contains(&("global".to_string(), 1)));
+    let rendered = data(&scopes)["rendered"].as_str().expect("rendered");
+    assert!(rendered.contains("- github.com/acme/api [current]: 1 active, 0 pending"));
+    let web_entry = entries
+        .iter()
+        .find(|entry| entry["label"] == json!("github.com/acme/web"))
+        .expect("web entry");
+    assert_eq!(web_entry["kind"], json!("git"));
+    assert!(web_entry["first_seen"].is_string());
+
+    service
+        .execute_command("/memory export --all md memory.md", &api)
+        .expect("export");
+    let markdown = fs::read_to_string(api.join("memory.md")).expect("read export");
+    assert!(markdown.contains("## github.com/acme/web (project:"));
+    assert!(markdown.contains("## global"));
+
+    let stats = service.stats(&api).expect("stats");
+    let rendered = data(&stats)["rendered"].as_str().expect("rendered");
+    assert!(rendered.contains("- Scope github.com/acme/api: 1 active, 0 pending"));
+    assert!(!rendered.contains("acme/web"));
+}
+
+#[test]
+fn scope_registry_is_sealed_when_encrypted_and_written_only_on_change() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let api = git_workspace(temp.path(), "api", "git@github.com:acme/api.git");
+    fs::create_dir_all(&memory_dir).expect("create memory dir");
+    fs::write(memory_dir.join("memory.key"), "07".repeat(32)).expect("key file");
+    fs::write(
+        memory_dir.join("config.json"),
+        r#"{"encryption": {"keyFile": "memory.key"}}"#,
+    )
+    .expect("config");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    service
+        .execute_command("/memory add API uses axum", &api)
+        .expect("add");
+    service
+        .execute_command("/memory encrypt", &api)
+        .expect("encrypt");
+
+    let conn = rusqlite::Connection::open(memory_dir.join("memory.sqlite")).expect("open db");
+    let registry = |conn: &rusqlite::Connection| -> Vec<(String, String, String, String)> {
+        let mut stmt = conn
+            .prepare("SELECT identifier, root, label, last_seen FROM scopes")
+            .expect("prepare");
+        stmt.query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })
+        .expect("query")
+        .collect::<rusqlite::Result<_>>()
+        .expect("rows")
+    };
+    let rows = registry(&conn);
+    assert!(!rows.is_empty());
+    for (identifier, root, label, _) in &rows {
+        for value in [identifier, root, label] {
+            assert!(
+                value.starts_with("enc:v1:"),
+                "plaintext registry value {value}"
+            );
+        }
+    }
+
+    conn.execute("UPDATE scopes SET last_seen = 'marker'", [])
+        .expect("mark rows");
+    let scopes = service
+        .execute_command("/memory scopes", &api)
+        .expect("scopes");
+    let rendered = data(&scopes)["rendered"].as_str().expect("rendered");
+    assert!(rendered.contains("- github.com/acme/api [current]: 1 active, 0 pending"));
+    assert!(
+        registry(&conn)
+            .iter()
+            .all(|(_, _, _, last_seen)| last_seen == "marker")
+    );
+
+    service
+        .execute_command("/memory decrypt", &api)
+        .expect("decrypt");
+    assert!(
+        registry(&conn)
+            .iter()
+            .any(|(_, _, label, _)| label == "github.com/acme/api")
+    );
+}
+
+#[test]
+fn org_scope_is_shared_by_repos_of_the_same_owner() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let api = git_workspace(temp.path(), "api", "git@github.com:acme/api.git");
+    let web = git_workspace(temp.path(), "web", "https://github.com/acme/web.git");
+    let other = git_workspace(temp.path(), "other", "git@github.com:globex/tool.git");
+    let plain = temp.path().join("plain");
+    fs::create_dir_all(&plain).expect("plain dir");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    let added = service
+        .execute_command("/memory add --org Sign commits with the acme key", &api)
+        .expect("add org");
+    assert_eq!(data(&added)["scope"], json!("org:github.com/acme"));
+    let no_org = service
+        .execute_command("/memory add --org Not possible here", &plain)
+        .expect("add without remote");
+    assert_eq!(no_org.get("ok").and_then(Value::as_bool), Some(false));
+
+    let preview = service.show_injection_preview(&web).expect("web preview");
+    let block = data(&preview)["block"].as_str().expect("block");
+    assert!(block.contains("[org:github.com/acme/other] Sign commits with the acme key"));
+    let foreign = service
+        .show_injection_preview(&other)
+        .expect("other preview");
+    assert_eq!(data(&foreign)["block"], json!(""));
+
+    let stats = service.stats(&web).expect("stats");
+    assert_eq!(data(&stats)["stats"]["org"], json!(1));
+    assert!(
+        data(&stats)["rendered"]
+            .as_str()
+            .expect("rendered")
+            .contains("- Org scope: 1")
+    );
+}
+
+fn git(workspace: &std::path::Path, args: &[&str]) {
+    let status = std::process::Command::new("git")
+        .arg("-C")
+        .arg(workspace)
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .status()
+        .expect("git");
+    assert!(status.success());
+}
+
+#[test]
+fn branch_scope_follows_checkout_and_promotes_after_merge() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let main = temp.path().join("main");
+    let worktree = temp.path().join("feature-wt");
+    fs::create_dir_all(&main).expect("create repo");
+    git(&main, &["init", "-q", "-b", "main"]);
+    git(&main, &["commit", "-q", "--allow-empty", "-m", "init"]);
+    git(
+        &main,
+        &[
+            "worktree",
+            "add",
+            "-q",
+            "-b",
+            "feature/axum",
+            worktree.to_str().expect("utf8"),
+        ],
+    );
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    let main_scope = service
+        .execute_command("/memory scope", &main)
+        .expect("main scope");
+    let wt_scope = service
+        .execute_command("/memory scope", &worktree)
+        .expect("worktree scope");
+    assert_eq!(
+        data(&main_scope)["scope"]["scope"],
+        data(&wt_scope)["scope"]["scope"]
+    );
+    assert_eq!(data(&wt_scope)["scope"]["branch"], json!("feature/axum"));
+
+    let added = service
+        .execute_command("/memory add --branch We are migrating to axum", &worktree)
+        .expect("add branch");
+    assert_eq!(data(&added)["scope_label"], json!("branch:feature/axum"));
+
+    let on_branch = service.show_injection_preview(&worktree).expect("preview");
+    assert!(
+        data(&on_branch)["block"]
+            .as_str()
+            .expect("block")
+            .contains("[branch:feature/axum/other] We are migrating to axum")
+    );
+    let on_main = service.show_injection_preview(&main).expect("preview");
+    assert_eq!(data(&on_main)["block"], json!(""));
+
+    let promoted = service
+        .execute_command("/memory branch promote feature/axum", &main)
+        .expect("promote");
+    assert_eq!(data(&promoted)["moved"], json!(1));
+    let after = service.show_injection_preview(&main).expect("preview");
+    assert!(
+        data(&after)["block"]
+            .as_str()
+            .expect("block")
+            .contains("[project/other] We are migrating to axum")
+    );
+}
+
+#[test]
+fn move_and_copy_keep_identity_and_dedupe_on_collision() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let api = git_workspace(temp.path(), "api", "git@github.com:acme/api.git");
+    let web = git_workspace(temp.path(), "web", "git@github.com:acme/web.git");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    let added = service
+        .execute_command("/memory add Prefer conventional commits", &api)
+        .expect("add");
+    let id = data(&added)["id"].as_str().expect("id").to_string();
+    service
+        .execute_command(&format!("/memory pin {id} on"), &api)
+        .expect("pin");
+
+    let copied = service
+        .execute_command(
+            &format!("/memory copy {id} --to git@github.com:acme/web.git"),
+            &api,
+        )
+        .expect("copy");
+    assert_eq!(data(&copied)["result"], json!("copied"));
+    assert_eq!(data(&copied)["source_id"], json!(id));
+    let copy_id = data(&copied)["id"].as_str().expect("copy id").to_string();
+    assert_ne!(copy_id, id);
+    let web_search = service
+        .search_memories(&web, "conventional".to_string(), Some(10), None, false)
+        .expect("web search");
+    assert_eq!(data(&web_search)["page"]["items"][0]["id"], json!(copy_id));
+    assert_eq!(data(&web_search)["page"]["items"][0]["pinned"], json!(true));
+
+    let moved = service
+        .execute_command(&format!("/memory move {id} --global"), &api)
+        .expect("move");
+    assert_eq!(data(&moved)["result"], json!("moved"));
+    assert_eq!(data(&moved)["id"], json!(id));
+    assert_eq!(data(&moved)["to"], json!("global"));
+    let global_search = service
+        .search_memories(&api, "conventional".to_string(), Some(10), None, false)
+        .expect("search after move");
+    let items = data(&global_search)["page"]["items"]
+        .as_array()
+        .expect("items");
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["scope"], json!("global"));
+    assert_eq!(items[0]["pinned"], json!(true));
+
+    let collided = service
+        .execute_command(&format!("/memory move {copy_id} --global"), &web)
+        .expect("move collision");
+    assert_eq!(data(&collided)["result"], json!("deduped"));
+    assert_eq!(data(&collided)["id"], json!(id));
+    let web_list = service
+        .list_memories(&web, Some(10), None, false)
+        .expect("web list");
+    let items = data(&web_list)["page"]["items"].as_array().expect("items");
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["id"], json!(id));
+
+    for command in [
+        format!("/memory copy {id} --to myrepo"),
+        format!("/memory move {id} --scope projcet:typo"),
+    ] {
+        let rejected = service
+            .execute_command(&command, &api)
+            .expect("unknown target");
+        assert_eq!(rejected["ok"], json!(false), "{command}");
+        assert!(
+            rejected["error"]
+                .as_str()
+                .unwrap_or_default()
+                .contains("Unknown scope")
+        );
+    }
+
+    drop(service);
+    let store = MemoryStore::open(&memory_dir.join("memory.sqlite")).expect("open store");
+    let actions = store
+        .audit_events()
+        .expect("events")
+        .into_iter()
+        .filter(|event| event.memory_id == id || event.memory_id == copy_id)
+        .map(|event| event.action)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        actions.iter().filter(|action| *action == "copied").count(),
+        2
+    );
+    assert_eq!(
+        actions.iter().filter(|action| *action == "moved").count(),
+        2
+    );
+}
+
+#[test]
+fn scope_detectors_follow_configured_order_and_keep_hashes_stable() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let workspace = temp.path().join("repo");
+    fs::create_dir_all(&workspace).expect("create repo");
+    git(&workspace, &["init", "-q"]);
+
+    let scope_with = |name: &str, detectors: Value| {
+        let memory_dir = temp.path().join(name);
+        fs::create_dir_all(&memory_dir).expect("memory dir");
+        fs::write(
+            memory_dir.join("config.json"),
+            json!({"scope": {"detectors": detectors}}).to_string(),
+        )
+        .expect("config");
+        let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+        let shown = service
+            .execute_command("/memory scope", &workspace)
+            .expect("scope");
+        data(&shown)["scope"].clone()
+    };
+
+    let git_scope = scope_with("default", json!(["git", "jj", "hg"]));
+    assert_eq!(git_scope["kind"], json!("git"));
+    let hg_only = scope_with("hg-only", json!(["hg"]));
+    assert_eq!(hg_only["kind"], json!("path"));
+    assert_eq!(git_scope["scope"], hg_only["scope"]);
+    let unknown = scope_with("unknown", json!(["svn", "git"]));
+    assert_eq!(unknown["kind"], json!("git"));
+}
+
+#[test]
+fn scope_cache_is_invalidated_by_head_and_config_changes() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = git_workspace(temp.path(), "repo", "git@github.com:acme/api.git");
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    let scope = |service: &mut MemoryService| {
+        let shown = service
+            .execute_command("/memory scope", &workspace)
+            .expect("scope");
+        data(&shown)["scope"].clone()
+    };
+
+    let first = scope(&mut service);
+    let cached = scope(&mut service);
+    assert_eq!(first, cached);
+
+    git(&workspace, &["checkout", "-q", "-b", "topic"]);
+    let switched = scope(&mut service);
+    assert_eq!(switched["branch"], json!("topic"));
+    assert_eq!(switched["scope"], first["scope"]);
+
+    git(
+        &workspace,
+        &["remote", "set-url", "origin", "git@github.com:acme/web.git"],
+    );
+    let renamed = scope(&mut service);
+    assert_eq!(renamed["identifier"], json!("https://github.com/acme/web"));
+    assert_ne!(renamed["scope"], first["scope"]);
+
+    let stats = service.stats(&workspace).expect("stats");
+    let timing = &data(&stats)["stats"]["scope_detection"];
+    assert!(timing["cache_hits"].as_u64().expect("cache hits") >= 1);
+    assert!(timing["lookups"].as_u64().expect("lookups") >= 5);
+    assert!(
+        data(&stats)["rendered"]
+            .as_str()
+            .expect("rendered")
+            .contains("- Scope detection: ")
+    );
+}
+
+#[test]
+fn repo_config_overrides_global_toml_for_that_repo_only() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    fs::create_dir_all(&memory_dir).expect("memory dir");
+    fs::write(
+        memory_dir.join("config.toml"),
+        "listLimit = 30\n\n[injection]\nmaxItems = 6\n",
+    )
+    .expect("global config");
+    let api = git_workspace(temp.path(), "api", "git@github.com:acme/api.git");
+    let web = git_workspace(temp.path(), "web", "git@github.com:acme/web.git");
+    fs::write(
+        api.join(".codex-memory.toml"),
+        "[injection]\nmaxItems = 1\n",
+    )
+    .expect("repo config");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    for fact in ["API uses axum", "API deploys on Fridays"] {
+        service
+            .execute_command(&format!("/memory add {fact}"), &api)
+            .expect("add");
+    }
+    let preview = service.show_injection_preview(&api).expect("preview");
+    let block = data(&preview)["block"].as_str().expect("block");
+    assert_eq!(block.matches("[project/").count(), 1);
+
+    let shown = service
+        .execute_command("/memory config show --origin", &api)
+        .expect("config show");
+    assert_eq!(data(&shown)["config"]["injection"]["maxItems"], json!(1));
+    let rendered = data(&shown)["rendered"].as_str().expect("rendered");
+    assert!(rendered.contains("- injection.maxItems = 1 (repo: "));
+    assert!(rendered.contains("- listLimit = 30 (global: "));
+    assert!(rendered.contains("- searchLimit = 20 (default)"));
+
+    let other = service
+        .execute_command("/memory config show", &web)
+        .expect("config show web");
+    assert_eq!(data(&other)["config"]["injection"]["maxItems"], json!(6));
+    assert!(data(&other).get("origins").is_none());
+
+    service
+        .execute_command("/memory auto off", &api)
+        .expect("auto off");
+    let saved = fs::read_to_string(memory_dir.join("config.toml")).expect("read toml");
+    assert!(saved.contains("maxItems = 6"));
+    assert!(saved.contains("enabled = false"));
+    assert!(!memory_dir.join("config.json").exists());
+}
+
+#[test]
+fn config_set_get_unset_validate_and_persist_to_the_global_file() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let api = git_workspace(temp.path(), "api", "git@github.com:acme/api.git");
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+
+    let set = service
+        .execute_command("/memory config set injection.maxItems 3", &api)
+        .expect("set");
+    assert_eq!(data(&set)["effective"], json!(3));
+    let saved: Value = serde_json::from_str(
+        &fs::read_to_string(memory_dir.join("config.json")).expect("read config"),
+    )
+    .expect("saved json");
+    assert_eq!(saved["injection"]["maxItems"], json!(3));
+
+    let got = service
+        .execute_command("/memory config get injection", &api)
+        .expect("get");
+    assert_eq!(data(&got)["value"]["maxItems"], json!(3));
+    assert!(
+        data(&got)["rendered"]
+            .as_str()
+            .expect("rendered")
+            .contains("- injection.maxItems = 3 (global: ")
+    );
+
+    let rejected = service
+        .execute_command("/memory config set injection.maxItems none", &api)
+        .expect("invalid set");
+    assert_eq!(rejected.get("ok").and_then(Value::as_bool), Some(false));
+    assert!(
+        rejected["error"]
+            .as_str()
+            .expect("error")
+            .contains("injection.maxItems")
+    );
+    let unknown = service
+        .execute_command("/memory config get injection.maxItemz", &api)
+        .expect("unknown key");
+    assert_eq!(unknown.get("ok").and_then(Value::as_bool), Some(false));
+
+    fs::write(
+        api.join(".codex-memory.toml"),
+        "[injection]\nmaxItems = 1\n",
+    )
+    .expect("repo config");
+    let shadowed = service
+        .memory_config_typed(
+            &api,
+            Some("set".to_string()),
+            Some("injection.maxItems".to_string()),
+            Some("4".to_string()),
+            None,
+            None,
+        )
+        .expect("typed set");
+    assert_eq!(data(&shadowed)["effective"], json!(1));
+    assert!(
+        data(&shadowed)["overridden_by"]
+            .as_str()
+            .expect("overridden")
+            .starts_with("repo: ")
+    );
+
+    let unset = service
+        .execute_command("/memory config unset injection.maxItems", &api)
+        .expect("unset");
+    assert_eq!(data(&unset)["removed"], json!(true));
+    fs::remove_file(api.join(".codex-memory.toml")).expect("remove repo config");
+    let reset = service
+        .execute_command("/memory config get injection.maxItems", &api)
+        .expect("get after unset");
+    assert_eq!(data(&reset)["value"], json!(10));
+
+    let schema = service
+        .execute_command("/memory config schema", &api)
+        .expect("schema");
+    assert_eq!(
+        data(&schema)["schema"]["properties"]["injection"]["$ref"],
+        json!("#/$defs/InjectionConfig")
+    );
+}
+
+#[test]
+fn config_edits_from_other_processes_are_reloaded_and_not_clobbered() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    fs::create_dir_all(&workspace).expect("workspace");
+    let mut server = MemoryService::new_with_memory_dir(&memory_dir).expect("server");
+    let mut cli = MemoryService::new_with_memory_dir(&memory_dir).expect("cli");
+    assert!(!server.reload_config_if_changed().expect("no change"));
+
+    cli.execute_command("/memory auto off", &workspace)
+        .expect("auto off");
+    assert!(server.reload_config_if_changed().expect("reload"));
+    assert!(!server.config().auto_capture.enabled);
+
+    server
+        .execute_command("/memory config set listLimit 7", &workspace)
+        .expect("server set");
+    cli.execute_command("/memory config set searchLimit 3", &workspace)
+        .expect("cli set");
+    let saved: Value = serde_json::from_str(
+        &fs::read_to_string(memory_dir.join("config.json")).expect("read config"),
+    )
+    .expect("saved json");
+    assert_eq!(saved["autoCapture"]["enabled"], json!(false));
+    assert_eq!(saved["listLimit"], json!(7));
+    assert_eq!(saved["searchLimit"], json!(3));
+
+    fs::write(
+        memory_dir.join("config.json"),
+        r#"{"injection": {"maxItems": 2}}"#,
+    )
+    .expect("hand edit");
+    assert!(server.reload_config_if_changed().expect("reload hand edit"));
+    assert_eq!(server.config().injection.max_items, 2);
+    assert_eq!(server.config().list_limit, 50);
+
+    let half_saved = r#"{"injection": {"maxItems": 4"#;
+    fs::write(memory_dir.join("config.json"), half_saved).expect("half-saved edit");
+    assert!(!server.reload_config_if_changed().expect("keep last good"));
+    assert_eq!(server.config().injection.max_items, 2);
+    fs::remove_file(memory_dir.join("config.json")).expect("editor swap");
+    assert!(!server.reload_config_if_changed().expect("keep last good"));
+    assert_eq!(server.config().injection.max_items, 2);
+    assert!(!memory_dir.join("config.json").exists());
+    let names = fs::read_dir(&memory_dir)
+        .expect("read memory dir")
+        .filter_map(Result::ok)
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    assert!(
+        names
+            .iter()
+            .all(|name| !name.starts_with("config.invalid-"))
+    );
+
+    fs::write(
+        memory_dir.join("config.json"),
+        r#"{"injection": {"maxItems": 4}}"#,
+    )
+    .expect("finished edit");
+    assert!(
+        server
+            .reload_config_if_changed()
+            .expect("reload finished edit")
+    );
+    assert_eq!(server.config().injection.max_items, 4);
+}
+
+#[test]
+fn config_migrate_command_upgrades_old_global_files() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    fs::create_dir_all(&memory_dir).expect("memory dir");
+    fs::create_dir_all(&workspace).expect("workspace");
+    let config_path = memory_dir.join("config.json");
+    fs::write(&config_path, r#"{"secrets": "redact"}"#).expect("write v1 config");
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    assert_eq!(
+        serde_json::to_value(service.config().secrets.policy).expect("policy"),
+        json!("redact")
+    );
+
+    let refused = service
+        .execute_command("/memory config set configVersion 1", &workspace)
+        .expect("set version");
+    assert_eq!(refused["ok"], json!(false));
+
+    let dry_run = service
+        .execute_command("/memory config migrate --dry-run", &workspace)
+        .expect("dry run");
+    assert_eq!(data(&dry_run)["migration"]["from_version"], json!(1));
+    assert!(
+        data(&dry_run)["rendered"]
+            .as_str()
+            .expect("rendered")
+            .starts_with("Dry run:")
+    );
+    assert_eq!(
+        fs::read_to_string(&config_path).expect("read config"),
+        r#"{"secrets": "redact"}"#
+    );
+
+    service
+        .execute_command("/memory config set listLimit 9", &workspace)
+        .expect("set on v1 file");
+    let backups = fs::read_dir(&memory_dir)
+        .expect("read memory dir")
+        .filter_map(Result::ok)
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with("config.v1-"))
+        .collect::<Vec<_>>();
+    assert_eq!(backups.len(), 1);
+    let saved: Value =
+        serde_json::from_str(&fs::read_to_string(&config_path).expect("read config"))
+            .expect("saved json");
+    assert_eq!(saved["configVersion"], json!(2));
+    assert_eq!(saved["secrets"]["policy"], json!("redact"));
+    assert_eq!(saved["listLimit"], json!(9));
+
+    let done = service
+        .execute_command("/memory config migrate", &workspace)
+        .expect("migrate");
+    assert!(
+        data(&done)["rendered"]
+            .as_str()
+            .expect("rendered")
+            .contains("already at config version 2")
+    );
+}
+
 #[test]
 fn sync_agents_inserts_and_is_idempotent() {
     let temp = tempfile::tempdir().expect("tempdir");
@@ -137,7 +1064,7 @@ fn capture_candidates_persists_when_enabled() {
     assert_eq!(data(&result).get("added").and_then(Value::as_u64), Some(2));
 
     let listed = service
-        .list_memories(&workspace, Some(20), None)
+        .list_memories(&workspace, Some(20), None, false)
         .expect("list memories");
     let item_count = data(&listed)
         .get("page")
@@ -155,7 +1082,7 @@ fn export_memories_accepts_default_and_relative_workspace_paths() {
     let workspace = temp.path().join("workspace");
     fs::create_dir_all(&workspace).expect("create workspace");
 
-    let service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
 
     let default_export = service
         .export_memories(&workspace, ExportFormat::Json, false, String::new())
@@ -196,7 +1123,7 @@ fn export_memories_rejects_paths_outside_workspace() {
     let workspace = temp.path().join("workspace");
     fs::create_dir_all(&workspace).expect("create workspace");
 
-    let service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
 
     let escaped = service
         .export_memories(
@@ -220,3 +1147,1179 @@ fn export_memories_rejects_paths_outside_workspace() {
     assert_eq!(absolute.get("ok").and_then(Value::as_bool), Some(false));
     assert!(!absolute_path.exists());
 }
+
+#[test]
+fn begin_sync_writes_deterministic_block_and_finish_upgrades_it() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    fs::create_dir_all(&workspace).expect("create workspace");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    for index in 0..12 {
+        service
+            .add_memory(
+                format!("Project convention number {index} for the build"),
+                Some(ScopeTarget::Project),
+                None,
+                &workspace,
+                "test",
+            )
+            .expect("add memory");
+    }
+
+    let (first, stale) = service.begin_sync_agents(&workspace).expect("begin #1");
+    assert!(stale.is_some());
+    assert_eq!(
+        data(&first).pointer("/job/state").and_then(Value::as_str),
+        Some("pending")
+    );
+
+    let (_, pending) = service.begin_sync_agents(&workspace).expect("begin #2");
+    let pending = pending.expect("pending llm compaction");
+    let agents_path = workspace.join("AGENTS.md");
+    let deterministic = fs::read_to_string(&agents_path).expect("read agents");
+    assert!(deterministic.contains("deterministic fallback"));
+
+    let superseded = service
+        .finish_sync_agents(stale.expect("stale job"), Ok(Some("- stale".to_string())))
+        .expect("finish stale");
+    assert_eq!(
+        data(&superseded).get("superseded").and_then(Value::as_bool),
+        Some(true)
+    );
+    assert!(
+        !fs::read_to_string(&agents_path)
+            .expect("read agents")
+            .contains("- stale")
+    );
+
+    service
+        .finish_sync_agents(
+            pending,
+            Ok(Some("- Follow the numbered build conventions".to_string())),
+        )
+        .expect("finish");
+    let upgraded = fs::read_to_string(&agents_path).expect("read agents");
+    assert!(upgraded.contains("- Follow the numbered build conventions"));
+
+    let status = service.sync_status(&workspace).expect("status");
+    assert_eq!(
+        data(&status).pointer("/job/state").and_then(Value::as_str),
+        Some("completed")
+    );
+    assert_eq!(
+        data(&status).pointer("/job/mode").and_then(Value::as_str),
+        Some("llm")
+    );
+}
+
+#[test]
+fn finish_sync_falls_back_when_memories_change_during_the_summary() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    fs::create_dir_all(&workspace).expect("create workspace");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    for index in 0..12 {
+        service
+            .add_memory(
+                format!("Project convention number {index} for the build"),
+                Some(ScopeTarget::Project),
+                None,
+                &workspace,
+                "test",
+            )
+            .expect("add memory");
+    }
+
+    let (_, pending) = service.begin_sync_agents(&workspace).expect("begin");
+    let pending = pending.expect("pending llm compaction");
+    let added = service
+        .execute_command(
+            "/memory add Never force-push the release branch",
+            &workspace,
+        )
+        .expect("add during summary");
+    let id = data(&added)["id"].as_str().expect("id").to_string();
+    service
+        .execute_command(&format!("/memory pin {id} on"), &workspace)
+        .expect("pin");
+
+    let finished = service
+        .finish_sync_agents(
+            pending,
+            Ok(Some("- Follow the numbered build conventions".to_string())),
+        )
+        .expect("finish");
+    assert_eq!(
+        data(&finished).pointer("/job/mode").and_then(Value::as_str),
+        Some("llm_fallback")
+    );
+    assert_eq!(
+        data(&finished)
+            .pointer("/job/reason")
+            .and_then(Value::as_str),
+        Some("memories changed while the summary was running")
+    );
+    let agents = fs::read_to_string(workspace.join("AGENTS.md")).expect("read agents");
+    assert!(!agents.contains("- Follow the numbered build conventions"));
+    assert!(agents.contains("Never force-push the release branch"));
+}
+
+#[test]
+fn auto_status_reports_capture_rules_that_failed_to_compile() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    fs::create_dir_all(&workspace).expect("create workspace");
+    fs::create_dir_all(&memory_dir).expect("create memory dir");
+    fs::write(
+        memory_dir.join("config.json"),
+        r#"{"autoCapture": {"enabled": true, "rules": [
+            {"pattern": "(?i)^team rule:\\s*(.+)$"},
+            {"pattern": "(unclosed", "role": "any"}
+        ]}}"#,
+    )
+    .expect("config");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    let status = service
+        .execute_command("/memory auto status", &workspace)
+        .expect("status");
+    let errors = data(&status)["rule_errors"]
+        .as_array()
+        .expect("rule errors");
+    assert_eq!(errors.len(), 1);
+    assert!(
+        errors[0]
+            .as_str()
+            .is_some_and(|error| error.starts_with("autoCapture.rules[1]: invalid pattern"))
+    );
+    let rendered = data(&status)["rendered"].as_str().expect("rendered");
+    assert!(rendered.contains("2. /(unclosed/ role=any [invalid: invalid pattern"));
+    assert!(!rendered.contains("1. /(?i)^team rule:\\s*(.+)$/ role=user [invalid"));
+
+    let captured = service
+        .capture_candidates(
+            &workspace,
+            json!({"messages": [{"role": "user", "content": "Team rule: squash merge every pull request"}]}),
+            true,
+        )
+        .expect("capture");
+    assert_eq!(
+        data(&captured).get("added").and_then(Value::as_u64),
+        Some(1)
+    );
+}
+
+#[test]
+fn review_mode_queues_captures_until_approved() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    fs::create_dir_all(&workspace).expect("create workspace");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    service
+        .execute_command("/memory auto review", &workspace)
+        .expect("enable review mode");
+
+    let event = json!({
+        "messages": [
+            {"role": "user", "content": "remember that staging deploys go through the release branch"},
+            {"role": "user", "content": "I prefer small focused pull requests"}
+        ]
+    });
+    let captured = service
+        .capture_candidates(&workspace, event, true)
+        .expect("capture");
+    assert_eq!(
+        data(&captured).get("queued").and_then(Value::as_u64),
+        Some(2)
+    );
+    assert_eq!(
+        data(&captured).get("added").and_then(Value::as_u64),
+        Some(0)
+    );
+
+    let search = service
+        .search_memories(&workspace, "release".to_string(), None, None, false)
+        .expect("search");
+    assert!(
+        data(&search)
+            .pointer("/page/items")
+            .and_then(Value::as_array)
+            .expect("items")
+            .is_empty()
+    );
+    let search = service
+        .execute_command("/memory search release --pending", &workspace)
+        .expect("search pending");
+    assert_eq!(
+        data(&search)["page"]["items"][0]["status"],
+        json!("pending")
+    );
+    let listed = service
+        .list_memories(&workspace, None, None, true)
+        .expect("list pending");
+    assert_eq!(
+        data(&listed)["page"]["items"].as_array().map(Vec::len),
+        Some(2)
+    );
+    assert!(
+        data(&listed)["rendered"]
+            .as_str()
+            .expect("rendered")
+            .contains("[pending]")
+    );
+
+    let pending = service
+        .execute_command("/memory review", &workspace)
+        .expect("review list");
+    let items = data(&pending)
+        .pointer("/page/items")
+        .and_then(Value::as_array)
+        .expect("pending items")
+        .clone();
+    assert_eq!(items.len(), 2);
+    let first_id = items[0].get("id").and_then(Value::as_str).expect("id");
+    let second_id = items[1].get("id").and_then(Value::as_str).expect("id");
+
+    service
+        .execute_command(
+            &format!("/memory review category workflow {first_id}"),
+            &workspace,
+        )
+        .expect("recategorize");
+    service
+        .execute_command(&format!("/memory review approve {first_id}"), &workspace)
+        .expect("approve");
+    service
+        .execute_command(&format!("/memory review reject {second_id}"), &workspace)
+        .expect("reject");
+
+    let listed = service
+        .list_memories(&workspace, None, None, false)
+        .expect("list");
+    let items = data(&listed)
+        .pointer("/page/items")
+        .and_then(Value::as_array)
+        .expect("items");
+    assert_eq!(items.len(), 1);
+    assert_eq!(
+        items[0].get("category").and_then(Value::as_str),
+        Some("workflow")
+    );
+
+    let remaining = service
+        .execute_command("/memory review approve --all", &workspace)
+        .expect("approve all");
+    assert_eq!(remaining.get("ok").and_then(Value::as_bool), Some(false));
+}
+
+#[test]
+fn weak_or_implicit_retractions_do_not_delete_memories() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    fs::create_dir_all(&workspace).expect("create workspace");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    for fact in [
+        "Deploys are frozen on Fridays and during release weeks for all services",
+        "Prefer short commit subjects",
+    ] {
+        service
+            .add_memory(fact.to_string(), None, None, &workspace, "user")
+            .expect("add");
+    }
+
+    let event = json!({
+        "messages": [
+            {"role": "user", "content": "We don't deploy on Fridays"},
+            {"role": "user", "content": "We no longer deploy on Fridays"},
+            {"role": "user", "content": "Forget that we prefer short commit subjects"}
+        ]
+    });
+    let captured = service
+        .capture_candidates(&workspace, event, true)
+        .expect("capture");
+    let corrections = data(&captured)["corrections"]
+        .as_array()
+        .expect("corrections");
+    assert_eq!(corrections.len(), 2);
+    assert_eq!(corrections[0]["status"], json!("queued"));
+    assert!(corrections[0]["similarity"].as_f64().expect("similarity") < 0.5);
+    assert_eq!(corrections[1]["status"], json!("retracted"));
+
+    let listed = service
+        .list_memories(&workspace, None, None, false)
+        .expect("list");
+    let contents = data(&listed)
+        .pointer("/page/items")
+        .and_then(Value::as_array)
+        .expect("items")
+        .iter()
+        .filter_map(|item| item.get("content").and_then(Value::as_str))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        contents,
+        vec!["Deploys are frozen on Fridays and during release weeks for all services"]
+    );
+}
+
+#[test]
+fn correction_captures_supersede_and_retract_matching_memories() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    fs::create_dir_all(&workspace).expect("create workspace");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    for fact in [
+        "Use yarn for package installs",
+        "Deploys are frozen on Fridays",
+    ] {
+        service
+            .add_memory(fact.to_string(), None, None, &workspace, "user")
+            .expect("add");
+    }
+
+    let event = json!({
+        "messages": [
+            {"role": "user", "content": "Actually, we switched from yarn to pnpm."}
+        ]
+    });
+    let captured = service
+        .capture_candidates(&workspace, event, true)
+        .expect("capture");
+    assert_eq!(
+        data(&captured).get("superseded").and_then(Value::as_u64),
+        Some(1)
+    );
+    assert_eq!(
+        data(&captured)
+            .pointer("/corrections/0/matched/content")
+            .and_then(Value::as_str),
+        Some("Use yarn for package installs")
+    );
+
+    service
+        .execute_command("/memory auto review", &workspace)
+        .expect("enable review mode");
+    let event = json!({
+        "messages": [
+            {"role": "user", "content": "Forget that deploys are frozen on Fridays"}
+        ]
+    });
+    let captured = service
+        .capture_candidates(&workspace, event, true)
+        .expect("capture");
+    assert_eq!(
+        data(&captured).get("queued").and_then(Value::as_u64),
+        Some(1)
+    );
+    assert_eq!(
+        data(&captured).get("retracted").and_then(Value::as_u64),
+        Some(0)
+    );
+
+    let contents = |service: &mut MemoryService| {
+        let listed = service
+            .list_memories(&workspace, None, None, false)
+            .expect("list");
+        data(&listed)
+            .pointer("/page/items")
+            .and_then(Value::as_array)
+            .expect("items")
+            .iter()
+            .filter_map(|item| item.get("content").and_then(Value::as_str))
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        contents(&mut service),
+        vec![
+            "Use pnpm instead of yarn".to_string(),
+            "Deploys are frozen on Fridays".to_string()
+        ]
+    );
+
+    let approved = service
+        .execute_command("/memory review approve --all", &workspace)
+        .expect("approve");
+    assert_eq!(
+        data(&approved)
+            .pointer("/results/0/result")
+            .and_then(Value::as_str),
+        Some("retracted")
+    );
+    assert_eq!(
+        contents(&mut service),
+        vec!["Use pnpm instead of yarn".to_string()]
+    );
+}
+
+#[test]
+fn low_similarity_replacements_are_queued_instead_of_superseding() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    fs::create_dir_all(&workspace).expect("create workspace");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    service
+        .add_memory(
+            "Package installs on CI go through the yarn cache proxy on the build server"
+                .to_string(),
+            None,
+            None,
+            &workspace,
+            "user",
+        )
+        .expect("add");
+
+    let event = json!({
+        "messages": [
+            {"role": "user", "content": "Actually, we switched from yarn to pnpm."}
+        ]
+    });
+    let captured = service
+        .capture_candidates(&workspace, event, true)
+        .expect("capture");
+    assert_eq!(
+        data(&captured).get("superseded").and_then(Value::as_u64),
+        Some(0)
+    );
+    assert_eq!(
+        data(&captured)
+            .pointer("/corrections/0/status")
+            .and_then(Value::as_str),
+        Some("queued")
+    );
+
+    let listed = service
+        .list_memories(&workspace, None, None, false)
+        .expect("list");
+    assert_eq!(
+        data(&listed)["page"]["items"][0]["content"],
+        json!("Package installs on CI go through the yarn cache proxy on the build server")
+    );
+    let pending = service
+        .execute_command("/memory review", &workspace)
+        .expect("review list");
+    assert_eq!(
+        data(&pending)["page"]["items"][0]["content"],
+        json!("Use pnpm instead of yarn")
+    );
+}
+
+#[test]
+fn failed_approval_keeps_the_queued_correction_and_pending_row() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    fs::create_dir_all(&workspace).expect("create workspace");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    service
+        .add_memory(
+            "Deploys are frozen on Fridays".to_string(),
+            None,
+            None,
+            &workspace,
+            "user",
+        )
+        .expect("add");
+    service
+        .execute_command("/memory auto review", &workspace)
+        .expect("enable review mode");
+    let event = json!({
+        "messages": [
+            {"role": "user", "content": "Forget that deploys are frozen on Fridays"}
+        ]
+    });
+    service
+        .capture_candidates(&workspace, event, true)
+        .expect("capture");
+
+    let conn = rusqlite::Connection::open(memory_dir.join("memory.sqlite")).expect("open db");
+    conn.execute_batch(
+        "
+        CREATE TRIGGER block_retract BEFORE UPDATE OF status ON memories
+        WHEN OLD.status = 'active' AND NEW.status = 'deleted'
+        BEGIN SELECT RAISE(ABORT, 'blocked'); END;
+        ",
+    )
+    .expect("create trigger");
+    assert!(
+        service
+            .execute_command("/memory review approve --all", &workspace)
+            .is_err()
+    );
+
+    let state = |conn: &rusqlite::Connection| -> (i64, i64) {
+        conn.query_row(
+            "SELECT (SELECT COUNT(*) FROM pending_actions), (SELECT COUNT(*) FROM memories WHERE status = 'pending')",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .expect("state")
+    };
+    assert_eq!(state(&conn), (1, 1));
+
+    conn.execute_batch("DROP TRIGGER block_retract")
+        .expect("drop trigger");
+    let approved = service
+        .execute_command("/memory review approve --all", &workspace)
+        .expect("approve");
+    assert_eq!(
+        data(&approved)
+            .pointer("/results/0/result")
+            .and_then(Value::as_str),
+        Some("retracted")
+    );
+    assert_eq!(state(&conn), (0, 0));
+}
+
+#[test]
+fn backfill_previews_then_applies_session_rollouts_once() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    let day_dir = temp.path().join("sessions/2025/06/01");
+    fs::create_dir_all(&workspace).expect("create workspace");
+    fs::create_dir_all(&day_dir).expect("create sessions");
+
+    let lines = [
+        json!({"type": "session_meta", "payload": {"id": "s1", "cwd": workspace.display().to_string()}}),
+        json!({"type": "response_item", "payload": {"type": "message", "role": "user", "content": [{"type": "input_text", "text": "remember that release notes live in docs/releases"}]}}),
+        json!({"type": "response_item", "payload": {"type": "message", "role": "assistant", "content": [{"type": "output_text", "text": "Got it."}]}}),
+    ];
+    fs::write(
+        day_dir.join("rollout-2025-06-01-s1.jsonl"),
+        lines
+            .iter()
+            .map(Value::to_string)
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+    .expect("write rollout");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    let command = format!(
+        "/memory backfill --sessions-dir {}",
+        temp.path().join("sessions").display()
+    );
+
+    let preview = service
+        .execute_command(&command, &workspace)
+        .expect("preview");
+    assert_eq!(
+        data(&preview).get("candidates").and_then(Value::as_u64),
+        Some(1)
+    );
+    let listed = service
+        .list_memories(&workspace, None, None, false)
+        .expect("list");
+    assert!(
+        data(&listed)
+            .pointer("/page/items")
+            .and_then(Value::as_array)
+            .expect("items")
+            .is_empty()
+    );
+
+    let applied = service
+        .execute_command(&format!("{command} --apply"), &workspace)
+        .expect("apply");
+    assert_eq!(
+        data(&applied)
+            .pointer("/totals/added")
+            .and_then(Value::as_u64),
+        Some(1)
+    );
+
+    let again = service
+        .execute_command(&format!("{command} --apply"), &workspace)
+        .expect("rerun");
+    assert_eq!(
+        data(&again)
+            .get("already_processed")
+            .and_then(Value::as_u64),
+        Some(1)
+    );
+    assert_eq!(
+        data(&again).get("candidates").and_then(Value::as_u64),
+        Some(0)
+    );
+}
+
+#[test]
+fn redact_secret_policy_stores_placeholders_for_add_and_capture() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    fs::create_dir_all(&workspace).expect("create workspace");
+    fs::create_dir_all(&memory_dir).expect("create memory dir");
+    fs::write(memory_dir.join("config.json"), r#"{"secrets": "redact"}"#).expect("config");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    let added = service
+        .add_memory(
+            "connect with postgres://user@localhost/db using the dev role".to_string(),
+            None,
+            None,
+            &workspace,
+            "user",
+        )
+        .expect("add");
+    assert_eq!(
+        data(&added).get("content").and_then(Value::as_str),
+        Some("connect with <redacted:postgres-url> using the dev role")
+    );
+    assert_eq!(data(&added).get("redacted"), Some(&json!(["postgres-url"])));
+
+    let event = json!({
+        "messages": [
+            {"role": "user", "content": "remember that staging uses redis://cache.internal:6379/0 for sessions"}
+        ]
+    });
+    let captured = service
+        .capture_candidates(&workspace, event, true)
+        .expect("capture");
+    assert_eq!(
+        data(&captured).get("added").and_then(Value::as_u64),
+        Some(1)
+    );
+    assert_eq!(
+        data(&captured)
+            .pointer("/candidates/0/text")
+            .and_then(Value::as_str),
+        Some("staging uses <redacted:redis-url> for sessions")
+    );
+}
+
+#[test]
+fn secrets_test_command_explains_pii_custom_and_allowlisted_matches() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    fs::create_dir_all(&workspace).expect("create workspace");
+    fs::create_dir_all(&memory_dir).expect("create memory dir");
+    fs::write(
+        memory_dir.join("config.json"),
+        r#"{"secrets": {"pii": true, "patterns": [{"name": "ticket-key", "pattern": "TK-[0-9]{6}"}, {"name": "broken", "pattern": "EMPX-(\\d{6}"}], "allowlist": ["@example\\.com$"]}}"#,
+    )
+    .expect("config");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    let allowed = service
+        .execute_command("/memory secrets test ask dev@example.com", &workspace)
+        .expect("secrets test");
+    assert_eq!(
+        data(&allowed).get("outcome").and_then(Value::as_str),
+        Some("allowed")
+    );
+    assert_eq!(
+        data(&allowed).pointer("/findings/0/allowlisted"),
+        Some(&json!(true))
+    );
+
+    let blocked = service
+        .execute_command(
+            "/memory secrets test mail ops@acme.io with TK-123456",
+            &workspace,
+        )
+        .expect("secrets test");
+    assert_eq!(
+        data(&blocked).get("outcome").and_then(Value::as_str),
+        Some("blocked")
+    );
+    let rendered = data(&blocked)
+        .get("rendered")
+        .and_then(Value::as_str)
+        .expect("rendered");
+    assert!(rendered.contains("- email (pii) at 5..16"));
+    assert!(rendered.contains("- ticket-key (custom) at 22..31"));
+    assert!(rendered.contains("Config errors (these entries are ignored until fixed):"));
+    assert!(rendered.contains("- secrets.patterns[1] (broken): "));
+    assert_eq!(
+        data(&blocked)
+            .get("config_errors")
+            .and_then(Value::as_array)
+            .map(Vec::len),
+        Some(1)
+    );
+
+    let added = service
+        .add_memory(
+            "escalations go to ops@acme.io".to_string(),
+            None,
+            None,
+            &workspace,
+            "user",
+        )
+        .expect("add");
+    assert_eq!(added.get("ok"), Some(&json!(false)));
+}
+
+#[test]
+fn audit_reports_then_redacts_and_deletes_rows_and_event_history() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    fs::create_dir_all(&workspace).expect("create workspace");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    let first = service
+        .add_memory(
+            "escalations go to ops@acme.io during on-call".to_string(),
+            None,
+            None,
+            &workspace,
+            "user",
+        )
+        .expect("add");
+    let first_id = data(&first)["id"].as_str().expect("id").to_string();
+    let second = service
+        .add_memory(
+            "release notes are signed off by qa-lead@acme.io".to_string(),
+            None,
+            None,
+            &workspace,
+            "user",
+        )
+        .expect("add");
+    let second_id = data(&second)["id"].as_str().expect("id").to_string();
+    drop(service);
+
+    let conn = rusqlite::Connection::open(memory_dir.join("memory.sqlite")).expect("open db");
+    conn.execute(
+        "INSERT INTO memory_events (memory_id, action, timestamp, payload) VALUES (?, 'retracted', '2025-01-01T00:00:00Z', ?)",
+        rusqlite::params![first_id, r#"{"phrase":"forget ops@acme.io"}"#],
+    )
+    .expect("insert event");
+    drop(conn);
+    fs::write(
+        memory_dir.join("config.json"),
+        r#"{"secrets": {"pii": true}}"#,
+    )
+    .expect("config");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    let report = service
+        .execute_command("/memory audit --report audit.json", &workspace)
+        .expect("audit");
+    assert_eq!(data(&report)["totals"]["memories_flagged"], json!(2));
+    assert_eq!(data(&report)["totals"]["events_flagged"], json!(1));
+    assert_eq!(data(&report)["by_detector"]["email"], json!(3));
+    assert_eq!(data(&report)["findings"][0]["action"], json!("reported"));
+    let written: Value =
+        serde_json::from_str(&fs::read_to_string(workspace.join("audit.json")).expect("report"))
+            .expect("report json");
+    assert_eq!(written["findings"].as_array().map(Vec::len), Some(3));
+    assert!(!written.to_string().contains("ops@acme.io"));
+
+    let redact = service
+        .execute_command("/memory audit --redact", &workspace)
+        .expect("audit redact");
+    assert_eq!(data(&redact)["totals"]["redacted"], json!(3));
+    let search = service
+        .search_memories(&workspace, "escalations".to_string(), None, None, false)
+        .expect("search");
+    assert_eq!(
+        data(&search)["page"]["items"][0]["content"],
+        json!("escalations go to <redacted:email> during on-call")
+    );
+    let search = service
+        .search_memories(&workspace, "acme".to_string(), None, None, false)
+        .expect("search");
+    assert_eq!(data(&search)["page"]["items"], json!([]));
+    let raw_db = || {
+        let mut raw = fs::read(memory_dir.join("memory.sqlite")).expect("read db");
+        raw.extend(fs::read(memory_dir.join("memory.sqlite-wal")).unwrap_or_default());
+        String::from_utf8_lossy(&raw).into_owned()
+    };
+    assert!(!raw_db().contains("acme.io"));
+    drop(service);
+
+    fs::write(
+        memory_dir.join("config.json"),
+        r#"{"secrets": {"pii": true, "patterns": [{"name": "oncall", "pattern": "on-call"}]}}"#,
+    )
+    .expect("config");
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    let delete = service
+        .execute_command("/memory audit --delete", &workspace)
+        .expect("audit delete");
+    assert_eq!(data(&delete)["totals"]["memories_flagged"], json!(1));
+    assert_eq!(data(&delete)["findings"][0]["action"], json!("deleted"));
+    assert!(!raw_db().contains("escalations"));
+
+    let conn = rusqlite::Connection::open(memory_dir.join("memory.sqlite")).expect("open db");
+    let remaining: Vec<String> = conn
+        .prepare("SELECT id FROM memories")
+        .expect("prepare")
+        .query_map([], |row| row.get(0))
+        .expect("query")
+        .collect::<rusqlite::Result<_>>()
+        .expect("rows");
+    assert_eq!(remaining, vec![second_id]);
+    let history: Vec<String> = conn
+        .prepare("SELECT action FROM memory_events WHERE memory_id = ?")
+        .expect("prepare")
+        .query_map(rusqlite::params![first_id], |row| row.get(0))
+        .expect("query")
+        .collect::<rusqlite::Result<_>>()
+        .expect("rows");
+    assert_eq!(history, vec!["audit_purged"]);
+    let leaked: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM memory_events WHERE payload LIKE '%acme.io%'",
+            [],
+            |row| row.get(0),
+        )
+        .expect("count");
+    assert_eq!(leaked, 0);
+}
+
+#[test]
+fn failed_decrypt_rolls_back_rows_and_key_metadata_together() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    fs::create_dir_all(&workspace).expect("create workspace");
+    fs::create_dir_all(&memory_dir).expect("create memory dir");
+    fs::write(memory_dir.join("memory.key"), "07".repeat(32)).expect("key file");
+    fs::write(
+        memory_dir.join("config.json"),
+        r#"{"encryption": {"keyFile": "memory.key"}}"#,
+    )
+    .expect("config");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    for fact in ["Deploys go through staging", "Release tags are signed"] {
+        service
+            .add_memory(fact.to_string(), None, None, &workspace, "user")
+            .expect("add");
+    }
+    service
+        .execute_command("/memory encrypt", &workspace)
+        .expect("encrypt");
+    drop(service);
+
+    let conn = rusqlite::Connection::open(memory_dir.join("memory.sqlite")).expect("open db");
+    let meta_count = |conn: &rusqlite::Connection| -> i64 {
+        conn.query_row(
+            "SELECT COUNT(*) FROM store_meta WHERE key IN ('encryption', 'kdf', 'key_check')",
+            [],
+            |row| row.get(0),
+        )
+        .expect("meta count")
+    };
+    assert_eq!(meta_count(&conn), 3);
+    conn.execute(
+        "UPDATE memories SET content = 'enc:v1:broken' WHERE content = (SELECT MAX(content) FROM memories)",
+        [],
+    )
+    .expect("corrupt one row");
+    drop(conn);
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("reopen");
+    assert!(
+        service
+            .execute_command("/memory decrypt", &workspace)
+            .is_err()
+    );
+    drop(service);
+
+    let conn = rusqlite::Connection::open(memory_dir.join("memory.sqlite")).expect("open db");
+    assert_eq!(meta_count(&conn), 3);
+    let plaintext: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM memories WHERE content NOT LIKE 'enc:v1:%'",
+            [],
+            |row| row.get(0),
+        )
+        .expect("plaintext count");
+    assert_eq!(plaintext, 0);
+}
+
+#[test]
+fn encrypt_scrubs_plaintext_from_the_database_file_and_wal() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    fs::create_dir_all(&workspace).expect("create workspace");
+    fs::create_dir_all(&memory_dir).expect("create memory dir");
+    fs::write(memory_dir.join("memory.key"), "07".repeat(32)).expect("key file");
+    fs::write(
+        memory_dir.join("config.json"),
+        r#"{"encryption": {"keyFile": "memory.key"}}"#,
+    )
+    .expect("config");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    for fact in [
+        "Deploys go through the zanzibar staging cluster",
+        "Release tags are signed with the quokka team key",
+    ] {
+        service
+            .add_memory(fact.to_string(), None, None, &workspace, "user")
+            .expect("add");
+    }
+    service.sync_agents(&workspace).expect("sync");
+    service
+        .execute_command("/memory encrypt", &workspace)
+        .expect("encrypt");
+
+    let db_path = memory_dir.join("memory.sqlite");
+    let mut raw = fs::read(&db_path).expect("read db");
+    raw.extend(fs::read(memory_dir.join("memory.sqlite-wal")).unwrap_or_default());
+    let raw = String::from_utf8_lossy(&raw);
+    for needle in ["zanzibar", "quokka", &workspace.display().to_string()] {
+        assert!(!raw.contains(needle), "plaintext {needle} left on disk");
+    }
+    drop(service);
+
+    let conn = rusqlite::Connection::open(&db_path).expect("open db");
+    let sealed: Vec<String> = conn
+        .prepare("SELECT details FROM memory_compactions")
+        .expect("prepare")
+        .query_map([], |row| row.get(0))
+        .expect("query")
+        .collect::<rusqlite::Result<_>>()
+        .expect("rows");
+    assert!(!sealed.is_empty());
+    assert!(sealed.iter().all(|details| details.starts_with("enc:v1:")));
+}
+
+#[test]
+fn encrypt_and_decrypt_commands_migrate_content_and_keep_search_working() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    fs::create_dir_all(&workspace).expect("create workspace");
+    fs::create_dir_all(&memory_dir).expect("create memory dir");
+    fs::write(memory_dir.join("memory.key"), "07".repeat(32)).expect("key file");
+    fs::write(
+        memory_dir.join("config.json"),
+        r#"{"encryption": {"keyFile": "memory.key"}}"#,
+    )
+    .expect("config");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("service");
+    service
+        .add_memory(
+            "Deploys go through the staging cluster first".to_string(),
+            None,
+            None,
+            &workspace,
+            "user",
+        )
+        .expect("add");
+
+    let encrypted = service
+        .execute_command("/memory encrypt", &workspace)
+        .expect("encrypt");
+    assert_eq!(data(&encrypted)["memories"], json!(1));
+    let again = service
+        .execute_command("/memory encrypt", &workspace)
+        .expect("encrypt twice");
+    assert_eq!(again.get("ok"), Some(&json!(false)));
+
+    service
+        .add_memory(
+            "Release tags are signed with the team key".to_string(),
+            None,
+            None,
+            &workspace,
+            "user",
+        )
+        .expect("add encrypted");
+    let deduped = service
+        .add_memory(
+            "Release tags are signed with the team key".to_string(),
+            None,
+            None,
+            &workspace,
+            "user",
+        )
+        .expect("dedupe encrypted");
+    assert_eq!(data(&deduped)["result"], json!("deduped"));
+    drop(service);
+
+    let conn = rusqlite::Connection::open(memory_dir.join("memory.sqlite")).expect("open db");
+    let leaked: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM memories WHERE content LIKE '%staging%' OR content LIKE '%Release%' OR content NOT LIKE 'enc:v1:%'",
+            [],
+            |row| row.get(0),
+        )
+        .expect("count");
+    assert_eq!(leaked, 0);
+    let indexed: i64 = conn
+        .query_row("SELECT COUNT(*) FROM memories_fts", [], |row| row.get(0))
+        .expect("fts count");
+    assert_eq!(indexed, 0);
+    drop(conn);
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("reopen");
+    let search = service
+        .search_memories(&workspace, "staging cluster".to_string(), None, None, false)
+        .expect("search");
+    assert_eq!(
+        data(&search)["page"]["items"][0]["content"],
+        json!("Deploys go through the staging cluster first")
+    );
+    let stats = service.stats(&workspace).expect("stats");
+    assert_eq!(data(&stats)["stats"]["encrypted"], json!(true));
+    drop(service);
+
+    fs::write(memory_dir.join("memory.key"), "08".repeat(32)).expect("wrong key");
+    assert!(MemoryService::new_with_memory_dir(&memory_dir).is_err());
+    fs::write(memory_dir.join("config.json"), "{}").expect("no key");
+    assert!(MemoryService::new_with_memory_dir(&memory_dir).is_err());
+    fs::write(memory_dir.join("memory.key"), "07".repeat(32)).expect("key file");
+    fs::write(
+        memory_dir.join("config.json"),
+        r#"{"encryption": {"keyFile": "memory.key"}}"#,
+    )
+    .expect("config");
+
+    let mut service = MemoryService::new_with_memory_dir(&memory_dir).expect("reopen");
+    let decrypted = service
+        .execute_command("/memory decrypt", &workspace)
+        .expect("decrypt");
+    assert_eq!(data(&decrypted)["memories"], json!(2));
+    let stats = service.stats(&workspace).expect("stats");
+    assert_eq!(data(&stats)["stats"]["encrypted"], json!(false));
+    assert_eq!(data(&stats)["stats"]["has_fts"], json!(true));
+    let search = service
+        .search_memories(&workspace, "release".to_string(), None, None, false)
+        .expect("search");
+    assert_eq!(
+        data(&search)["page"]["items"].as_array().map(Vec::len),
+        Some(1)
+    );
+}
+
+#[test]
+fn opening_v3_database_widens_status_check_for_pending() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    fs::create_dir_all(&memory_dir).expect("create memory dir");
+
+    let conn = rusqlite::Connection::open(memory_dir.join("memory.sqlite")).expect("open db");
+    conn.execute_batch(
+        "
+        CREATE TABLE schema_migrations (version INTEGER PRIMARY KEY, applied_at TEXT NOT NULL);
+        INSERT INTO schema_migrations VALUES (1, 'x'), (2, 'x'), (3, 'x');
+        CREATE TABLE memories (
+          id TEXT PRIMARY KEY,
+          scope TEXT NOT NULL,
+          category TEXT NOT NULL DEFAULT 'other',
+          content TEXT NOT NULL,
+          content_hash TEXT NOT NULL,
+          status TEXT NOT NULL DEFAULT 'active' CHECK(status IN ('active', 'deleted', 'superseded')),
+          pinned INTEGER NOT NULL DEFAULT 0,
+          source TEXT NOT NULL DEFAULT 'user',
+          created_at TEXT NOT NULL,
+          updated_at TEXT NOT NULL
+        );
+        CREATE TABLE memory_events (
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          memory_id TEXT NOT NULL,
+          action TEXT NOT NULL,
+          timestamp TEXT NOT NULL,
+          payload TEXT
+        );
+        CREATE TABLE memory_compactions (
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          scope TEXT NOT NULL,
+          mode TEXT NOT NULL,
+          input_chars INTEGER NOT NULL,
+          output_chars INTEGER NOT NULL,
+          source_count INTEGER NOT NULL,
+          model TEXT,
+          reason TEXT,
+          details TEXT,
+          created_at TEXT NOT NULL
+        );
+        INSERT INTO memories VALUES
+          ('11111111-1111-4111-8111-111111111111', 'global', 'fact', 'Legacy memory', 'h',
+           'active', 0, 'user', '2025-01-01T00:00:00Z', '2025-01-01T00:00:00Z');
+        ",
+    )
+    .expect("seed v3 schema");
+    drop(conn);
+
+    let mut store = MemoryStore::open(&memory_dir.join("memory.sqlite")).expect("migrate store");
+    let added = store
+        .add_pending_memory(AddMemoryInput {
+            scope: "global".to_string(),
+            category: MemoryCategory::Fact,
+            content: "Queued for review".to_string(),
+            source: "auto".to_string(),
+        })
+        .expect("add pending");
+    assert!(matches!(added, AddMemoryResult::Added { .. }));
+
+    let stats = store.get_stats(&["global".to_string()]).expect("stats");
+    assert_eq!(stats.active, 1);
+    assert_eq!(stats.pending, 1);
+}
+
+#[test]
+fn processed_capture_hashes_survive_restart_and_stay_bounded() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let memory_dir = temp.path().join("memory");
+    let workspace = temp.path().join("workspace");
+    fs::create_dir_all(&workspace).expect("create workspace");
+
+    let event = json!({
+        "messages": [
+            {"role": "user", "content": "remember that the api crate owns request validation"}
+        ]
+    });
+
+    let mut first = MemoryService::new_with_memory_dir(&memory_dir).expect("service #1");
+    let captured = first
+        .capture_candidates(&workspace, event.clone(), true)
+        .expect("capture #1");
+    assert_eq!(
+        data(&captured).get("added").and_then(Value::as_u64),
+        Some(1)
+    );
+    drop(first);
+
+    let mut second = MemoryService::new_with_memory_dir(&memory_dir).expect("service #2");
+    let recaptured = second
+        .capture_candidates(&workspace, event, true)
+        .expect("capture #2");
+    assert!(
+        data(&recaptured)
+            .get("candidates")
+            .and_then(Value::as_array)
+            .expect("candidates")
+            .is_empty()
+    );
+    let stats = second.stats(&workspace).expect("stats");
+    assert_eq!(
+        data(&stats)
+            .pointer("/stats/processed_hashes")
+            .and_then(Value::as_i64),
+        Some(1)
+    );
+
+    let mut store = MemoryStore::open(&memory_dir.join("memory.sqlite")).expect("store");
+    for hash in ["a", "b", "c"] {
+        store.track_processed_hash(hash, 2).expect("track hash");
+    }
+    let hashes = store.load_processed_hashes().expect("load hashes");
+    assert_eq!(hashes.len(), 2);
+    assert!(hashes.contains("b") && hashes.contains("c"));
+}
@@ -278,11 +278,16 @@ fn configure_mcp_server(
         "memory_search",
         "memory_delete",
         "memory_pin",
+        "memory_move",
+        "memory_copy",
         "memory_auto",
+        "memory_config",
+        "memory_review",
         "memory_stats",
         "memory_export",
         "memory_refresh",
         "memory_sync_agents",
+        "memory_sync_status",
         "memory_capture_candidates",
     ] {
         enabled_tools.push(tool);
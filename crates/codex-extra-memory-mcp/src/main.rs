@@ -1,6 +1,11 @@
 use anyhow::{Result, anyhow};
 use clap::Parser;
-use codex_extra_memory_core::commands::{AutoMode, ExportFormat};
+use codex_extra_memory_core::commands::{
+    AutoMode, ExportFormat, MemoryCommand, parse_memory_command,
+};
+use codex_extra_memory_core::llm::{
+    classify_capture_with_llm_async, summarize_memories_with_llm_async,
+};
 use codex_extra_memory_core::service::MemoryService;
 use mcpkit::prelude::*;
 use mcpkit::transport::stdio::StdioTransport;
@@ -68,21 +73,75 @@ fn resolve_workspace(base: &Path, cwd: Option<String>) -> Result<PathBuf> {
     }
 }
 
-async fn with_service_blocking<F>(app: Arc<App>, f: F) -> Result<Value>
+fn reload_config(service: &mut MemoryService) -> &mut MemoryService {
+    if let Err(error) = service.reload_config_if_changed() {
+        eprintln!("codex-extra-memory: config reload failed: {error}");
+    }
+    service
+}
+
+async fn with_service_blocking<F, T>(app: Arc<App>, f: F) -> Result<T>
 where
-    F: FnOnce(&mut MemoryService) -> Result<Value> + Send + 'static,
+    F: FnOnce(&mut MemoryService) -> Result<T> + Send + 'static,
+    T: Send + 'static,
 {
     tokio::task::spawn_blocking(move || {
         let mut guard = app
             .service
             .lock()
             .map_err(|_| anyhow!("memory service mutex poisoned"))?;
-        f(&mut guard)
+        f(reload_config(&mut guard))
     })
     .await
     .map_err(|error| anyhow!("memory task join failure: {error}"))?
 }
 
+async fn start_sync_agents(app: Arc<App>, workspace: PathBuf) -> Result<Value> {
+    let (response, pending) = with_service_blocking(Arc::clone(&app), move |service| {
+        service.begin_sync_agents(&workspace)
+    })
+    .await?;
+
+    if let Some(pending) = pending {
+        tokio::spawn(async move {
+            let outcome = summarize_memories_with_llm_async(&pending.rows, &pending.request).await;
+            if let Err(error) = with_service_blocking(app, move |service| {
+                service.finish_sync_agents(pending, outcome)
+            })
+            .await
+            {
+                eprintln!("codex-extra-memory: background compaction failed: {error}");
+            }
+        });
+    }
+
+    Ok(response)
+}
+
+async fn capture_candidates(
+    app: Arc<App>,
+    workspace: PathBuf,
+    event_payload: Value,
+    persist: bool,
+) -> Result<Value> {
+    let plan_payload = event_payload.clone();
+    let plan_workspace = workspace.clone();
+    let request = with_service_blocking(Arc::clone(&app), move |service| {
+        Ok(service.llm_capture_request(&plan_workspace, &plan_payload))
+    })
+    .await?;
+
+    let llm_outcome = match request {
+        Some(request) => Some(classify_capture_with_llm_async(&request).await),
+        None => None,
+    };
+
+    with_service_blocking(app, move |service| {
+        service.capture_candidates_with_llm(&workspace, event_payload, persist, llm_outcome)
+    })
+    .await
+}
+
 fn to_tool_output(result: Result<Value>) -> ToolOutput {
     match result {
         Ok(value) => match serde_json::to_string_pretty(&value) {
@@ -129,10 +188,14 @@ impl CodexExtraMemoryMcp {
             Err(error) => return ToolOutput::error(error.to_string()),
         };
         let app = Arc::clone(&self.app);
-        let result = with_service_blocking(app, move |service| {
-            service.execute_command(&input, &workspace)
-        })
-        .await;
+        let result = if matches!(parse_memory_command(&input), Ok(MemoryCommand::Sync)) {
+            start_sync_agents(app, workspace).await
+        } else {
+            with_service_blocking(app, move |service| {
+                service.execute_command(&input, &workspace)
+            })
+            .await
+        };
         to_tool_output(result.map(|data| {
             if let Some(session_id) = session_id {
                 wrap_memory_command_result(data, session_id)
@@ -163,11 +226,14 @@ impl CodexExtraMemoryMcp {
         )
     }
 
-    #[tool(description = "List memories with cursor pagination")]
+    #[tool(
+        description = "List memories with cursor pagination; include_pending adds review-queue items"
+    )]
     async fn memory_list(
         &self,
         limit: Option<usize>,
         cursor: Option<String>,
+        include_pending: Option<bool>,
         cwd: Option<String>,
     ) -> ToolOutput {
         let workspace = match resolve_workspace(&self.app.workspace, cwd) {
@@ -177,18 +243,21 @@ impl CodexExtraMemoryMcp {
         let app = Arc::clone(&self.app);
         to_tool_output(
             with_service_blocking(app, move |service| {
-                service.list_memories(&workspace, limit, cursor)
+                service.list_memories(&workspace, limit, cursor, include_pending.unwrap_or(false))
             })
             .await,
         )
     }
 
-    #[tool(description = "Search memories with cursor pagination")]
+    #[tool(
+        description = "Search memories with cursor pagination; include_pending adds review-queue items"
+    )]
     async fn memory_search(
         &self,
         query: String,
         limit: Option<usize>,
         cursor: Option<String>,
+        include_pending: Option<bool>,
         cwd: Option<String>,
     ) -> ToolOutput {
         let workspace = match resolve_workspace(&self.app.workspace, cwd) {
@@ -198,7 +267,13 @@ impl CodexExtraMemoryMcp {
         let app = Arc::clone(&self.app);
         to_tool_output(
             with_service_blocking(app, move |service| {
-                service.search_memories(&workspace, query, limit, cursor)
+                service.search_memories(
+                    &workspace,
+                    query,
+                    limit,
+                    cursor,
+                    include_pending.unwrap_or(false),
+                )
             })
             .await,
         )
@@ -239,14 +314,84 @@ impl CodexExtraMemoryMcp {
         )
     }
 
-    #[tool(description = "Auto-capture mode (on/off/status)")]
+    #[tool(
+        description = "Move a memory to another scope (global, org, project, package, branch, or a scope id), keeping its id, pin and history"
+    )]
+    async fn memory_move(
+        &self,
+        id_or_prefix: String,
+        to: String,
+        cwd: Option<String>,
+    ) -> ToolOutput {
+        let workspace = match resolve_workspace(&self.app.workspace, cwd) {
+            Ok(workspace) => workspace,
+            Err(error) => return ToolOutput::error(error.to_string()),
+        };
+        let app = Arc::clone(&self.app);
+        to_tool_output(
+            with_service_blocking(app, move |service| {
+                service.memory_transfer_typed(&workspace, id_or_prefix, to, false)
+            })
+            .await,
+        )
+    }
+
+    #[tool(
+        description = "Copy a memory into another scope (global, org, project, package, branch, or a scope id)"
+    )]
+    async fn memory_copy(
+        &self,
+        id_or_prefix: String,
+        to: String,
+        cwd: Option<String>,
+    ) -> ToolOutput {
+        let workspace = match resolve_workspace(&self.app.workspace, cwd) {
+            Ok(workspace) => workspace,
+            Err(error) => return ToolOutput::error(error.to_string()),
+        };
+        let app = Arc::clone(&self.app);
+        to_tool_output(
+            with_service_blocking(app, move |service| {
+                service.memory_transfer_typed(&workspace, id_or_prefix, to, true)
+            })
+            .await,
+        )
+    }
+
+    #[tool(
+        description = "Show, get, set, or unset memory config values, or upgrade the config file (action: show|get|set|unset|schema|migrate; dry_run for migrate)"
+    )]
+    async fn memory_config(
+        &self,
+        action: Option<String>,
+        key: Option<String>,
+        value: Option<String>,
+        origin: Option<bool>,
+        dry_run: Option<bool>,
+        cwd: Option<String>,
+    ) -> ToolOutput {
+        let workspace = match resolve_workspace(&self.app.workspace, cwd) {
+            Ok(workspace) => workspace,
+            Err(error) => return ToolOutput::error(error.to_string()),
+        };
+        let app = Arc::clone(&self.app);
+        to_tool_output(
+            with_service_blocking(app, move |service| {
+                service.memory_config_typed(&workspace, action, key, value, origin, dry_run)
+            })
+            .await,
+        )
+    }
+
+    #[tool(description = "Auto-capture mode (on/off/review/status)")]
     async fn memory_auto(&self, mode: String) -> ToolOutput {
         let parsed = match mode.to_lowercase().as_str() {
             "on" => AutoMode::On,
             "off" => AutoMode::Off,
+            "review" => AutoMode::Review,
             "status" | "" => AutoMode::Status,
             _ => {
-                return ToolOutput::error("mode must be one of: on, off, status");
+                return ToolOutput::error("mode must be one of: on, off, review, status");
             }
         };
 
@@ -256,6 +401,35 @@ impl CodexExtraMemoryMcp {
         )
     }
 
+    #[tool(
+        description = "Review pending auto-captured memories: list, approve, reject, edit or category"
+    )]
+    async fn memory_review(
+        &self,
+        action: Option<String>,
+        ids: Option<Vec<String>>,
+        all: Option<bool>,
+        text: Option<String>,
+        category: Option<String>,
+        limit: Option<usize>,
+        cursor: Option<String>,
+        cwd: Option<String>,
+    ) -> ToolOutput {
+        let workspace = match resolve_workspace(&self.app.workspace, cwd) {
+            Ok(workspace) => workspace,
+            Err(error) => return ToolOutput::error(error.to_string()),
+        };
+        let app = Arc::clone(&self.app);
+        to_tool_output(
+            with_service_blocking(app, move |service| {
+                service.memory_review_typed(
+                    &workspace, action, ids, all, text, category, limit, cursor,
+                )
+            })
+            .await,
+        )
+    }
+
     #[tool(description = "Get memory stats")]
     async fn memory_stats(&self, cwd: Option<String>) -> ToolOutput {
         let workspace = match resolve_workspace(&self.app.workspace, cwd) {
@@ -306,13 +480,22 @@ impl CodexExtraMemoryMcp {
 
     #[tool(description = "Sync managed memory block into workspace AGENTS.md")]
     async fn memory_sync_agents(&self, cwd: Option<String>) -> ToolOutput {
+        let workspace = match resolve_workspace(&self.app.workspace, cwd) {
+            Ok(workspace) => workspace,
+            Err(error) => return ToolOutput::error(error.to_string()),
+        };
+        to_tool_output(start_sync_agents(Arc::clone(&self.app), workspace).await)
+    }
+
+    #[tool(description = "Report the status of the latest AGENTS.md sync and its LLM compaction")]
+    async fn memory_sync_status(&self, cwd: Option<String>) -> ToolOutput {
         let workspace = match resolve_workspace(&self.app.workspace, cwd) {
             Ok(workspace) => workspace,
             Err(error) => return ToolOutput::error(error.to_string()),
         };
         let app = Arc::clone(&self.app);
         to_tool_output(
-            with_service_blocking(app, move |service| service.sync_agents(&workspace)).await,
+            with_service_blocking(app, move |service| service.sync_status(&workspace)).await,
         )
     }
 
@@ -329,11 +512,13 @@ impl CodexExtraMemoryMcp {
             Ok(workspace) => workspace,
             Err(error) => return ToolOutput::error(error.to_string()),
         };
-        let app = Arc::clone(&self.app);
         to_tool_output(
-            with_service_blocking(app, move |service| {
-                service.capture_candidates(&workspace, event_payload, persist.unwrap_or(true))
-            })
+            capture_candidates(
+                Arc::clone(&self.app),
+                workspace,
+                event_payload,
+                persist.unwrap_or(true),
+            )
             .await,
         )
     }